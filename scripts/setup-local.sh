@@ -68,7 +68,7 @@ case $choice in
         echo "Press Ctrl+C to stop"
         echo "════════════════════════════════════════"
         echo ""
-        cargo run --release
+        cargo run --release -- --dev
         ;;
 
     2)
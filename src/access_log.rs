@@ -0,0 +1,150 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! One structured log line per request, for capacity planning: method, path,
+//! status, the `EnclaveError` code when the request failed, request and
+//! response sizes, and latency. Runs inside the request span, so each line
+//! also carries the `request_id`.
+
+use crate::auth::API_KEY_HEADER;
+use axum::body::HttpBody;
+use axum::extract::Request;
+use axum::http::HeaderMap;
+use axum::middleware::Next;
+use axum::response::Response;
+use std::time::Instant;
+use tracing::{debug, info};
+
+/// Headers whose values never reach the logs.
+pub const REDACTED_HEADERS: &[&str] = &[API_KEY_HEADER, "authorization", "cookie"];
+
+/// Response extension naming the `EnclaveError` code a response was built from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorCode(pub &'static str);
+
+/// `headers` as `name: value` pairs, with credentials replaced by `<redacted>`.
+pub fn redacted_headers(headers: &HeaderMap) -> String {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if REDACTED_HEADERS.contains(&name.as_str()) {
+                "<redacted>"
+            } else {
+                value.to_str().unwrap_or("<binary>")
+            };
+            format!("{}: {}", name, value)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Body size when known up front, `-` for streamed bodies.
+fn body_size(size: Option<u64>) -> String {
+    size.map_or_else(|| "-".to_string(), |size| size.to_string())
+}
+
+/// Middleware logging a start event (at debug, with redacted headers) and a
+/// finish event for every request.
+pub async fn access_log(request: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let request_bytes = body_size(request.body().size_hint().exact());
+    debug!(
+        method = %method,
+        path = %path,
+        request_bytes = %request_bytes,
+        headers = %redacted_headers(request.headers()),
+        "request started"
+    );
+
+    let response = next.run(request).await;
+
+    let error_code = response.extensions().get::<ErrorCode>().map_or("-", |code| code.0);
+    info!(
+        method = %method,
+        path = %path,
+        status = response.status().as_u16(),
+        error_code,
+        request_bytes = %request_bytes,
+        response_bytes = %body_size(response.body().size_hint().exact()),
+        latency_ms = started.elapsed().as_millis() as u64,
+        "request finished"
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EnclaveError;
+    use axum::body::Body;
+    use axum::http::header::AUTHORIZATION;
+    use axum::http::HeaderValue;
+    use axum::response::IntoResponse;
+    use axum::routing::get;
+    use axum::{middleware, Router};
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+    use tower::ServiceExt;
+
+    /// Log sink shared with the test subscriber.
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_credentials_are_redacted() {
+        let mut headers = HeaderMap::new();
+        headers.insert(API_KEY_HEADER, HeaderValue::from_static("secret-key"));
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer secret-key"));
+        headers.insert("content-type", HeaderValue::from_static("application/json"));
+
+        let logged = redacted_headers(&headers);
+        assert!(!logged.contains("secret-key"));
+        assert!(logged.contains("x-api-key: <redacted>"));
+        assert!(logged.contains("content-type: application/json"));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_finish_event_carries_status_code_and_sizes() {
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let app = Router::new()
+            .route(
+                "/fail",
+                get(|| async { EnclaveError::HashMismatch("mismatch".into()).into_response() }),
+            )
+            .layer(middleware::from_fn(access_log));
+        let request = axum::http::Request::get("/fail")
+            .header(API_KEY_HEADER, "secret-key")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.extensions().get::<ErrorCode>(), Some(&ErrorCode("hash_mismatch")));
+
+        let logs = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        let line = logs.lines().find(|line| line.contains("request finished")).unwrap();
+        assert!(line.contains("path=/fail"));
+        assert!(line.contains("status=422"));
+        assert!(line.contains("error_code=\"hash_mismatch\""));
+        assert!(line.contains("request_bytes=0"));
+        assert!(line.contains("latency_ms="));
+        assert!(!logs.contains("secret-key"));
+    }
+}
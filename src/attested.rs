@@ -0,0 +1,237 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! One signature binding a verified dataset to the enclave's attestation:
+//! "this enclave, with this measurement, verified this dataset".
+//!
+//! `/verify_metadata_attested` runs every `/verify_metadata` check, requests
+//! a fresh attestation document and signs an `AttestedVerification` holding
+//! the metadata and the SHA-256 of that document under
+//! `IntentScope::AttestedMetadata`. The document is returned alongside, so a
+//! verifier can check its PCRs and public key and that it hashes to the
+//! signed digest. Without an NSM (with `ALLOW_MOCK_ATTESTATION`), the digest
+//! is of the mock document, which is marked `mock`.
+//!
+//! A Move verifier rebuilds the signed bytes with
+//! `bcs::to_bytes(&intent_message)`, which is:
+//! - the scope byte `0x07`
+//! - `timestamp_ms`, the enclave time of signing, as a little-endian u64
+//! - the BCS of the `DatasetVerification`, as `/verify_metadata` signs it
+//! - `attestation_digest` as a ULEB128 length (`0x20`) and its 32 bytes
+
+use crate::common::{
+    request_attestation, to_signed_response, IntentMessage, IntentScope, ProcessedDataResponse,
+    SigningQuery,
+};
+use crate::{
+    check_client_metadata, webhook, AppState, DatasetVerification, EnclaveError,
+    MetadataVerificationRequest,
+};
+use axum::extract::{Query, State};
+use axum::Json;
+use fastcrypto::encoding::{Encoding, Hex};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tracing::info;
+
+/// Inner type for IntentMessage<T> binding a `DatasetVerification` to the attestation of
+/// the enclave signing it - MUST match Move contract exactly
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AttestedVerification {
+    pub verification: DatasetVerification,
+    /// SHA-256 of the attestation document returned with the signature
+    pub attestation_digest: Vec<u8>,
+}
+
+/// The signed `AttestedVerification` and the attestation document it commits to.
+#[derive(Serialize, Deserialize)]
+pub struct AttestedMetadataResponse {
+    #[serde(flatten)]
+    pub signed: ProcessedDataResponse<IntentMessage<AttestedVerification>>,
+    /// Hex of the attestation document `attestation_digest` is the hash of
+    pub attestation: String,
+    /// True when the document is a dev-server mock rather than a real NSM attestation
+    pub mock: bool,
+}
+
+/// Endpoint that verifies metadata like `/verify_metadata` and signs it
+/// together with the digest of a fresh attestation document.
+pub async fn verify_metadata_attested(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SigningQuery>,
+    Json(request): Json<MetadataVerificationRequest>,
+) -> Result<Json<AttestedMetadataResponse>, EnclaveError> {
+    let started = tokio::time::Instant::now();
+    let result = sign_attested(&state, request, &query);
+    state.pad_rejection(started, &result).await;
+    state.metrics.record("verify_metadata_attested", &result);
+    if let Ok(Json(response)) = &result {
+        let message = &response.signed.response;
+        let verification = message.data.verification.clone();
+        let signed = IntentMessage::new(verification, message.timestamp_ms, message.intent);
+        state.audit_log.record("verify_metadata_attested", &signed);
+    }
+    webhook::notify(&state, "verify_metadata_attested", &result);
+    result
+}
+
+fn sign_attested(
+    state: &AppState,
+    request: MetadataVerificationRequest,
+    query: &SigningQuery,
+) -> Result<Json<AttestedMetadataResponse>, EnclaveError> {
+    if request.intent_scope.is_some() {
+        return Err(EnclaveError::InvalidInput(
+            "intent_scope is not accepted; attested metadata is always signed under \
+             attested_metadata"
+                .to_string(),
+        ));
+    }
+    // Before dedup and policy binding are recorded, so a failed attestation
+    // leaves the hash free for the retry
+    let attestation = request_attestation(state, None)?;
+    let verification = check_client_metadata(state, request.metadata)?;
+    let attestation_digest = Sha256::digest(&attestation.document).to_vec();
+    info!("Signing metadata bound to attestation {}", Hex::encode(&attestation_digest));
+
+    let signed = to_signed_response(
+        state.signer.current().as_ref(),
+        AttestedVerification { verification, attestation_digest },
+        state.clock.now_millis()?,
+        IntentScope::AttestedMetadata,
+    );
+    Ok(Json(AttestedMetadataResponse {
+        signed: query.apply(&state.signer, signed)?,
+        attestation: Hex::encode(&attestation.document),
+        mock: attestation.mock,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::common::signing_bytes;
+    use crate::{dedup, DATASET_VERIFICATION_VERSION, SUI_ADDRESS_LENGTH};
+    use fastcrypto::ed25519::Ed25519KeyPair;
+    use fastcrypto::traits::KeyPair;
+
+    const NOW_MS: u64 = 1_700_000_000_000;
+
+    fn test_state() -> AppState {
+        let mut state = AppState::new(
+            Ed25519KeyPair::generate(&mut rand::thread_rng()),
+            "test-key".to_string(),
+        );
+        state.allow_mock_attestation = true;
+        state.clock = Box::new(ManualClock::new(NOW_MS));
+        state
+    }
+
+    fn no_query() -> SigningQuery {
+        SigningQuery::default()
+    }
+
+    fn signed_bytes_query() -> SigningQuery {
+        SigningQuery { include_signed_bytes: true, ..Default::default() }
+    }
+
+    fn request(intent_scope: Option<&str>) -> MetadataVerificationRequest {
+        let metadata = DatasetVerification {
+            version: DATASET_VERIFICATION_VERSION,
+            dataset_id: b"dataset-1".to_vec(),
+            name: b"data.csv".to_vec(),
+            description: b"Attested".to_vec(),
+            format: b"CSV".to_vec(),
+            size: 1024,
+            original_hash: vec![0xAB; 32],
+            walrus_blob_id: b"blob-1".to_vec(),
+            seal_policy_id: b"policy-1".to_vec(),
+            timestamp: NOW_MS,
+            uploader: format!("0x{}", "ab".repeat(SUI_ADDRESS_LENGTH)).into_bytes(),
+        };
+        let request = serde_json::json!({ "metadata": metadata, "intent_scope": intent_scope });
+        serde_json::from_value(request).unwrap()
+    }
+
+    #[test]
+    fn test_metadata_is_signed_with_attestation_digest() {
+        let state = test_state();
+        let Json(response) = sign_attested(&state, request(None), &signed_bytes_query()).unwrap();
+        let message = &response.signed.response;
+        assert_eq!(message.intent, IntentScope::AttestedMetadata);
+        assert_eq!(message.timestamp_ms, NOW_MS);
+        let document = Hex::decode(&response.attestation).unwrap();
+        let digest = Sha256::digest(&document).to_vec();
+        assert_eq!(message.data.attestation_digest, digest);
+        assert_eq!(message.data.verification.description, b"Attested");
+
+        // scope || timestamp_ms || verification || ULEB128 length || digest
+        let mut expected = vec![0x07];
+        expected.extend(NOW_MS.to_le_bytes());
+        expected.extend(bcs::to_bytes(&message.data.verification).unwrap());
+        expected.push(0x20);
+        expected.extend(&digest);
+        assert_eq!(signing_bytes(message), expected);
+        assert_eq!(response.signed.signed_bytes, Some(Hex::encode(&expected)));
+        let signature = Hex::decode(&response.signed.signature).unwrap();
+        assert!(state.signer.verify(&expected, &signature));
+    }
+
+    #[test]
+    fn test_intent_scope_and_missing_attestation_are_refused() {
+        let state = test_state();
+        let result = sign_attested(&state, request(Some("verify_metadata")), &no_query());
+        assert!(matches!(result.err(), Some(EnclaveError::InvalidInput(_))));
+
+        // Nothing is signed when the attestation cannot be obtained
+        let mut state = test_state();
+        state.allow_mock_attestation = false;
+        let err = sign_attested(&state, request(None), &no_query()).unwrap_err();
+        assert!(matches!(err, EnclaveError::AttestationUnavailable(_)));
+    }
+
+    #[test]
+    fn test_failed_attestation_does_not_burn_the_hash() {
+        let mut state = test_state();
+        state.recent_hashes = Some(dedup::RecentHashes::default());
+        state.allow_mock_attestation = false;
+        assert!(sign_attested(&state, request(None), &no_query()).is_err());
+
+        // The retry, once attestation works again, is not refused as a replay
+        state.allow_mock_attestation = true;
+        assert!(sign_attested(&state, request(None), &no_query()).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_rejections_wait_for_floor() {
+        const FLOOR: std::time::Duration = std::time::Duration::from_millis(200);
+        let mut state = test_state();
+        state.rejection_floor = Some(FLOOR);
+        let started = std::time::Instant::now();
+        let result = verify_metadata_attested(
+            State(Arc::new(state)),
+            Query(no_query()),
+            Json(request(Some("verify_metadata"))),
+        );
+        assert!(result.await.is_err());
+        assert!(started.elapsed() >= FLOOR);
+    }
+
+    #[tokio::test]
+    async fn test_signing_is_recorded_in_audit_log() {
+        let state = Arc::new(test_state());
+        let query = Query(no_query());
+        verify_metadata_attested(State(state.clone()), query, Json(request(None)))
+            .await
+            .unwrap();
+
+        let records = state.audit_log.find("dataset-1");
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].endpoint, "verify_metadata_attested");
+        assert_eq!(records[0].intent, IntentScope::AttestedMetadata);
+        assert_eq!(records[0].timestamp_ms, NOW_MS);
+    }
+}
@@ -0,0 +1,172 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! In-memory record of signed dataset verifications, answering "did the
+//! enclave sign dataset X, and when?".
+//!
+//! The log is a bounded ring buffer: once full, the oldest record is dropped.
+//! It lives only in enclave memory and is lost on every restart, so it is a
+//! recent-history aid, not a compliance archive; pair it with the webhook (or
+//! another persistent sink) when records must survive the instance. Records
+//! never include the signature.
+
+use crate::common::{IntentMessage, IntentScope, ProcessedDataResponse};
+use crate::{AppState, DatasetVerification, EnclaveError};
+use axum::extract::{Query, State};
+use axum::Json;
+use fastcrypto::encoding::{Encoding, Hex};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// Default number of signing operations kept in the audit log.
+pub const DEFAULT_AUDIT_LOG_SIZE: usize = 10_000;
+
+/// One signed `DatasetVerification`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// Endpoint that produced the signature, e.g. `verify_metadata`
+    pub endpoint: String,
+    pub dataset_id: String,
+    /// Hex encoded `original_hash`
+    pub original_hash: String,
+    /// Timestamp inside the signed message, in milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    pub intent: IntentScope,
+}
+
+/// Bounded ring buffer of the most recent signing operations.
+pub struct AuditLog {
+    capacity: usize,
+    records: Mutex<VecDeque<AuditRecord>>,
+}
+
+impl AuditLog {
+    /// Create a log holding up to `capacity` records; 0 disables auditing.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            records: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Record that `signed` was produced by `endpoint`, evicting the oldest
+    /// record when full.
+    pub fn record(&self, endpoint: &str, signed: &IntentMessage<DatasetVerification>) {
+        if self.capacity == 0 {
+            return;
+        }
+        let record = AuditRecord {
+            endpoint: endpoint.to_string(),
+            dataset_id: String::from_utf8_lossy(&signed.data.dataset_id).into_owned(),
+            original_hash: Hex::encode(&signed.data.original_hash),
+            timestamp_ms: signed.timestamp_ms,
+            intent: signed.intent,
+        };
+        let mut records = self.records.lock().expect("audit log lock poisoned");
+        if records.len() == self.capacity {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+
+    /// Records for `dataset_id`, oldest first.
+    pub fn find(&self, dataset_id: &str) -> Vec<AuditRecord> {
+        let records = self.records.lock().expect("audit log lock poisoned");
+        records.iter().filter(|record| record.dataset_id == dataset_id).cloned().collect()
+    }
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_AUDIT_LOG_SIZE)
+    }
+}
+
+/// Record the response of a successful signing request from `endpoint`.
+pub fn record(
+    state: &AppState,
+    endpoint: &str,
+    result: &Result<Json<ProcessedDataResponse<IntentMessage<DatasetVerification>>>, EnclaveError>,
+) {
+    if let Ok(Json(signed)) = result {
+        state.audit_log.record(endpoint, &signed.response);
+    }
+}
+
+/// Query parameters for `/audit`.
+#[derive(Debug, Deserialize)]
+pub struct AuditQuery {
+    pub dataset_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditResponse {
+    pub dataset_id: String,
+    /// Matching signing operations still in the log, oldest first
+    pub records: Vec<AuditRecord>,
+}
+
+/// Endpoint listing the signing operations recorded for one `dataset_id`.
+pub async fn audit(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<AuditQuery>,
+) -> Json<AuditResponse> {
+    let records = state.audit_log.find(&query.dataset_id);
+    Json(AuditResponse { dataset_id: query.dataset_id, records })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DATASET_VERIFICATION_VERSION;
+
+    fn signed(dataset_id: &[u8], timestamp_ms: u64) -> IntentMessage<DatasetVerification> {
+        IntentMessage::new(
+            DatasetVerification {
+                version: DATASET_VERIFICATION_VERSION,
+                dataset_id: dataset_id.to_vec(),
+                name: b"data.csv".to_vec(),
+                description: b"Audit test".to_vec(),
+                format: b"CSV".to_vec(),
+                size: 1024,
+                original_hash: vec![0xAB; 32],
+                walrus_blob_id: b"blob".to_vec(),
+                seal_policy_id: b"policy".to_vec(),
+                timestamp: timestamp_ms,
+                uploader: b"0xA".to_vec(),
+            },
+            timestamp_ms,
+            IntentScope::VerifyMetadata,
+        )
+    }
+
+    #[test]
+    fn test_records_are_found_by_dataset_id() {
+        let log = AuditLog::new(10);
+        log.record("verify_metadata", &signed(b"dataset-1", 1));
+        log.record("verify_metadata", &signed(b"dataset-2", 2));
+        log.record("resign_metadata", &signed(b"dataset-1", 3));
+
+        let records = log.find("dataset-1");
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].timestamp_ms, 1);
+        assert_eq!(records[1].endpoint, "resign_metadata");
+        assert_eq!(records[1].original_hash, "ab".repeat(32));
+        assert!(log.find("dataset-3").is_empty());
+    }
+
+    #[test]
+    fn test_oldest_record_is_evicted_when_full() {
+        let log = AuditLog::new(2);
+        for timestamp in 1..=3 {
+            log.record("verify_metadata", &signed(b"dataset-1", timestamp));
+        }
+        let timestamps: Vec<_> = log.find("dataset-1").iter().map(|r| r.timestamp_ms).collect();
+        assert_eq!(timestamps, [2, 3]);
+
+        let disabled = AuditLog::new(0);
+        disabled.record("verify_metadata", &signed(b"dataset-1", 1));
+        assert!(disabled.find("dataset-1").is_empty());
+    }
+}
@@ -0,0 +1,82 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{AppState, EnclaveError};
+use axum::extract::{Request, State};
+use axum::http::header::AUTHORIZATION;
+use axum::http::HeaderMap;
+use axum::middleware::Next;
+use axum::response::Response;
+use std::sync::Arc;
+
+/// Alternative header carrying the API key, for clients that cannot set Authorization.
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Middleware rejecting requests whose API key does not match `state.api_key`.
+/// Accepts either `Authorization: Bearer <key>` or `X-API-Key: <key>`.
+/// Does nothing when `state.require_api_key` is false.
+pub async fn require_api_key(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, EnclaveError> {
+    if !state.require_api_key {
+        return Ok(next.run(request).await);
+    }
+
+    match extract_api_key(request.headers()) {
+        Some(key) if constant_time_eq(key.as_bytes(), state.api_key.as_bytes()) => {
+            Ok(next.run(request).await)
+        }
+        Some(_) => Err(EnclaveError::Unauthorized("invalid API key".to_string())),
+        None => Err(EnclaveError::Unauthorized("missing API key".to_string())),
+    }
+}
+
+/// Extract the API key from the request headers, preferring the bearer token.
+pub fn extract_api_key(headers: &HeaderMap) -> Option<&str> {
+    if let Some(token) = headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+    {
+        return Some(token.trim());
+    }
+    headers
+        .get(API_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+}
+
+/// Compare two byte strings without short-circuiting on the first difference.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_extract_api_key() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(extract_api_key(&headers), None);
+
+        headers.insert(API_KEY_HEADER, HeaderValue::from_static("from-header"));
+        assert_eq!(extract_api_key(&headers), Some("from-header"));
+
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer from-bearer"));
+        assert_eq!(extract_api_key(&headers), Some("from-bearer"));
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"local-dev-key", b"local-dev-key"));
+        assert!(!constant_time_eq(b"local-dev-key", b"local-dev-kex"));
+        assert!(!constant_time_eq(b"short", b"longer-key"));
+    }
+}
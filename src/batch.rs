@@ -0,0 +1,245 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Batch metadata signing with output suited to aggregate verification.
+//!
+//! Every item is canonicalized and signed by the same enclave key under
+//! `IntentScope::VerifyMetadata`, exactly as `verify_metadata` would sign it.
+//! Alongside the per-item responses the endpoint returns the public key, the
+//! signatures and the signed BCS bytes in item order, which is what
+//! `Ed25519PublicKey::verify_batch_empty_fail_different_msg` takes.
+//!
+//! Items arrive either as JSON in `items` or as hex encoded BCS in
+//! `items_bcs`. BCS items are decoded through `bcs_guard`, after the item
+//! count has been checked, so a hostile batch costs at most
+//! `max_batch_items * max_bcs_item_bytes` of decoding.
+
+use crate::bcs_guard::decode_bcs;
+use crate::common::{signing_bytes, IntentMessage, IntentScope, ProcessedDataResponse};
+use crate::text::decode_hex;
+use crate::{
+    prepare_client_metadata, record_client_metadata, sign_client_metadata, AppState,
+    DatasetVerification, EnclaveError,
+};
+use axum::extract::State;
+use axum::Json;
+use fastcrypto::encoding::{Encoding, Hex};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::info;
+
+/// Request to sign a batch of dataset metadata.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchVerifyMetadataRequest {
+    #[serde(default)]
+    pub items: Vec<DatasetVerification>,
+    /// Hex encoded BCS of each `DatasetVerification`, instead of `items`
+    #[serde(default)]
+    pub items_bcs: Vec<String>,
+}
+
+/// Signed items plus everything needed to verify them together.
+#[derive(Serialize, Deserialize)]
+pub struct BatchVerifyMetadataResponse {
+    /// Hex encoded public key that produced every signature
+    pub public_key: String,
+    /// Hex encoded signatures, in request order
+    pub signatures: Vec<String>,
+    /// Hex encoded signed BCS bytes, in request order
+    pub messages: Vec<String>,
+    pub responses: Vec<ProcessedDataResponse<IntentMessage<DatasetVerification>>>,
+}
+
+/// Endpoint that validates and signs every item with the enclave key.
+/// Nothing is signed unless every item passes validation.
+pub async fn batch_verify_metadata(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<BatchVerifyMetadataRequest>,
+) -> Result<Json<BatchVerifyMetadataResponse>, EnclaveError> {
+    let result = sign_batch(&state, request);
+    state.metrics.record("batch_verify_metadata", &result);
+    if let Ok(Json(response)) = &result {
+        for signed in &response.responses {
+            state.audit_log.record("batch_verify_metadata", &signed.response);
+        }
+    }
+    result
+}
+
+fn sign_batch(
+    state: &AppState,
+    request: BatchVerifyMetadataRequest,
+) -> Result<Json<BatchVerifyMetadataResponse>, EnclaveError> {
+    if !request.items.is_empty() && !request.items_bcs.is_empty() {
+        return Err(EnclaveError::InvalidInput(
+            "send either items or items_bcs, not both".to_string(),
+        ));
+    }
+    let count = request.items.len() + request.items_bcs.len();
+    if count == 0 {
+        return Err(EnclaveError::InvalidInput("items cannot be empty".to_string()));
+    }
+    if count > state.limits.max_batch_items {
+        return Err(EnclaveError::InvalidInput(format!(
+            "too many items: {} (max {})",
+            count, state.limits.max_batch_items
+        )));
+    }
+    let items = if request.items_bcs.is_empty() {
+        request.items
+    } else {
+        decode_bcs_items(state, &request.items_bcs)?
+    };
+    let items = items
+        .into_iter()
+        .enumerate()
+        .map(|(index, item)| {
+            prepare_client_metadata(state, item).map_err(|e| {
+                EnclaveError::InvalidInput(format!("item {}: {}", index, e.message()))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    // Recorded once every item is valid; a repeated hash or rebound policy fails it
+    for (index, item) in items.iter().enumerate() {
+        record_client_metadata(state, item).map_err(|e| match e {
+            EnclaveError::Conflict(msg) => {
+                EnclaveError::Conflict(format!("item {}: {}", index, msg))
+            }
+            e => e,
+        })?;
+    }
+
+    info!("Signing batch of {} datasets", items.len());
+
+    // One key for the whole batch, even if it is rotated meanwhile
+    let signer = state.signer.current();
+    let responses: Vec<_> = items
+        .into_iter()
+        .map(|item| {
+            sign_client_metadata(state, signer.as_ref(), item, IntentScope::VerifyMetadata)
+        })
+        .collect();
+
+    Ok(Json(BatchVerifyMetadataResponse {
+        public_key: Hex::encode(signer.public_key_bytes()),
+        signatures: responses.iter().map(|r| r.signature.clone()).collect(),
+        messages: responses
+            .iter()
+            .map(|r| Hex::encode(signing_bytes(&r.response)))
+            .collect(),
+        responses,
+    }))
+}
+
+/// Decode hex BCS items, naming the failing item by index.
+fn decode_bcs_items(
+    state: &AppState,
+    items_bcs: &[String],
+) -> Result<Vec<DatasetVerification>, EnclaveError> {
+    items_bcs
+        .iter()
+        .enumerate()
+        .map(|(index, hex)| {
+            let what = format!("item {}", index);
+            let bytes = decode_hex(&what, hex)?;
+            decode_bcs(&what, &bytes, state.limits.max_bcs_item_bytes)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{current_timestamp_ms, DATASET_VERIFICATION_VERSION};
+    use fastcrypto::ed25519::{Ed25519KeyPair, Ed25519PublicKey, Ed25519Signature};
+    use fastcrypto::traits::{KeyPair, ToFromBytes, VerifyingKey};
+
+    fn item(id: &[u8]) -> DatasetVerification {
+        DatasetVerification {
+            version: DATASET_VERIFICATION_VERSION,
+            dataset_id: id.to_vec(),
+            name: b"data.csv".to_vec(),
+            description: b"Batch test".to_vec(),
+            format: b"CSV".to_vec(),
+            size: 1024,
+            original_hash: vec![0xAB; 32],
+            walrus_blob_id: b"blob".to_vec(),
+            seal_policy_id: b"policy".to_vec(),
+            timestamp: current_timestamp_ms().unwrap(),
+            uploader: b"0xA".to_vec(),
+        }
+    }
+
+    fn test_state() -> AppState {
+        let mut state = AppState::new(
+            Ed25519KeyPair::generate(&mut rand::thread_rng()),
+            "test-key".to_string(),
+        );
+        state.strict_uploader_address = false;
+        state
+    }
+
+    #[test]
+    fn test_batch_signatures_verify_together() {
+        let state = test_state();
+        let request = BatchVerifyMetadataRequest {
+            items: vec![item(b"a"), item(b"b"), item(b"c")],
+            items_bcs: vec![],
+        };
+
+        let Json(response) = sign_batch(&state, request).expect("batch should be signed");
+        assert_eq!(response.signatures.len(), 3);
+
+        let pk = Ed25519PublicKey::from_bytes(&Hex::decode(&response.public_key).unwrap()).unwrap();
+        let messages: Vec<Vec<u8>> = response.messages.iter().map(|m| Hex::decode(m).unwrap()).collect();
+        let signatures: Vec<Ed25519Signature> = response
+            .signatures
+            .iter()
+            .map(|s| Ed25519Signature::from_bytes(&Hex::decode(s).unwrap()).unwrap())
+            .collect();
+        let pks = vec![pk; messages.len()];
+        assert!(Ed25519PublicKey::verify_batch_empty_fail_different_msg(&messages, &pks, &signatures).is_ok());
+    }
+
+    #[test]
+    fn test_batch_rejects_invalid_item() {
+        let state = test_state();
+        let mut invalid = item(b"b");
+        invalid.name.clear();
+        let request = BatchVerifyMetadataRequest {
+            items: vec![item(b"a"), invalid],
+            items_bcs: vec![],
+        };
+
+        let err = sign_batch(&state, request).err().expect("invalid item must fail the batch");
+        assert!(err.message().starts_with("item 1:"));
+    }
+
+    #[test]
+    fn test_batch_accepts_bcs_items_within_bounds() {
+        let mut state = test_state();
+        let encode = |item: &DatasetVerification| Hex::encode(bcs::to_bytes(item).unwrap());
+        let request = |items_bcs: Vec<String>| BatchVerifyMetadataRequest {
+            items: vec![],
+            items_bcs,
+        };
+
+        let Json(response) = sign_batch(&state, request(vec![encode(&item(b"a"))])).unwrap();
+        assert_eq!(response.responses[0].response.data.dataset_id, b"a");
+
+        let mut truncated = encode(&item(b"b"));
+        truncated.truncate(truncated.len() - 4);
+        let err = sign_batch(&state, request(vec![encode(&item(b"a")), truncated]));
+        assert!(err.err().unwrap().message().starts_with("item 1 is not valid BCS"));
+
+        state.limits.max_bcs_item_bytes = 16;
+        let err = sign_batch(&state, request(vec![encode(&item(b"a"))])).err().unwrap();
+        assert!(matches!(err, EnclaveError::PayloadTooLarge(_)));
+
+        let both = BatchVerifyMetadataRequest {
+            items: vec![item(b"a")],
+            items_bcs: vec![encode(&item(b"b"))],
+        };
+        assert!(sign_batch(&state, both).is_err());
+    }
+}
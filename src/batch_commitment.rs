@@ -0,0 +1,160 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! One signature over a whole batch of `DatasetVerification`s.
+//!
+//! Unlike `/batch_verify_metadata`, which signs every item separately, this
+//! signs `IntentMessage<Vec<DatasetVerification>>` once under
+//! `IntentScope::Batch`. A Move verifier rebuilds the signed bytes with
+//! `bcs::to_bytes(&intent_message)`, which is:
+//! - the scope byte `0x05`
+//! - `timestamp_ms` as a little-endian u64
+//! - the item count as a ULEB128 length
+//! - each item's BCS, in request order
+//!
+//! Items are canonicalized as `/verify_metadata` does and signed in the order
+//! sent, so the same batch always yields the same bytes for a given
+//! timestamp. The response carries the items as signed.
+
+use crate::common::{
+    to_signed_response, IntentMessage, IntentScope, ProcessedDataResponse, SigningQuery,
+};
+use crate::{prepare_client_metadata, AppState, DatasetVerification, EnclaveError};
+use axum::extract::{Query, State};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::info;
+
+/// Request to sign a batch as a single commitment.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SignBatchCommitmentRequest {
+    pub items: Vec<DatasetVerification>,
+}
+
+/// Endpoint that validates every item and signs the whole batch once.
+pub async fn sign_batch_commitment(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SigningQuery>,
+    Json(request): Json<SignBatchCommitmentRequest>,
+) -> Result<Json<ProcessedDataResponse<IntentMessage<Vec<DatasetVerification>>>>, EnclaveError> {
+    let result = commit_batch(&state, request)
+        .and_then(|Json(response)| query.apply(&state.signer, response).map(Json));
+    state.metrics.record("sign_batch_commitment", &result);
+    result
+}
+
+fn commit_batch(
+    state: &AppState,
+    request: SignBatchCommitmentRequest,
+) -> Result<Json<ProcessedDataResponse<IntentMessage<Vec<DatasetVerification>>>>, EnclaveError> {
+    if request.items.is_empty() {
+        return Err(EnclaveError::InvalidInput("items cannot be empty".to_string()));
+    }
+    if request.items.len() > state.limits.max_batch_items {
+        return Err(EnclaveError::InvalidInput(format!(
+            "too many items: {} (max {})",
+            request.items.len(),
+            state.limits.max_batch_items
+        )));
+    }
+    let items = request
+        .items
+        .into_iter()
+        .enumerate()
+        .map(|(index, item)| {
+            prepare_client_metadata(state, item).map_err(|e| {
+                EnclaveError::InvalidInput(format!("item {}: {}", index, e.message()))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    info!("Signing one commitment over {} datasets", items.len());
+    Ok(Json(to_signed_response(
+        state.signer.current().as_ref(),
+        items,
+        state.clock.now_millis()?,
+        IntentScope::Batch,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::signing_bytes;
+    use crate::DATASET_VERIFICATION_VERSION;
+    use fastcrypto::ed25519::Ed25519KeyPair;
+    use fastcrypto::encoding::{Encoding, Hex};
+    use fastcrypto::traits::KeyPair;
+
+    fn test_state() -> AppState {
+        let mut state = AppState::new(
+            Ed25519KeyPair::generate(&mut rand::thread_rng()),
+            "test-key".to_string(),
+        );
+        state.strict_uploader_address = false;
+        state.limits.timestamp_freshness_ms = None;
+        state
+    }
+
+    fn item(id: &[u8]) -> DatasetVerification {
+        DatasetVerification {
+            version: DATASET_VERIFICATION_VERSION,
+            dataset_id: id.to_vec(),
+            name: b"data.csv".to_vec(),
+            description: b"Batch commitment test".to_vec(),
+            format: b"CSV".to_vec(),
+            size: 1024,
+            original_hash: vec![0xAB; 32],
+            walrus_blob_id: b"blob".to_vec(),
+            seal_policy_id: b"policy".to_vec(),
+            timestamp: 1700000000000,
+            uploader: b"0xA".to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_batch_commitment_bytes_are_deterministic() {
+        let state = test_state();
+        let items = || vec![item(b"a"), item(b"b")];
+        let Json(signed) =
+            commit_batch(&state, SignBatchCommitmentRequest { items: items() }).unwrap();
+        let message = &signed.response;
+        assert_eq!(message.intent, IntentScope::Batch);
+
+        // scope || timestamp_ms || ULEB128 count || items, as documented above
+        let mut expected = vec![0x05];
+        expected.extend(message.timestamp_ms.to_le_bytes());
+        expected.push(2);
+        for item in items() {
+            expected.extend(bcs::to_bytes(&item).unwrap());
+        }
+        assert_eq!(signing_bytes(message), expected);
+
+        // One signature over those bytes; a rebuilt message signs identically
+        let rebuilt = IntentMessage::new(items(), message.timestamp_ms, IntentScope::Batch);
+        assert_eq!(signing_bytes(&rebuilt), expected);
+        let signature = Hex::decode(&signed.signature).unwrap();
+        assert!(state.signer.verify(&expected, &signature));
+        let again = to_signed_response(
+            state.signer.current().as_ref(),
+            items(),
+            message.timestamp_ms,
+            IntentScope::Batch,
+        );
+        assert_eq!(again.signature, signed.signature);
+    }
+
+    #[test]
+    fn test_batch_commitment_rejects_invalid_item() {
+        let state = test_state();
+        let mut bad = item(b"b");
+        bad.name.clear();
+        let err = commit_batch(&state, SignBatchCommitmentRequest { items: vec![item(b"a"), bad] })
+            .unwrap_err();
+        assert_eq!(err.message(), "item 1: name cannot be empty");
+
+        let empty = SignBatchCommitmentRequest { items: vec![] };
+        assert!(commit_batch(&state, empty).is_err());
+    }
+}
@@ -0,0 +1,274 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Batch dataset verification: each item is fetched, checked against its
+//! declared `size` and `original_hash` and signed exactly as `verify_dataset`
+//! would sign it.
+//!
+//! Up to `max_batch_fetch_concurrency` items are fetched at once, and each
+//! fetch still waits for a slot of the global fetch limiter, so a large batch
+//! shares outbound capacity with single requests instead of starving them.
+//! Batches declaring more than `max_batch_total_bytes` across all items are
+//! refused before anything is fetched, and each item's download stops as soon
+//! as it passes the item's declared `size`, so the budget bounds the bytes
+//! actually fetched. Results come back in request order.
+//! An item that fails (unreachable URL, mismatched hash) carries its error
+//! without affecting the others.
+
+use crate::common::{IntentMessage, ProcessedDataResponse};
+use crate::{
+    verify_dataset_claims, AppState, DatasetVerification, EnclaveError, VerifyDatasetRequest,
+};
+use axum::extract::State;
+use axum::Json;
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::info;
+
+/// Request to verify a batch of datasets.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchVerifyDatasetRequest {
+    pub items: Vec<VerifyDatasetRequest>,
+}
+
+/// Error of one item, in the body shape a single request would return.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchItemError {
+    pub error: String,
+    pub code: String,
+}
+
+type SignedDataset = ProcessedDataResponse<IntentMessage<DatasetVerification>>;
+
+/// Outcome of one item: exactly one of `response` and `error` is set.
+#[derive(Serialize, Deserialize)]
+pub struct BatchItemResult {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<SignedDataset>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<BatchItemError>,
+}
+
+impl From<Result<Json<SignedDataset>, EnclaveError>> for BatchItemResult {
+    fn from(result: Result<Json<SignedDataset>, EnclaveError>) -> Self {
+        match result {
+            Ok(Json(response)) => Self { response: Some(response), error: None },
+            Err(e) => Self {
+                response: None,
+                error: Some(BatchItemError {
+                    error: e.message().to_string(),
+                    code: e.code().to_string(),
+                }),
+            },
+        }
+    }
+}
+
+/// Per-item results, in request order.
+#[derive(Serialize, Deserialize)]
+pub struct BatchVerifyDatasetResponse {
+    pub results: Vec<BatchItemResult>,
+}
+
+/// Endpoint that fetches and verifies every item concurrently.
+pub async fn batch_verify_dataset(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<BatchVerifyDatasetRequest>,
+) -> Result<Json<BatchVerifyDatasetResponse>, EnclaveError> {
+    let result = verify_batch(&state, request).await;
+    state.metrics.record("batch_verify_dataset", &result);
+    if let Ok(Json(response)) = &result {
+        for signed in response.results.iter().filter_map(|r| r.response.as_ref()) {
+            state.audit_log.record("batch_verify_dataset", &signed.response);
+        }
+    }
+    result
+}
+
+async fn verify_batch(
+    state: &AppState,
+    request: BatchVerifyDatasetRequest,
+) -> Result<Json<BatchVerifyDatasetResponse>, EnclaveError> {
+    if request.items.is_empty() {
+        return Err(EnclaveError::InvalidInput("items cannot be empty".to_string()));
+    }
+    if request.items.len() > state.limits.max_batch_items {
+        return Err(EnclaveError::InvalidInput(format!(
+            "too many items: {} (max {})",
+            request.items.len(),
+            state.limits.max_batch_items
+        )));
+    }
+    state
+        .limits
+        .check_batch_total_bytes(request.items.iter().map(|item| item.metadata.size))?;
+
+    let concurrency = state.limits.max_batch_fetch_concurrency.max(1);
+    info!(
+        "Verifying batch of {} datasets, {} at a time",
+        request.items.len(),
+        concurrency
+    );
+
+    // Items finish in any order; the index puts them back in request order
+    let mut results: Vec<(usize, BatchItemResult)> =
+        stream::iter(request.items.into_iter().enumerate())
+            .map(|(index, item)| async move {
+                (index, verify_dataset_claims(state, item).await.into())
+            })
+            .buffer_unordered(concurrency)
+            .collect()
+            .await;
+    results.sort_unstable_by_key(|(index, _)| *index);
+
+    Ok(Json(BatchVerifyDatasetResponse {
+        results: results.into_iter().map(|(_, result)| result).collect(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{current_timestamp_ms, DATASET_VERIFICATION_VERSION, SUI_ADDRESS_LENGTH};
+    use fastcrypto::ed25519::Ed25519KeyPair;
+    use fastcrypto::traits::KeyPair;
+    use sha2::{Digest, Sha256};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serve `GET /<n>` with body `item-<n>`, answering later items sooner so
+    /// they complete out of order. Returns the base URL and the peak number of
+    /// requests being answered at once.
+    async fn serve_items(count: u64) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let active = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let server_peak = peak.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let (active, peak) = (active.clone(), server_peak.clone());
+                tokio::spawn(async move {
+                    let mut request = vec![0u8; 4096];
+                    let read = socket.read(&mut request).await.unwrap();
+                    let request = String::from_utf8_lossy(&request[..read]).to_string();
+                    let path = request.split_whitespace().nth(1).unwrap();
+                    let n: u64 = path.trim_start_matches('/').parse().unwrap();
+
+                    let now_active = active.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now_active, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(20 * (count - n))).await;
+                    active.fetch_sub(1, Ordering::SeqCst);
+
+                    let body = format!("item-{}", n);
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    socket.write_all(response.as_bytes()).await.unwrap();
+                });
+            }
+        });
+        (base, peak)
+    }
+
+    fn item(base: &str, n: u64) -> VerifyDatasetRequest {
+        let body = format!("item-{}", n);
+        VerifyDatasetRequest {
+            metadata: DatasetVerification {
+                version: DATASET_VERIFICATION_VERSION,
+                dataset_id: format!("dataset-{}", n).into_bytes(),
+                name: b"data.csv".to_vec(),
+                description: b"Batch test".to_vec(),
+                format: b"CSV".to_vec(),
+                size: body.len() as u64,
+                original_hash: Sha256::digest(body.as_bytes()).to_vec(),
+                walrus_blob_id: b"blob-1".to_vec(),
+                seal_policy_id: b"policy-1".to_vec(),
+                timestamp: current_timestamp_ms().unwrap(),
+                uploader: format!("0x{}", "ab".repeat(SUI_ADDRESS_LENGTH)).into_bytes(),
+            },
+            dataset_url: format!("{}/{}", base, n),
+            hash_algorithm: None,
+            hash_wire_bytes: None,
+        }
+    }
+
+    fn test_state(concurrency: usize) -> AppState {
+        let mut state = AppState::new(
+            Ed25519KeyPair::generate(&mut rand::thread_rng()),
+            "test-key".to_string(),
+        );
+        state.url_policy.allowlist = vec!["127.0.0.1".to_string()];
+        state.limits.max_batch_fetch_concurrency = concurrency;
+        state
+    }
+
+    #[tokio::test]
+    async fn test_results_keep_request_order_with_capped_concurrency() {
+        const COUNT: u64 = 6;
+        let (base, peak) = serve_items(COUNT).await;
+        let state = test_state(2);
+
+        let mut items: Vec<_> = (0..COUNT).map(|n| item(&base, n)).collect();
+        // A wrong hash fails only its own item
+        items[3].metadata.original_hash = vec![0xAB; 32];
+        let Json(response) =
+            verify_batch(&state, BatchVerifyDatasetRequest { items }).await.unwrap();
+
+        assert_eq!(response.results.len(), COUNT as usize);
+        for (n, result) in response.results.iter().enumerate() {
+            if n == 3 {
+                assert_eq!(result.error.as_ref().unwrap().code, "hash_mismatch");
+                assert!(result.response.is_none());
+                continue;
+            }
+            let signed = result.response.as_ref().expect("item should be signed");
+            assert_eq!(signed.response.data.dataset_id, format!("dataset-{}", n).as_bytes());
+        }
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_batch_size_is_checked() {
+        let state = test_state(2);
+        let empty = BatchVerifyDatasetRequest { items: vec![] };
+        assert!(verify_batch(&state, empty).await.is_err());
+
+        let mut state = state;
+        state.limits.max_batch_items = 1;
+        let items = vec![item("http://127.0.0.1:1", 0), item("http://127.0.0.1:1", 1)];
+        let err = verify_batch(&state, BatchVerifyDatasetRequest { items }).await.unwrap_err();
+        assert_eq!(err.message(), "too many items: 2 (max 1)");
+    }
+
+    #[tokio::test]
+    async fn test_batch_total_size_budget() {
+        let (base, _) = serve_items(2).await;
+        let items = || vec![item(&base, 0), item(&base, 1)];
+        let declared: u64 = items().iter().map(|item| item.metadata.size).sum();
+
+        // Exactly at the budget, every item is fetched and signed
+        let mut state = test_state(2);
+        state.limits.max_batch_total_bytes = declared;
+        let Json(response) =
+            verify_batch(&state, BatchVerifyDatasetRequest { items: items() }).await.unwrap();
+        assert!(response.results.iter().all(|result| result.response.is_some()));
+
+        // One byte over, the batch is refused as a whole
+        state.limits.max_batch_total_bytes = declared - 1;
+        let err = verify_batch(&state, BatchVerifyDatasetRequest { items: items() })
+            .await
+            .unwrap_err();
+        assert!(matches!(err, EnclaveError::PayloadTooLarge(_)));
+        assert_eq!(
+            err.message(),
+            format!("batch declares {} bytes in total, budget is {} bytes", declared, declared - 1)
+        );
+    }
+}
@@ -0,0 +1,126 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Bounded decoding of client-supplied BCS.
+//!
+//! BCS from a client is untrusted input: an oversized blob, a length prefix
+//! promising more data than is present, or deeply nested enums must be
+//! refused with an error, never a panic or a blown stack. Everything the
+//! enclave decodes from client BCS goes through `decode_bcs`.
+
+use crate::EnclaveError;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// Deepest nesting of structs and enums accepted, well below the BCS
+/// crate's own limit of 500. Signed payload types are only a few levels deep.
+pub const MAX_BCS_DEPTH: usize = 16;
+
+/// Decode `bytes` as a `T`, naming the input as `what` in errors. Rejects
+/// inputs over `max_bytes`, nesting deeper than `MAX_BCS_DEPTH`, trailing
+/// bytes, and any encoding that does not re-encode to exactly `bytes`, so
+/// what gets signed is the canonical form.
+pub fn decode_bcs<T>(what: &str, bytes: &[u8], max_bytes: usize) -> Result<T, EnclaveError>
+where
+    T: Serialize + DeserializeOwned,
+{
+    if bytes.len() > max_bytes {
+        return Err(EnclaveError::PayloadTooLarge(format!(
+            "{} is {} bytes of BCS, limit is {}",
+            what,
+            bytes.len(),
+            max_bytes
+        )));
+    }
+    let decoded = catch_unwind(AssertUnwindSafe(|| {
+        bcs::from_bytes_with_limit::<T>(bytes, MAX_BCS_DEPTH)
+    }))
+    .map_err(|_| EnclaveError::InvalidInput(format!("{} could not be decoded as BCS", what)))?;
+    let value = decoded
+        .map_err(|e| EnclaveError::InvalidInput(format!("{} is not valid BCS: {}", what, e)))?;
+    if bcs::to_bytes(&value).ok().as_deref() != Some(bytes) {
+        return Err(EnclaveError::InvalidInput(format!(
+            "{} is not in canonical BCS encoding",
+            what
+        )));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DatasetVerification, DATASET_VERIFICATION_VERSION};
+    use serde::Deserialize;
+
+    fn metadata() -> DatasetVerification {
+        DatasetVerification {
+            version: DATASET_VERIFICATION_VERSION,
+            dataset_id: b"dataset-1".to_vec(),
+            name: b"data.csv".to_vec(),
+            description: b"Test dataset".to_vec(),
+            format: b"CSV".to_vec(),
+            size: 1024,
+            original_hash: vec![0xAB; 32],
+            walrus_blob_id: b"blob-1".to_vec(),
+            seal_policy_id: b"policy-1".to_vec(),
+            timestamp: 1700000000000,
+            uploader: b"0xA".to_vec(),
+        }
+    }
+
+    /// Recursive type whose nesting is chosen by the input
+    #[derive(Debug, Serialize, Deserialize)]
+    enum Nested {
+        Leaf,
+        Node(Box<Nested>),
+    }
+
+    #[test]
+    fn test_valid_item_is_decoded() {
+        let bytes = bcs::to_bytes(&metadata()).unwrap();
+        let decoded: DatasetVerification = decode_bcs("item 0", &bytes, 1024).unwrap();
+        assert_eq!(decoded.dataset_id, b"dataset-1");
+    }
+
+    #[test]
+    fn test_oversized_input_is_rejected_before_decoding() {
+        let bytes = bcs::to_bytes(&metadata()).unwrap();
+        let err = decode_bcs::<DatasetVerification>("item 0", &bytes, 16).unwrap_err();
+        assert!(matches!(err, EnclaveError::PayloadTooLarge(_)));
+        assert!(err.message().starts_with("item 0 is"));
+    }
+
+    #[test]
+    fn test_malformed_input_is_rejected_without_panicking() {
+        let bytes = bcs::to_bytes(&metadata()).unwrap();
+
+        // Truncated, with trailing garbage, and a length prefix claiming 2^31 bytes
+        let truncated = &bytes[..bytes.len() - 3];
+        assert!(decode_bcs::<DatasetVerification>("item", truncated, 1024).is_err());
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(decode_bcs::<DatasetVerification>("item", &trailing, 1024).is_err());
+        let huge_length = [DATASET_VERIFICATION_VERSION, 0xff, 0xff, 0xff, 0xff, 0x07];
+        assert!(decode_bcs::<DatasetVerification>("item", &huge_length, 1024).is_err());
+        assert!(decode_bcs::<DatasetVerification>("item", &[], 1024).is_err());
+
+        // A non-minimal ULEB128 length of dataset_id (after the version byte)
+        // decodes to the same value but is not canonical
+        let mut padded = vec![bytes[0], 0x89, 0x00];
+        padded.extend_from_slice(&bytes[2..]);
+        assert!(decode_bcs::<DatasetVerification>("item", &padded, 1024).is_err());
+    }
+
+    #[test]
+    fn test_deep_nesting_is_rejected() {
+        let shallow = [vec![1u8; 4], vec![0]].concat();
+        assert!(decode_bcs::<Nested>("payload", &shallow, 1 << 20).is_ok());
+
+        // Would recurse once per byte without a depth limit
+        let deep = [vec![1u8; 100_000], vec![0]].concat();
+        let err = decode_bcs::<Nested>("payload", &deep, 1 << 20).unwrap_err();
+        assert!(err.message().starts_with("payload is not valid BCS"));
+    }
+}
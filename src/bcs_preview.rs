@@ -0,0 +1,110 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Preview of the exact bytes the enclave signs for a `DatasetVerification`,
+//! for debugging serialization mismatches against the Move contract.
+
+use crate::common::{signing_bytes, IntentMessage, IntentScope};
+use crate::{AppState, DatasetVerification, EnclaveError};
+use axum::extract::State;
+use axum::Json;
+use fastcrypto::encoding::{Encoding, Hex};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Request to preview the signing payload for `metadata`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BcsPreviewRequest {
+    pub metadata: DatasetVerification,
+    /// Intent message timestamp; defaults to `metadata.timestamp` as used by `verify_metadata`
+    pub timestamp_ms: Option<u64>,
+    /// Intent scope as its numeric value; defaults to `VerifyMetadata` (1)
+    pub intent: Option<IntentScope>,
+}
+
+/// BCS-serialized `IntentMessage<DatasetVerification>`, unsigned.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BcsPreviewResponse {
+    pub intent: IntentScope,
+    pub timestamp_ms: u64,
+    /// Hex encoded bytes, starting with the intent scope byte
+    pub bcs: String,
+}
+
+/// Endpoint that returns the bytes `to_signed_response` would sign, without signing.
+pub async fn bcs_preview(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<BcsPreviewRequest>,
+) -> Result<Json<BcsPreviewResponse>, EnclaveError> {
+    let result = Ok(Json(preview(request)));
+    state.metrics.record("bcs_preview", &result);
+    result
+}
+
+fn preview(request: BcsPreviewRequest) -> BcsPreviewResponse {
+    let intent = request.intent.unwrap_or(IntentScope::VerifyMetadata);
+    let timestamp_ms = request.timestamp_ms.unwrap_or(request.metadata.timestamp);
+    let intent_msg = IntentMessage::new(request.metadata, timestamp_ms, intent);
+
+    BcsPreviewResponse {
+        intent,
+        timestamp_ms,
+        bcs: Hex::encode(signing_bytes(&intent_msg)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::to_signed_response;
+    use crate::DATASET_VERIFICATION_VERSION;
+    use fastcrypto::ed25519::{Ed25519KeyPair, Ed25519Signature};
+    use fastcrypto::traits::{KeyPair, ToFromBytes, VerifyingKey};
+
+    fn metadata() -> DatasetVerification {
+        DatasetVerification {
+            version: DATASET_VERIFICATION_VERSION,
+            dataset_id: b"test-123".to_vec(),
+            name: b"test.csv".to_vec(),
+            description: b"Test dataset".to_vec(),
+            format: b"CSV".to_vec(),
+            size: 1024,
+            original_hash: b"abc123".to_vec(),
+            walrus_blob_id: b"blob-123".to_vec(),
+            seal_policy_id: b"policy-123".to_vec(),
+            timestamp: 1700000000000,
+            uploader: b"0xA".to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_preview_matches_signed_bytes() {
+        let kp = Ed25519KeyPair::generate(&mut rand::thread_rng());
+        let signed = to_signed_response(&kp, metadata(), 1700000000000, IntentScope::VerifyMetadata);
+
+        let response = preview(BcsPreviewRequest {
+            metadata: metadata(),
+            timestamp_ms: None,
+            intent: None,
+        });
+        let bytes = Hex::decode(&response.bcs).unwrap();
+
+        assert_eq!(bytes[0], IntentScope::VerifyMetadata as u8);
+        let signature = Hex::decode(&signed.signature).unwrap();
+        let signature = Ed25519Signature::from_bytes(&signature).unwrap();
+        assert!(kp.public().verify(&bytes, &signature).is_ok());
+    }
+
+    #[test]
+    fn test_preview_overrides() {
+        let response = preview(BcsPreviewRequest {
+            metadata: metadata(),
+            timestamp_ms: Some(42),
+            intent: Some(IntentScope::ProcessData),
+        });
+        let expected = IntentMessage::new(metadata(), 42, IntentScope::ProcessData);
+
+        assert_eq!(response.timestamp_ms, 42);
+        assert_eq!(response.bcs, Hex::encode(bcs::to_bytes(&expected).unwrap()));
+    }
+}
@@ -0,0 +1,174 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::common::{IntentMessage, IntentScope, ProcessedDataResponse};
+use crate::metrics::Metrics;
+use crate::DatasetVerification;
+use lru::LruCache;
+use sha2::{Digest, Sha256};
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+
+/// Default number of signed responses kept in the cache.
+pub const DEFAULT_SIGNATURE_CACHE_SIZE: usize = 1024;
+
+type SignedVerification = ProcessedDataResponse<IntentMessage<DatasetVerification>>;
+
+/// LRU cache of signed responses keyed by the hash of the signed metadata and
+/// the signing key, so re-submitting identical metadata returns a
+/// byte-identical response while that key signs. After a rotation the new key
+/// misses even if a request still holding the old key re-inserted an entry.
+///
+/// Callers must validate the metadata (including timestamp freshness) before
+/// looking it up: an entry is only served while its timestamp would still be
+/// accepted.
+pub struct SignatureCache {
+    /// `None` when caching is disabled
+    entries: Option<Mutex<LruCache<[u8; 32], SignedVerification>>>,
+}
+
+impl SignatureCache {
+    /// Create a cache holding up to `capacity` responses; 0 disables caching.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: NonZeroUsize::new(capacity).map(|capacity| Mutex::new(LruCache::new(capacity))),
+        }
+    }
+
+    /// Cache key: sha256 over the signer's public key, the intent scope byte
+    /// and the BCS bytes of `metadata`.
+    pub fn key(public_key: &[u8], metadata: &DatasetVerification, intent: IntentScope) -> [u8; 32] {
+        let bytes = bcs::to_bytes(metadata).expect("should not fail");
+        let mut hasher = Sha256::new();
+        hasher.update(public_key);
+        hasher.update([intent as u8]);
+        hasher.update(&bytes);
+        hasher.finalize().into()
+    }
+
+    /// Return the response cached for `metadata` signed by `public_key`, or
+    /// sign it with `sign` (which must use that key) and cache the result.
+    /// Hits and misses are counted in `metrics`.
+    pub fn get_or_sign(
+        &self,
+        metrics: &Metrics,
+        public_key: &[u8],
+        metadata: DatasetVerification,
+        intent: IntentScope,
+        sign: impl FnOnce(DatasetVerification) -> SignedVerification,
+    ) -> SignedVerification {
+        let Some(entries) = &self.entries else {
+            return sign(metadata);
+        };
+
+        let key = Self::key(public_key, &metadata, intent);
+        if let Some(cached) = entries.lock().expect("cache lock poisoned").get(&key) {
+            metrics.signature_cache_total.with_label_values(&["hit"]).inc();
+            return cached.clone();
+        }
+
+        metrics.signature_cache_total.with_label_values(&["miss"]).inc();
+        let response = sign(metadata);
+        entries
+            .lock()
+            .expect("cache lock poisoned")
+            .put(key, response.clone());
+        response
+    }
+
+    /// Drop every cached response, e.g. after the signing key changed.
+    pub fn clear(&self) {
+        if let Some(entries) = &self.entries {
+            entries.lock().expect("cache lock poisoned").clear();
+        }
+    }
+}
+
+impl Default for SignatureCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_SIGNATURE_CACHE_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::to_signed_response;
+    use crate::DATASET_VERIFICATION_VERSION;
+    use crate::signer::Signer;
+    use fastcrypto::ed25519::Ed25519KeyPair;
+    use fastcrypto::traits::KeyPair;
+
+    fn metadata(id: &[u8]) -> DatasetVerification {
+        DatasetVerification {
+            version: DATASET_VERIFICATION_VERSION,
+            dataset_id: id.to_vec(),
+            name: b"data.csv".to_vec(),
+            description: b"Cache test".to_vec(),
+            format: b"CSV".to_vec(),
+            size: 1024,
+            original_hash: vec![0xAB; 32],
+            walrus_blob_id: b"blob".to_vec(),
+            seal_policy_id: b"policy".to_vec(),
+            timestamp: 1700000000000,
+            uploader: b"0xA".to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_identical_metadata_hits_cache() {
+        let kp = Ed25519KeyPair::generate(&mut rand::thread_rng());
+        let pk = kp.public_key_bytes();
+        let metrics = Metrics::new();
+        let cache = SignatureCache::new(1);
+        let sign = |m: DatasetVerification| {
+            let timestamp = m.timestamp;
+            to_signed_response(&kp, m, timestamp, IntentScope::VerifyMetadata)
+        };
+        let intent = IntentScope::VerifyMetadata;
+
+        let first = cache.get_or_sign(&metrics, &pk, metadata(b"a"), intent, sign);
+        let second = cache.get_or_sign(&metrics, &pk, metadata(b"a"), intent, |_| {
+            panic!("identical metadata must be served from the cache")
+        });
+        assert_eq!(first.signature, second.signature);
+
+        // Capacity 1: a different dataset evicts the first entry
+        cache.get_or_sign(&metrics, &pk, metadata(b"b"), intent, sign);
+        cache.get_or_sign(&metrics, &pk, metadata(b"a"), intent, sign);
+
+        let lookups = &metrics.signature_cache_total;
+        assert_eq!(lookups.with_label_values(&["hit"]).get(), 1);
+        assert_eq!(lookups.with_label_values(&["miss"]).get(), 3);
+    }
+
+    #[test]
+    fn test_key_depends_on_intent_scope() {
+        assert_ne!(
+            SignatureCache::key(b"pk", &metadata(b"a"), IntentScope::VerifyMetadata),
+            SignatureCache::key(b"pk", &metadata(b"a"), IntentScope::ProcessData)
+        );
+    }
+
+    #[test]
+    fn test_entry_signed_by_another_key_is_not_served() {
+        let old = Ed25519KeyPair::generate(&mut rand::thread_rng());
+        let new = Ed25519KeyPair::generate(&mut rand::thread_rng());
+        let metrics = Metrics::new();
+        let cache = SignatureCache::default();
+        let intent = IntentScope::VerifyMetadata;
+        let sign_with = |kp: &Ed25519KeyPair, m: DatasetVerification| {
+            let timestamp = m.timestamp;
+            to_signed_response(kp, m, timestamp, intent)
+        };
+
+        let (old_pk, new_pk) = (old.public_key_bytes(), new.public_key_bytes());
+
+        // A request still holding the old key inserts after the rotation
+        cache.get_or_sign(&metrics, &old_pk, metadata(b"a"), intent, |m| sign_with(&old, m));
+        let signed =
+            cache.get_or_sign(&metrics, &new_pk, metadata(b"a"), intent, |m| sign_with(&new, m));
+        let bytes = crate::common::signing_bytes(&signed.response);
+        assert!(new.verify(&bytes, &hex::decode(&signed.signature).unwrap()));
+    }
+}
@@ -0,0 +1,195 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Machine-readable description of what this enclave accepts, so clients can
+//! discover supported inputs and limits without trial and error.
+
+use crate::common::IntentScope;
+use crate::decompress::Decompression;
+use crate::format::SNIFFABLE_FORMATS;
+use crate::hashing::HashAlgorithm;
+use crate::limits::{FieldLimits, Limits};
+use crate::{AppState, METADATA_INTENT_SCOPES};
+use axum::extract::State;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CapabilitiesResponse {
+    /// Scheme of the current signing key, e.g. `ed25519`
+    pub signature_scheme: String,
+    /// Values accepted as `hash_algorithm`
+    pub hash_algorithms: Vec<String>,
+    /// Digest `verify_metadata` requires `original_hash` to match the length
+    /// of; null when any non-empty hash is accepted
+    pub metadata_hash_algorithm: Option<String>,
+    /// Values accepted as `schema_version`
+    pub schema_versions: Vec<String>,
+    /// Dataset formats recognized by content sniffing
+    pub formats: Vec<String>,
+    /// Values accepted as `decompress`
+    pub decompressions: Vec<String>,
+    /// Every scope the enclave signs under
+    pub intent_scopes: Vec<IntentScopeInfo>,
+    /// Scopes a client may select for dataset metadata
+    pub metadata_intent_scopes: Vec<IntentScopeInfo>,
+    /// Type tags `/sign_intent` accepts
+    pub payload_types: Vec<String>,
+    pub limits: LimitsInfo,
+    pub fetch: FetchInfo,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IntentScopeInfo {
+    pub name: String,
+    pub value: u8,
+}
+
+impl From<IntentScope> for IntentScopeInfo {
+    fn from(scope: IntentScope) -> Self {
+        Self {
+            name: format!("{:?}", scope),
+            value: scope as u8,
+        }
+    }
+}
+
+/// `Limits` with durations flattened to plain numbers.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LimitsInfo {
+    pub max_dataset_bytes: u64,
+    pub min_dataset_bytes: u64,
+    pub max_batch_items: usize,
+    pub max_batch_fetch_concurrency: usize,
+    pub max_batch_total_bytes: u64,
+    pub max_bcs_item_bytes: usize,
+    pub max_body_bytes: usize,
+    pub max_batch_body_bytes: usize,
+    pub max_expected_hash_hex_len: usize,
+    /// Null when timestamps are not checked for freshness
+    pub timestamp_freshness_ms: Option<u64>,
+    /// Null when only establishing the connection is bounded
+    pub fetch_timeout_secs: Option<u64>,
+    pub fields: FieldLimitsInfo,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FieldLimitsInfo {
+    pub max_dataset_id_len: usize,
+    pub max_name_len: usize,
+    pub max_description_len: usize,
+    pub max_format_len: usize,
+    pub max_original_hash_len: usize,
+    pub max_walrus_blob_id_len: usize,
+    pub max_seal_policy_id_len: usize,
+    pub max_uploader_len: usize,
+}
+
+impl From<&Limits> for LimitsInfo {
+    fn from(limits: &Limits) -> Self {
+        Self {
+            max_dataset_bytes: limits.max_dataset_bytes,
+            min_dataset_bytes: limits.min_dataset_bytes,
+            max_batch_items: limits.max_batch_items,
+            max_batch_fetch_concurrency: limits.max_batch_fetch_concurrency,
+            max_batch_total_bytes: limits.max_batch_total_bytes,
+            max_bcs_item_bytes: limits.max_bcs_item_bytes,
+            max_body_bytes: limits.max_body_bytes,
+            max_batch_body_bytes: limits.max_batch_body_bytes,
+            max_expected_hash_hex_len: limits.max_expected_hash_hex_len,
+            timestamp_freshness_ms: limits.timestamp_freshness_ms,
+            fetch_timeout_secs: limits.fetch_timeout.map(|timeout| timeout.as_secs()),
+            fields: (&limits.fields).into(),
+        }
+    }
+}
+
+impl From<&FieldLimits> for FieldLimitsInfo {
+    fn from(fields: &FieldLimits) -> Self {
+        Self {
+            max_dataset_id_len: fields.max_dataset_id_len,
+            max_name_len: fields.max_name_len,
+            max_description_len: fields.max_description_len,
+            max_format_len: fields.max_format_len,
+            max_original_hash_len: fields.max_original_hash_len,
+            max_walrus_blob_id_len: fields.max_walrus_blob_id_len,
+            max_seal_policy_id_len: fields.max_seal_policy_id_len,
+            max_uploader_len: fields.max_uploader_len,
+        }
+    }
+}
+
+/// Where datasets may be fetched from.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FetchInfo {
+    /// Whether Walrus blobs can be fetched through a client-chosen aggregator
+    pub walrus: bool,
+    /// Whether `ipfs://` dataset URLs are accepted
+    pub ipfs: bool,
+    /// Gateway `ipfs://` URLs are fetched through
+    pub ipfs_gateway: String,
+    /// Hosts dataset URLs are restricted to; empty when any public host is allowed
+    pub allowlist: Vec<String>,
+}
+
+/// Endpoint describing the accepted algorithms, schema versions, intent
+/// scopes and configured limits. Built from in-memory configuration only.
+pub async fn capabilities(State(state): State<Arc<AppState>>) -> Json<CapabilitiesResponse> {
+    Json(CapabilitiesResponse {
+        signature_scheme: state.signer.current().scheme().to_string(),
+        hash_algorithms: HashAlgorithm::ALL
+            .iter()
+            .map(|algorithm| algorithm.name().to_string())
+            .collect(),
+        metadata_hash_algorithm: state
+            .metadata_hash_algorithm
+            .map(|algorithm| algorithm.name().to_string()),
+        schema_versions: state.supported_schema_versions.clone(),
+        formats: SNIFFABLE_FORMATS.iter().map(|format| format.to_string()).collect(),
+        decompressions: Decompression::ALL.iter().map(|d| d.name().to_string()).collect(),
+        intent_scopes: IntentScope::ALL.iter().copied().map(Into::into).collect(),
+        metadata_intent_scopes: METADATA_INTENT_SCOPES.into_iter().map(Into::into).collect(),
+        payload_types: state.payload_types.type_tags().map(str::to_string).collect(),
+        limits: (&state.limits).into(),
+        fetch: FetchInfo {
+            walrus: true,
+            ipfs: true,
+            ipfs_gateway: state.ipfs_gateway.clone(),
+            allowlist: state.url_policy.allowlist.clone(),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fastcrypto::ed25519::Ed25519KeyPair;
+    use fastcrypto::traits::KeyPair;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_capabilities_reflect_configuration() {
+        let mut state = AppState::new(
+            Ed25519KeyPair::generate(&mut rand::thread_rng()),
+            "test-key".to_string(),
+        );
+        state.supported_schema_versions.push("v2.0".to_string());
+        state.limits.fetch_timeout = Some(Duration::from_secs(30));
+        state.limits.timestamp_freshness_ms = None;
+
+        let Json(capabilities) = capabilities(State(Arc::new(state))).await;
+        assert_eq!(capabilities.signature_scheme, "ed25519");
+        assert_eq!(capabilities.hash_algorithms.len(), HashAlgorithm::ALL.len());
+        assert_eq!(capabilities.metadata_hash_algorithm.as_deref(), Some("sha256"));
+        assert_eq!(capabilities.schema_versions, ["v1.0", "v2.0"]);
+        assert_eq!(capabilities.intent_scopes.len(), IntentScope::ALL.len());
+        assert_eq!(capabilities.intent_scopes[1].name, "VerifyMetadata");
+        assert_eq!(capabilities.intent_scopes[1].value, 1);
+        assert_eq!(capabilities.limits.fetch_timeout_secs, Some(30));
+
+        let json = serde_json::to_value(&capabilities).unwrap();
+        assert!(json["limits"]["timestamp_freshness_ms"].is_null());
+        assert_eq!(json["fetch"]["ipfs_gateway"], crate::ipfs::DEFAULT_IPFS_GATEWAY);
+    }
+}
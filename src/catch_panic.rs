@@ -0,0 +1,101 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Recovery from panics in handlers and middleware.
+//!
+//! Without it, a panic unwinds through the connection task and the client
+//! sees the connection drop. `catch_panic` turns it into the usual
+//! `generic_error` 500 JSON and logs the panic message with the request ID.
+//! The message is only logged, never sent to the client.
+
+use crate::request_id::REQUEST_ID_HEADER;
+use crate::EnclaveError;
+use axum::extract::Request;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use futures::FutureExt;
+use std::any::Any;
+use std::panic::AssertUnwindSafe;
+use tracing::error;
+
+/// Middleware answering a panicking request with a 500 `EnclaveError`.
+pub async fn catch_panic(request: Request, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("-")
+        .to_string();
+    let path = request.uri().path().to_string();
+
+    match AssertUnwindSafe(next.run(request)).catch_unwind().await {
+        Ok(response) => response,
+        Err(panic) => {
+            error!(
+                request_id = %request_id,
+                path = %path,
+                "Handler panicked: {}",
+                panic_message(panic.as_ref())
+            );
+            EnclaveError::GenericError("internal error while handling the request".to_string())
+                .into_response()
+        }
+    }
+}
+
+/// The message `panic!` was given, when it is a string.
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message
+    } else {
+        "<non-string panic payload>"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use axum::routing::get;
+    use axum::{middleware, Router};
+    use tower::ServiceExt;
+
+    async fn out_of_bounds() -> &'static str {
+        let items: Vec<&str> = Vec::new();
+        items[3]
+    }
+
+    fn app() -> Router {
+        Router::new()
+            .route("/panic", get(out_of_bounds))
+            .route("/health", get(|| async { "OK" }))
+            .layer(middleware::from_fn(catch_panic))
+    }
+
+    #[tokio::test]
+    async fn test_panicking_handler_returns_500_json() {
+        let request = Request::get("/panic").body(Body::empty()).unwrap();
+        let response = app().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "generic_error");
+        assert_eq!(body["error"], "internal error while handling the request");
+
+        // Other requests are unaffected
+        let request = Request::get("/health").body(Body::empty()).unwrap();
+        let response = app().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_panic_message() {
+        assert_eq!(panic_message(&"static"), "static");
+        assert_eq!(panic_message(&"owned".to_string()), "owned");
+        assert_eq!(panic_message(&42), "<non-string panic payload>");
+    }
+}
@@ -0,0 +1,57 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Wall-clock source for signed timestamps, freshness windows and dedup
+//! windows. Handlers read the time through `AppState::clock`, so tests can
+//! replace it with a `ManualClock` instead of depending on real time.
+
+use crate::{current_timestamp_ms, EnclaveError};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Source of the current time.
+pub trait Clock: Send + Sync {
+    /// Milliseconds since the Unix epoch.
+    fn now_millis(&self) -> Result<u64, EnclaveError>;
+}
+
+/// The system clock, used by default.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_millis(&self) -> Result<u64, EnclaveError> {
+        current_timestamp_ms()
+    }
+}
+
+/// A clock that stands still until set or advanced.
+#[derive(Debug, Default)]
+pub struct ManualClock {
+    now_ms: AtomicU64,
+}
+
+impl ManualClock {
+    pub fn new(now_ms: u64) -> Self {
+        Self { now_ms: AtomicU64::new(now_ms) }
+    }
+
+    pub fn set(&self, now_ms: u64) {
+        self.now_ms.store(now_ms, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, ms: u64) {
+        self.now_ms.fetch_add(ms, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now_millis(&self) -> Result<u64, EnclaveError> {
+        Ok(self.now_ms.load(Ordering::SeqCst))
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for std::sync::Arc<C> {
+    fn now_millis(&self) -> Result<u64, EnclaveError> {
+        (**self).now_millis()
+    }
+}
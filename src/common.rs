@@ -3,10 +3,16 @@
 
 use crate::AppState;
 use crate::EnclaveError;
-use axum::{extract::State, Json};
-use fastcrypto::traits::Signer;
-use fastcrypto::{encoding::Encoding, traits::ToFromBytes};
-use fastcrypto::{encoding::Hex, traits::KeyPair as FcKeyPair};
+use axum::{
+    extract::{Query, State},
+    http::{header::CONTENT_TYPE, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use crate::key_rotation::{self, KeyRing};
+use crate::signer::Signer;
+use crate::text::decode_hex;
+use fastcrypto::encoding::{Base64, Encoding, Hex};
 use nsm_api::api::{Request as NsmRequest, Response as NsmResponse};
 use nsm_api::driver;
 use reqwest::Client;
@@ -14,17 +20,17 @@ use serde::{Deserialize, Serialize};
 use serde_bytes::ByteBuf;
 use serde_repr::Deserialize_repr;
 use serde_repr::Serialize_repr;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Debug;
 use std::sync::Arc;
 use std::time::Duration;
 use tracing::info;
 
-use fastcrypto::ed25519::Ed25519KeyPair;
 /// ==== COMMON TYPES ====
 /// Intent message wrapper struct containing the intent scope and timestamp.
 /// This standardizes the serialized payload for signing.
-#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IntentMessage<T: Serialize> {
     pub intent: IntentScope,
     pub timestamp_ms: u64,
@@ -33,10 +39,122 @@ pub struct IntentMessage<T: Serialize> {
 
 /// Intent scope enum. Add new scope here if needed, each corresponds to a
 /// scope for signing. Replace in with your own intent per message type being signed by the enclave.
-#[derive(Serialize_repr, Deserialize_repr, Debug)]
+#[derive(Serialize_repr, Deserialize_repr, Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum IntentScope {
     ProcessData = 0,
+    /// Metadata-only verification (V3), no dataset fetched by the enclave.
+    VerifyMetadata = 1,
+    /// Merkle root committing to a collection of dataset verifications.
+    MerkleRoot = 2,
+    /// Receipt recording that the enclave rejected a request.
+    Rejection = 3,
+    /// Enclave time attested for a client digest.
+    Timestamp = 4,
+    /// One commitment over a whole batch of dataset verifications.
+    Batch = 5,
+    /// Dataset verification together with the `Content-Type` and final URL
+    /// it was served with.
+    Provenance = 6,
+    /// Dataset verification bound to the enclave's attestation document.
+    AttestedMetadata = 7,
+    /// Size a dataset URL reports via HTTP HEAD; the content is not hashed.
+    HeadMetadata = 8,
+    /// Scope of the stand-in payload type registered by `/sign_intent` tests.
+    #[cfg(test)]
+    TestPayload = 200,
+}
+
+impl IntentScope {
+    /// Every scope, in discriminant order.
+    pub const ALL: &'static [IntentScope] = &[
+        IntentScope::ProcessData,
+        IntentScope::VerifyMetadata,
+        IntentScope::MerkleRoot,
+        IntentScope::Rejection,
+        IntentScope::Timestamp,
+        IntentScope::Batch,
+        IntentScope::Provenance,
+        IntentScope::AttestedMetadata,
+        IntentScope::HeadMetadata,
+        #[cfg(test)]
+        IntentScope::TestPayload,
+    ];
+
+    /// Whether one of the enclave's own endpoints signs under this scope.
+    /// `/sign_intent` refuses to register a payload type under such a scope,
+    /// since a signature over the payload could pass as the endpoint's.
+    pub fn is_built_in(self) -> bool {
+        match self {
+            IntentScope::ProcessData
+            | IntentScope::VerifyMetadata
+            | IntentScope::MerkleRoot
+            | IntentScope::Rejection
+            | IntentScope::Timestamp
+            | IntentScope::Batch
+            | IntentScope::Provenance
+            | IntentScope::AttestedMetadata
+            | IntentScope::HeadMetadata => true,
+            #[cfg(test)]
+            IntentScope::TestPayload => false,
+        }
+    }
+
+    /// Resolve a scope selected by name or discriminant in a request.
+    pub fn from_selector(selector: &IntentScopeSelector) -> Result<Self, EnclaveError> {
+        let found = match selector {
+            IntentScopeSelector::Value(value) => {
+                Self::ALL.iter().copied().find(|scope| *scope as u8 == *value)
+            }
+            IntentScopeSelector::Name(name) => {
+                let normalized = name.trim().replace('_', "").to_ascii_lowercase();
+                Self::ALL
+                    .iter()
+                    .copied()
+                    .find(|scope| format!("{:?}", scope).to_ascii_lowercase() == normalized)
+            }
+        };
+        found.ok_or_else(|| {
+            EnclaveError::InvalidInput(format!("unknown intent_scope: {}", selector))
+        })
+    }
+}
+
+/// Serialized as its discriminant (`serde_repr`), so described as an integer
+/// enum rather than the derived string enum.
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for IntentScope {
+    fn schema_name() -> String {
+        "IntentScope".to_string()
+    }
+
+    fn json_schema(_: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        schemars::schema::SchemaObject {
+            instance_type: Some(schemars::schema::InstanceType::Integer.into()),
+            enum_values: Some(Self::ALL.iter().map(|scope| (*scope as u8).into()).collect()),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+/// Intent scope chosen by a client, either by name (`"verify_metadata"`,
+/// `"VerifyMetadata"`) or by its discriminant (`1`).
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum IntentScopeSelector {
+    Value(u8),
+    Name(String),
+}
+
+impl std::fmt::Display for IntentScopeSelector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IntentScopeSelector::Value(value) => write!(f, "{}", value),
+            IntentScopeSelector::Name(name) => write!(f, "{:?}", name),
+        }
+    }
 }
 
 impl<T: Serialize + Debug> IntentMessage<T> {
@@ -50,21 +168,93 @@ impl<T: Serialize + Debug> IntentMessage<T> {
 }
 
 /// Wrapper struct containing the response (the intent message) and signature.
-#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct ProcessedDataResponse<T> {
     pub response: T,
     pub signature: String,
+    /// Hex of the exact BCS bytes that were signed, when requested with
+    /// `?include_signed_bytes=true`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signed_bytes: Option<String>,
+    /// Base64 Sui serialized signature (scheme flag, signature, public key),
+    /// as Sui transactions take it, when requested with
+    /// `?include_sui_signature=true`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sui_signature: Option<String>,
+}
+
+impl<T: Serialize> ProcessedDataResponse<IntentMessage<T>> {
+    /// Attach the hex of the signed bytes when `include` is set.
+    pub fn with_signed_bytes(mut self, include: bool) -> Self {
+        if include {
+            self.signed_bytes = Some(Hex::encode(signing_bytes(&self.response)));
+        }
+        self
+    }
+
+    /// Attach the Sui serialized form of the signature when `include` is
+    /// set, with the public key of whichever key in `keys` made it.
+    pub fn with_sui_signature(
+        mut self,
+        include: bool,
+        keys: &KeyRing,
+    ) -> Result<Self, EnclaveError> {
+        if include {
+            let signature = Hex::decode(&self.signature)
+                .map_err(|e| EnclaveError::GenericError(format!("invalid signature hex: {}", e)))?;
+            let signer = keys
+                .signer_of(&signing_bytes(&self.response), &signature)?
+                .ok_or_else(|| {
+                    EnclaveError::GenericError("signature was not made by a known key".to_string())
+                })?;
+            let serialized = key_rotation::sui_signature(signer.as_ref(), &signature)?;
+            self.sui_signature = Some(Base64::encode(serialized));
+        }
+        Ok(self)
+    }
+}
+
+/// Query flags accepted by signing endpoints.
+#[derive(Debug, Default, Deserialize)]
+pub struct SigningQuery {
+    /// Return the hex of the exact signed bytes alongside the signature, so
+    /// clients can verify without re-encoding the BCS themselves
+    #[serde(default)]
+    pub include_signed_bytes: bool,
+    /// Also return the signature in Sui's serialized form, `sui_signature`
+    #[serde(default)]
+    pub include_sui_signature: bool,
+}
+
+impl SigningQuery {
+    /// `response` with the extras these flags request attached.
+    pub fn apply<T: Serialize>(
+        &self,
+        keys: &KeyRing,
+        response: ProcessedDataResponse<IntentMessage<T>>,
+    ) -> Result<ProcessedDataResponse<IntentMessage<T>>, EnclaveError> {
+        response
+            .with_signed_bytes(self.include_signed_bytes)
+            .with_sui_signature(self.include_sui_signature, keys)
+    }
 }
 
 /// Wrapper struct containing the request payload.
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ProcessDataRequest<T> {
     pub payload: T,
 }
 
-/// Sign the bcs bytes of the the payload with keypair.
+/// The exact bytes the enclave signs for an intent message.
+pub fn signing_bytes<T: Serialize>(intent_msg: &IntentMessage<T>) -> Vec<u8> {
+    bcs::to_bytes(intent_msg).expect("should not fail")
+}
+
+/// Sign the bcs bytes of the the payload with the enclave signer.
 pub fn to_signed_response<T: Serialize + Clone>(
-    kp: &Ed25519KeyPair,
+    signer: &dyn Signer,
     payload: T,
     timestamp_ms: u64,
     intent: IntentScope,
@@ -75,71 +265,292 @@ pub fn to_signed_response<T: Serialize + Clone>(
         data: payload.clone(),
     };
 
-    let signing_payload = bcs::to_bytes(&intent_msg).expect("should not fail");
-    let sig = kp.sign(&signing_payload);
+    let signing_payload = signing_bytes(&intent_msg);
+    let sig = signer.sign(&signing_payload);
     ProcessedDataResponse {
         response: intent_msg,
         signature: Hex::encode(sig),
+        signed_bytes: None,
+        sui_signature: None,
     }
 }
 
 /// ==== HEALTHCHECK, GET ATTESTASTION ENDPOINT IMPL ====
+/// Maximum nonce length accepted by the NSM in an attestation request.
+pub const NSM_MAX_NONCE_LEN: usize = 512;
+
+/// Prefix of the mock attestation document returned when no NSM is present.
+pub const MOCK_ATTESTATION_PREFIX: &[u8] = b"SEALTRUST-MOCK-ATTESTATION";
+
+/// Content type of the raw attestation document.
+pub const CBOR_CONTENT_TYPE: &str = "application/cbor";
+
+/// Response header marking a non-JSON attestation as a dev-server mock.
+pub const ATTESTATION_MOCK_HEADER: &str = "x-attestation-mock";
+
+/// Query parameters for get attestation.
+#[derive(Debug, Default, Deserialize)]
+pub struct AttestationQuery {
+    /// Hex encoded challenge nonce to embed in the attestation document.
+    pub nonce: Option<String>,
+    /// Output encoding: "json" (default), "hex" or "cbor".
+    pub format: Option<String>,
+}
+
+/// Output encoding of `/get_attestation`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttestationFormat {
+    /// `GetAttestationResponse` with the document hex encoded
+    Json,
+    /// Hex of the exact document bytes as `text/plain`
+    Hex,
+    /// The raw COSE_Sign1 CBOR document as `application/cbor`
+    Cbor,
+}
+
+impl AttestationFormat {
+    /// Parse the `format` query parameter, defaulting to `Json`.
+    pub fn parse(format: Option<&str>) -> Result<Self, EnclaveError> {
+        match format.map(|f| f.trim().to_ascii_lowercase()).as_deref() {
+            None | Some("json") => Ok(Self::Json),
+            Some("hex") => Ok(Self::Hex),
+            Some("cbor") => Ok(Self::Cbor),
+            Some(other) => Err(EnclaveError::InvalidInput(format!(
+                "unknown attestation format {:?} (expected json, hex or cbor)",
+                other
+            ))),
+        }
+    }
+}
+
 /// Response for get attestation.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GetAttestationResponse {
     /// Attestation document serialized in Hex.
     pub attestation: String,
+    /// Hex encoded nonce embedded in the document, echoed from the request.
+    pub nonce: Option<String>,
+    /// True when the document is a dev-server mock rather than a real NSM attestation.
+    pub mock: bool,
+}
+
+/// Attestation document as returned by the NSM (or the dev mock).
+pub(crate) struct Attestation {
+    pub(crate) document: Vec<u8>,
+    nonce: Option<Vec<u8>>,
+    pub(crate) mock: bool,
+}
+
+impl Attestation {
+    /// Encode the document in `format`. Hex and CBOR carry the exact document
+    /// bytes the Move verifier consumes.
+    fn into_response(self, format: AttestationFormat) -> Response {
+        let mock = [(ATTESTATION_MOCK_HEADER, self.mock.to_string())];
+        match format {
+            AttestationFormat::Json => Json(GetAttestationResponse {
+                attestation: Hex::encode(&self.document),
+                nonce: self.nonce.as_ref().map(Hex::encode),
+                mock: self.mock,
+            })
+            .into_response(),
+            AttestationFormat::Hex => {
+                (mock, [(CONTENT_TYPE, "text/plain")], Hex::encode(&self.document))
+                    .into_response()
+            }
+            AttestationFormat::Cbor => {
+                (mock, [(CONTENT_TYPE, CBOR_CONTENT_TYPE)], self.document).into_response()
+            }
+        }
+    }
 }
 
-/// Endpoint that returns an attestation committed
-/// to the enclave's public key.
+/// Endpoint that returns an attestation committed to the enclave's public
+/// key and an optional challenge nonce, encoded as `?format=json|hex|cbor`.
 pub async fn get_attestation(
     State(state): State<Arc<AppState>>,
-) -> Result<Json<GetAttestationResponse>, EnclaveError> {
+    Query(query): Query<AttestationQuery>,
+) -> Result<Response, EnclaveError> {
     info!("get attestation called");
 
-    let pk = state.eph_kp.public();
+    let result = AttestationFormat::parse(query.format.as_deref()).and_then(|format| {
+        let nonce = parse_nonce(query.nonce.as_deref())?;
+        request_attestation(&state, nonce).map(|attestation| attestation.into_response(format))
+    });
+    state.metrics.record("get_attestation", &result);
+    result
+}
+
+/// Decode and bound-check an optional hex nonce.
+fn parse_nonce(nonce: Option<&str>) -> Result<Option<Vec<u8>>, EnclaveError> {
+    let Some(nonce) = nonce else {
+        return Ok(None);
+    };
+    let bytes = decode_hex("nonce", nonce)?;
+    if bytes.is_empty() || bytes.len() > NSM_MAX_NONCE_LEN {
+        return Err(EnclaveError::InvalidInput(format!(
+            "nonce must be between 1 and {} bytes, got {}",
+            NSM_MAX_NONCE_LEN,
+            bytes.len()
+        )));
+    }
+    Ok(Some(bytes))
+}
+
+/// Request an attestation document from the NSM driver bound to the enclave public key.
+pub(crate) fn request_attestation(
+    state: &AppState,
+    nonce: Option<Vec<u8>>,
+) -> Result<Attestation, EnclaveError> {
+    let pk = state.signer.current().public_key_bytes();
     let fd = driver::nsm_init();
 
-    // Send attestation request to NSM driver with public key set.
+    if fd < 0 {
+        if !state.allow_mock_attestation {
+            return Err(EnclaveError::AttestationUnavailable(
+                "NSM device not available".to_string(),
+            ));
+        }
+        // Dev server without NSM: clearly marked mock committing to the same inputs.
+        let mut document = MOCK_ATTESTATION_PREFIX.to_vec();
+        document.extend_from_slice(&pk);
+        document.extend_from_slice(nonce.as_deref().unwrap_or_default());
+        return Ok(Attestation {
+            document,
+            nonce,
+            mock: true,
+        });
+    }
+
+    // Send attestation request to NSM driver with public key and nonce set.
     let request = NsmRequest::Attestation {
         user_data: None,
-        nonce: None,
-        public_key: Some(ByteBuf::from(pk.as_bytes().to_vec())),
+        nonce: nonce.clone().map(ByteBuf::from),
+        public_key: Some(ByteBuf::from(pk)),
     };
 
     let response = driver::nsm_process_request(fd, request);
     match response {
         NsmResponse::Attestation { document } => {
             driver::nsm_exit(fd);
-            Ok(Json(GetAttestationResponse {
-                attestation: Hex::encode(document),
-            }))
+            Ok(Attestation {
+                document,
+                nonce,
+                mock: false,
+            })
         }
         _ => {
             driver::nsm_exit(fd);
-            Err(EnclaveError::GenericError(
+            Err(EnclaveError::AttestationUnavailable(
                 "unexpected response".to_string(),
             ))
         }
     }
 }
 
+/// PCR indices reported by `/version`: enclave image, kernel and application.
+pub const VERSION_PCR_INDICES: [u16; 3] = [0, 1, 2];
+
+/// Build information for the running server.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VersionResponse {
+    /// Crate version
+    pub version: String,
+    /// Git commit the binary was built from, or "unknown"
+    pub git_commit: String,
+    /// Hex encoded PCR values by index; null when no NSM device is present
+    pub pcrs: Option<BTreeMap<u16, String>>,
+}
+
+/// Endpoint reporting the crate version, git commit and, inside an enclave,
+/// the PCR measurements an attestation document commits to.
+pub async fn version() -> Json<VersionResponse> {
+    Json(VersionResponse {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_commit: env!("SEALTRUST_GIT_COMMIT").to_string(),
+        pcrs: describe_pcrs(),
+    })
+}
+
+/// Read the `VERSION_PCR_INDICES` from the NSM. `None` without an NSM device.
+fn describe_pcrs() -> Option<BTreeMap<u16, String>> {
+    let fd = driver::nsm_init();
+    if fd < 0 {
+        return None;
+    }
+
+    let mut pcrs = BTreeMap::new();
+    for index in VERSION_PCR_INDICES {
+        match driver::nsm_process_request(fd, NsmRequest::DescribePCR { index }) {
+            NsmResponse::DescribePCR { data, .. } => {
+                pcrs.insert(index, Hex::encode(data));
+            }
+            response => info!("Failed to describe PCR{}: {:?}", index, response),
+        }
+    }
+    driver::nsm_exit(fd);
+    Some(pcrs)
+}
+
+/// Liveness probe response.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LivenessResponse {
+    /// Always "ok" while the server is running
+    pub status: String,
+}
+
+/// Liveness endpoint: answers as long as the server runs, without checking
+/// any dependency.
+pub async fn health() -> Json<LivenessResponse> {
+    Json(LivenessResponse {
+        status: "ok".to_string(),
+    })
+}
+
 /// Health check response.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct HealthCheckResponse {
+    /// "healthy" when all critical checks pass, "unhealthy" otherwise, and
+    /// "draining" once shutdown has begun.
+    pub status: String,
+    /// Whether the signing keypair is loaded and produces verifiable signatures.
+    pub keypair_loaded: bool,
+    /// Whether the configured canary URL is reachable; null when none is configured.
+    pub network_ok: Option<bool>,
+    /// Milliseconds since the app state was created.
+    pub uptime_ms: u64,
     /// Hex encoded public key booted on enclave.
     pub pk: String,
     /// Status of endpoint connectivity checks
     pub endpoints_status: HashMap<String, bool>,
 }
 
-/// Endpoint that health checks the enclave connectivity to all
-/// domains and returns the enclave's public key.
+/// Readiness endpoint: checks the signing keypair, outbound connectivity to
+/// the canary URL and all allowed domains, and returns the enclave's public
+/// key. Responds with 503 when a critical check fails, or without probing
+/// anything while the server drains for shutdown.
 pub async fn health_check(
     State(state): State<Arc<AppState>>,
-) -> Result<Json<HealthCheckResponse>, EnclaveError> {
-    let pk = state.eph_kp.public();
+) -> Result<(StatusCode, Json<HealthCheckResponse>), EnclaveError> {
+    let signer = state.signer.current();
+    let pk = signer.public_key_bytes();
+
+    // The keypair is usable if a probe signature verifies against the public key
+    const PROBE: &[u8] = b"sealtrust-health-check";
+    let keypair_loaded = signer.verify(PROBE, &signer.sign(PROBE));
+
+    if state.is_draining() {
+        return Ok((
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(HealthCheckResponse {
+                status: "draining".to_string(),
+                keypair_loaded,
+                network_ok: None,
+                uptime_ms: state.started_at.elapsed().as_millis() as u64,
+                pk: Hex::encode(&pk),
+                endpoints_status: HashMap::new(),
+            }),
+        ));
+    }
 
     // Create HTTP client with timeout
     let client = Client::builder()
@@ -214,8 +625,166 @@ pub async fn health_check(
         }
     };
 
-    Ok(Json(HealthCheckResponse {
-        pk: Hex::encode(pk.as_bytes()),
-        endpoints_status,
-    }))
+    let network_ok = match &state.health_canary_url {
+        Some(url) => Some(match client.get(url).send().await {
+            Ok(response) => response.status().is_success(),
+            Err(e) => {
+                info!("Failed to reach canary {}: {}", url, e);
+                false
+            }
+        }),
+        None => None,
+    };
+
+    let healthy = keypair_loaded && network_ok.unwrap_or(true);
+    let status = if healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    Ok((
+        status,
+        Json(HealthCheckResponse {
+            status: if healthy { "healthy" } else { "unhealthy" }.to_string(),
+            keypair_loaded,
+            network_ok,
+            uptime_ms: state.started_at.elapsed().as_millis() as u64,
+            pk: Hex::encode(&pk),
+            endpoints_status,
+        }),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_health_returns_json() {
+        use axum::body::Body;
+        use axum::http::{header::CONTENT_TYPE, Request};
+        use axum::routing::get;
+        use axum::Router;
+        use tower::ServiceExt;
+
+        let app = Router::new().route("/health", get(health));
+        let request = Request::get("/health").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(bytes.as_ref(), br#"{"status":"ok"}"#);
+    }
+
+    #[tokio::test]
+    async fn test_version_reports_crate_version() {
+        let Json(response) = version().await;
+        assert_eq!(response.version, env!("CARGO_PKG_VERSION"));
+        assert!(!response.git_commit.is_empty());
+    }
+
+    #[test]
+    fn test_intent_scope_selector() {
+        let select = |json: &str| {
+            IntentScope::from_selector(&serde_json::from_str::<IntentScopeSelector>(json).unwrap())
+        };
+        assert_eq!(select("0").unwrap(), IntentScope::ProcessData);
+        assert_eq!(select("1").unwrap(), IntentScope::VerifyMetadata);
+        assert_eq!(select(r#""verify_metadata""#).unwrap(), IntentScope::VerifyMetadata);
+        assert_eq!(select(r#""MerkleRoot""#).unwrap(), IntentScope::MerkleRoot);
+        assert_eq!(select("7").unwrap(), IntentScope::AttestedMetadata);
+        assert_eq!(select(r#""head_metadata""#).unwrap(), IntentScope::HeadMetadata);
+        assert!(select("255").is_err());
+        assert!(select(r#""sign_anything""#).is_err());
+    }
+
+    #[test]
+    fn test_attestation_format() {
+        assert_eq!(AttestationFormat::parse(None).unwrap(), AttestationFormat::Json);
+        assert_eq!(AttestationFormat::parse(Some("HEX")).unwrap(), AttestationFormat::Hex);
+        assert_eq!(AttestationFormat::parse(Some("cbor")).unwrap(), AttestationFormat::Cbor);
+        let err = AttestationFormat::parse(Some("pem")).unwrap_err();
+        assert!(err.message().starts_with("unknown attestation format \"pem\""));
+    }
+
+    #[tokio::test]
+    async fn test_attestation_encodings_carry_the_same_document() {
+        use fastcrypto::ed25519::Ed25519KeyPair;
+        use fastcrypto::traits::KeyPair;
+
+        let mut state = AppState::new(
+            Ed25519KeyPair::generate(&mut rand::thread_rng()),
+            "test-key".to_string(),
+        );
+        state.allow_mock_attestation = true;
+        let state = Arc::new(state);
+        let fetch = |format: &str| {
+            let query = AttestationQuery {
+                nonce: Some("c0ffee".to_string()),
+                format: Some(format.to_string()),
+            };
+            get_attestation(State(state.clone()), Query(query))
+        };
+        let body = |response: Response| async move {
+            axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap()
+        };
+
+        let json: GetAttestationResponse =
+            serde_json::from_slice(&body(fetch("json").await.unwrap()).await).unwrap();
+        // Only meaningful without an NSM device, where the mock is served
+        if !json.mock {
+            return;
+        }
+        let hex = fetch("hex").await.unwrap();
+        assert_eq!(hex.headers()[ATTESTATION_MOCK_HEADER], "true");
+        assert_eq!(body(hex).await.as_ref(), json.attestation.as_bytes());
+
+        let cbor = fetch("cbor").await.unwrap();
+        assert_eq!(cbor.headers()[CONTENT_TYPE], CBOR_CONTENT_TYPE);
+        assert_eq!(Hex::encode(body(cbor).await), json.attestation);
+
+        assert!(matches!(fetch("xml").await, Err(EnclaveError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_parse_nonce() {
+        assert_eq!(parse_nonce(None).unwrap(), None);
+        assert_eq!(parse_nonce(Some("c0ffee")).unwrap(), Some(vec![0xc0, 0xff, 0xee]));
+        assert!(parse_nonce(Some("not-hex")).is_err());
+        assert!(parse_nonce(Some("")).is_err());
+        assert!(parse_nonce(Some(&"ab".repeat(NSM_MAX_NONCE_LEN))).is_ok());
+        assert!(parse_nonce(Some(&"ab".repeat(NSM_MAX_NONCE_LEN + 1))).is_err());
+    }
+
+    #[test]
+    fn test_sui_signature_layout() {
+        use fastcrypto::ed25519::Ed25519KeyPair;
+        use fastcrypto::traits::KeyPair;
+        use std::time::Duration;
+
+        let keys = KeyRing::new(Ed25519KeyPair::generate(&mut rand::thread_rng()));
+        let signer = keys.current();
+        let signed = to_signed_response(signer.as_ref(), 7u64, 1, IntentScope::Timestamp);
+        let query = SigningQuery { include_sui_signature: true, ..Default::default() };
+        // Still attributed to the signing key after it is rotated out
+        keys.rotate(Ed25519KeyPair::generate(&mut rand::thread_rng()), Duration::from_secs(60))
+            .unwrap();
+        let signed = query.apply(&keys, signed).unwrap();
+
+        // flag || signature || public key, as Sui serializes an Ed25519 signature
+        let sui = Base64::decode(signed.sui_signature.as_deref().unwrap()).unwrap();
+        let signature = Hex::decode(&signed.signature).unwrap();
+        assert_eq!(sui.len(), 1 + 64 + 32);
+        assert_eq!(sui[0], 0x00);
+        assert_eq!(sui[1..65], signature[..]);
+        assert_eq!(sui[65..], signer.public_key_bytes()[..]);
+        assert!(signed.signed_bytes.is_none());
+
+        // Only added on request
+        let signed = to_signed_response(signer.as_ref(), 7u64, 1, IntentScope::Timestamp);
+        let signed = SigningQuery::default().apply(&keys, signed).unwrap();
+        assert!(signed.sui_signature.is_none());
+    }
 }
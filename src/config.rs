@@ -0,0 +1,647 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Startup configuration, read and validated once before the server starts.
+//!
+//! Every setting is an environment variable. Variables that are not set fall
+//! back to the JSON file named by `CONFIG_FILE`, an object of variable names
+//! to values, e.g. `{"PORT": 8080, "ALLOWED_FORMATS": ["CSV", "PARQUET"]}`.
+//! Arrays are joined with commas and objects (such as `CSV_SCHEMAS`) are
+//! passed on as JSON. A bad value stops startup with an error naming the
+//! variable, and so does an unknown name in the file.
+
+use crate::cors::{parse_allowed_origins, AllowedOrigins};
+use crate::dedup::{PolicyBindings, RecentHashes, DEFAULT_DEDUP_CAPACITY};
+use crate::fetch::{build_http_client, FetchLimiter, ProxyConfig};
+use crate::hashing::HashAlgorithm;
+use crate::idempotency::{IdempotencyCache, DEFAULT_IDEMPOTENCY_CAPACITY};
+use crate::key_rotation::DEFAULT_KEY_GRACE_PERIOD;
+use crate::limits::Limits;
+use crate::logging::{self, LogFormat};
+use crate::rate_limit::{RateLimiter, DEFAULT_RATE_LIMIT_CAPACITY};
+use crate::server::{self, ServerConfig};
+use crate::url_policy::IpRange;
+use crate::webhook::Webhook;
+use crate::{audit, cache, rejections, signer, AppState};
+use anyhow::{anyhow, bail};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Variable naming the optional JSON config file.
+pub const CONFIG_FILE_VAR: &str = "CONFIG_FILE";
+/// Bind host when `HOST` is not set.
+pub const DEFAULT_HOST: &str = "127.0.0.1";
+/// Bind port when `PORT` is not set.
+pub const DEFAULT_PORT: u16 = 3000;
+
+/// Every variable read at startup. Anything else in a config file is a typo.
+pub const CONFIG_VARS: &[&str] = &[
+    "HOST", "PORT", "API_KEY", "ENCLAVE_KEY_FILE", "RUST_LOG", "LOG_LEVEL", "LOG_FORMAT",
+    "REJECT_CONTROL_CHARS", "COLLECT_VALIDATION_ERRORS", "STRICT_WALRUS_BLOB_ID",
+    "HEALTH_CANARY_URL", "FETCH_ALLOWLIST", "FETCH_PERMITTED_IP_RANGES", "IPFS_GATEWAY",
+    "FETCH_PROXY", "HTTPS_PROXY", "https_proxy", "HTTP_PROXY", "http_proxy",
+    "FETCH_PROXY_USERNAME", "FETCH_PROXY_PASSWORD", "MAX_REDIRECTS", "METADATA_HASH_ALGORITHM",
+    "WEBHOOK_URL", "WEBHOOK_SECRET", "WEBHOOK_TIMEOUT_SECS", "SUPPORTED_SCHEMA_VERSIONS",
+    "ALLOWED_FORMATS", "CSV_SCHEMAS", "MAX_DATASET_BYTES", "MIN_DATASET_BYTES",
+    "MAX_BATCH_ITEMS", "MAX_BATCH_FETCH_CONCURRENCY", "MAX_BATCH_TOTAL_BYTES",
+    "MAX_BCS_ITEM_BYTES", "MAX_BODY_BYTES", "MAX_BATCH_BODY_BYTES", "MAX_EXPECTED_HASH_HEX_LEN",
+    "TIMESTAMP_FRESHNESS_MS",
+    "FETCH_TIMEOUT_SECS", "SIGNATURE_CACHE_SIZE", "IDEMPOTENCY_TTL_SECS", "IDEMPOTENCY_CAPACITY",
+    "AUDIT_LOG_SIZE", "REJECTION_LOG_SIZE", "DEDUP_WINDOW_MS", "DEDUP_CAPACITY",
+    "REJECTION_FLOOR_MS", "MAX_CONCURRENT_FETCHES", "FETCH_PERMIT_TIMEOUT_SECS",
+    "FETCH_QUEUE_DEPTH", "POLICY_BINDING_CAPACITY", "RATE_LIMIT_PER_SEC", "RATE_LIMIT_BURST",
+    "KEY_ROTATION_INTERVAL_SECS", "KEY_ROTATION_GRACE_SECS", "CORS_ALLOWED_ORIGINS", "HTTP2",
+    "KEEP_ALIVE_INTERVAL_SECS", "KEEP_ALIVE_TIMEOUT_SECS", "MAX_CONCURRENT_STREAMS",
+    "TLS_CERT_PATH", "TLS_KEY_PATH", "SHUTDOWN_DRAIN_SECS",
+];
+
+/// Variables that only refine another one, and are refused without it.
+const DEPENDENT_VARS: [(&str, &str); 7] = [
+    ("RATE_LIMIT_BURST", "RATE_LIMIT_PER_SEC"),
+    ("KEY_ROTATION_GRACE_SECS", "KEY_ROTATION_INTERVAL_SECS"),
+    ("DEDUP_CAPACITY", "DEDUP_WINDOW_MS"),
+    ("IDEMPOTENCY_CAPACITY", "IDEMPOTENCY_TTL_SECS"),
+    ("WEBHOOK_SECRET", "WEBHOOK_URL"),
+    ("WEBHOOK_TIMEOUT_SECS", "WEBHOOK_URL"),
+    ("FETCH_PROXY_PASSWORD", "FETCH_PROXY_USERNAME"),
+];
+
+/// PEM certificate chain and private key served over HTTPS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsPaths {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+/// Every startup setting. `None` keeps the `AppState::new` default.
+#[derive(Clone)]
+pub struct Config {
+    /// Address to bind, from `HOST` and `PORT`
+    pub listen: SocketAddr,
+    /// Key protected endpoints require; `None` leaves them unauthenticated
+    pub api_key: Option<String>,
+    /// DEV ONLY file persisting the signing key across restarts
+    pub key_file: Option<PathBuf>,
+    /// `RUST_LOG` directives, taking precedence over `log_level`
+    pub rust_log: Option<String>,
+    /// Level logged when `RUST_LOG` is unset
+    pub log_level: Option<String>,
+    pub log_format: LogFormat,
+    pub reject_control_chars: Option<bool>,
+    pub collect_validation_errors: Option<bool>,
+    pub strict_walrus_blob_id: Option<bool>,
+    pub health_canary_url: Option<String>,
+    pub fetch_allowlist: Option<Vec<String>>,
+    pub permitted_ip_ranges: Option<Vec<IpRange>>,
+    pub ipfs_gateway: Option<String>,
+    pub proxy: Option<ProxyConfig>,
+    pub max_redirects: Option<usize>,
+    /// `Some(None)` disables the `original_hash` length check
+    pub metadata_hash_algorithm: Option<Option<HashAlgorithm>>,
+    pub webhook: Option<Webhook>,
+    pub supported_schema_versions: Option<Vec<String>>,
+    pub allowed_formats: Option<Vec<String>>,
+    pub csv_schemas: Option<HashMap<String, Vec<String>>>,
+    pub limits: Limits,
+    pub signature_cache_size: Option<usize>,
+    /// Replay window and capacity of the `Idempotency-Key` cache
+    pub idempotency: Option<(Duration, usize)>,
+    pub audit_log_size: Option<usize>,
+    pub rejection_log_size: Option<usize>,
+    /// Window in milliseconds and capacity of the `original_hash` dedup
+    pub dedup: Option<(u64, NonZeroUsize)>,
+    pub rejection_floor: Option<Duration>,
+    pub max_concurrent_fetches: Option<usize>,
+    pub fetch_permit_timeout: Option<Duration>,
+    pub fetch_queue_depth: Option<usize>,
+    pub policy_binding_capacity: Option<NonZeroUsize>,
+    /// Requests per second and burst allowed per client
+    pub rate_limit: Option<(f64, u32)>,
+    /// Interval between signing key rotations and grace period of retired keys
+    pub key_rotation: Option<(Duration, Duration)>,
+    /// `None` leaves the choice to the caller (permissive in `--dev`)
+    pub cors_allowed_origins: Option<AllowedOrigins>,
+    pub server: ServerConfig,
+    pub tls: Option<TlsPaths>,
+    /// Time `/health_check` reports "draining" after SIGTERM before exiting
+    pub shutdown_drain: Duration,
+}
+
+impl Config {
+    /// Configuration from the process environment, falling back to the file
+    /// named by `CONFIG_FILE`; see [`Config::from_vars`].
+    pub fn from_env() -> anyhow::Result<Self> {
+        let file = match std::env::var(CONFIG_FILE_VAR) {
+            Ok(path) => {
+                let contents = std::fs::read_to_string(&path)
+                    .map_err(|e| anyhow!("cannot read {} {:?}: {}", CONFIG_FILE_VAR, path, e))?;
+                parse_config_file(&contents)
+                    .map_err(|e| anyhow!("invalid {} {:?}: {}", CONFIG_FILE_VAR, path, e))?
+            }
+            Err(_) => HashMap::new(),
+        };
+        Self::from_vars(|name| std::env::var(name).ok().or_else(|| file.get(name).cloned()))
+    }
+
+    /// Configuration from the variables listed in [`CONFIG_VARS`], read
+    /// through `var`.
+    pub fn from_vars(var: impl Fn(&str) -> Option<String>) -> anyhow::Result<Self> {
+        for (dependent, required) in DEPENDENT_VARS {
+            if var(dependent).is_some() && var(required).is_none() {
+                bail!("{} is set but {} is missing", dependent, required);
+            }
+        }
+
+        let host = var("HOST").unwrap_or_else(|| DEFAULT_HOST.to_string());
+        let ip: IpAddr = parse("HOST", &host)?;
+        let port = match var("PORT") {
+            Some(port) => parse("PORT", &port)?,
+            None => DEFAULT_PORT,
+        };
+
+        let rust_log = var("RUST_LOG");
+        let log_level = var("LOG_LEVEL");
+        logging::env_filter(rust_log.as_deref(), log_level.as_deref())?;
+        let log_format = LogFormat::parse(var("LOG_FORMAT").as_deref())?;
+
+        let metadata_hash_algorithm = match var("METADATA_HASH_ALGORITHM") {
+            Some(value) => Some(match value.trim() {
+                "" | "none" => None,
+                name => Some(HashAlgorithm::parse(Some(name)).map_err(|e| {
+                    anyhow!("invalid METADATA_HASH_ALGORITHM {:?}: {}", value, e.message())
+                })?),
+            }),
+            None => None,
+        };
+        let csv_schemas = match var("CSV_SCHEMAS") {
+            Some(value) => Some(
+                serde_json::from_str(&value)
+                    .map_err(|e| anyhow!("invalid CSV_SCHEMAS {:?}: {}", value, e))?,
+            ),
+            None => None,
+        };
+        let permitted_ip_ranges = match var("FETCH_PERMITTED_IP_RANGES") {
+            Some(value) => Some(
+                list(&value)
+                    .iter()
+                    .map(|range| IpRange::parse(range))
+                    .collect::<Result<_, _>>()
+                    .map_err(|e| anyhow!("invalid FETCH_PERMITTED_IP_RANGES: {}", e.message()))?,
+            ),
+            None => None,
+        };
+
+        let idempotency = match var("IDEMPOTENCY_TTL_SECS") {
+            Some(ttl) => {
+                let capacity = match var("IDEMPOTENCY_CAPACITY") {
+                    Some(capacity) => parse("IDEMPOTENCY_CAPACITY", &capacity)?,
+                    None => DEFAULT_IDEMPOTENCY_CAPACITY,
+                };
+                Some((secs("IDEMPOTENCY_TTL_SECS", &ttl)?, capacity))
+            }
+            None => None,
+        };
+        let dedup = match var("DEDUP_WINDOW_MS") {
+            Some(window) => {
+                let capacity = match var("DEDUP_CAPACITY") {
+                    Some(capacity) => parse("DEDUP_CAPACITY", &capacity)?,
+                    None => DEFAULT_DEDUP_CAPACITY,
+                };
+                let capacity = NonZeroUsize::new(capacity)
+                    .ok_or_else(|| anyhow!("DEDUP_CAPACITY must be positive"))?;
+                Some((parse("DEDUP_WINDOW_MS", &window)?, capacity))
+            }
+            None => None,
+        };
+        let policy_binding_capacity = match var("POLICY_BINDING_CAPACITY") {
+            Some(value) => Some(
+                NonZeroUsize::new(parse("POLICY_BINDING_CAPACITY", &value)?)
+                    .ok_or_else(|| anyhow!("POLICY_BINDING_CAPACITY must be positive"))?,
+            ),
+            None => None,
+        };
+        let rate_limit = match var("RATE_LIMIT_PER_SEC") {
+            Some(value) => {
+                let requests_per_sec: f64 = parse("RATE_LIMIT_PER_SEC", &value)?;
+                if !(requests_per_sec.is_finite() && requests_per_sec > 0.0) {
+                    bail!("RATE_LIMIT_PER_SEC must be positive, got {}", value);
+                }
+                let burst = match var("RATE_LIMIT_BURST") {
+                    Some(burst) => parse("RATE_LIMIT_BURST", &burst)?,
+                    None => requests_per_sec.ceil() as u32,
+                };
+                Some((requests_per_sec, burst))
+            }
+            None => None,
+        };
+        let key_rotation = match var("KEY_ROTATION_INTERVAL_SECS") {
+            Some(value) => {
+                let interval = secs("KEY_ROTATION_INTERVAL_SECS", &value)?;
+                if interval.is_zero() {
+                    bail!("KEY_ROTATION_INTERVAL_SECS must be positive");
+                }
+                let grace_period = match var("KEY_ROTATION_GRACE_SECS") {
+                    Some(grace) => secs("KEY_ROTATION_GRACE_SECS", &grace)?,
+                    None => DEFAULT_KEY_GRACE_PERIOD,
+                };
+                Some((interval, grace_period))
+            }
+            None => None,
+        };
+        let cors_allowed_origins = match var("CORS_ALLOWED_ORIGINS") {
+            Some(value) => Some(
+                parse_allowed_origins(&value)
+                    .map_err(|e| anyhow!("invalid CORS_ALLOWED_ORIGINS: {}", e))?,
+            ),
+            None => None,
+        };
+
+        let mut server = ServerConfig::default();
+        if let Some(value) = var("HTTP2") {
+            server.http2 = parse("HTTP2", &value)?;
+        }
+        if let Some(value) = var("KEEP_ALIVE_INTERVAL_SECS") {
+            let interval = secs("KEEP_ALIVE_INTERVAL_SECS", &value)?;
+            server.keep_alive_interval = (!interval.is_zero()).then_some(interval);
+        }
+        if let Some(value) = var("KEEP_ALIVE_TIMEOUT_SECS") {
+            server.keep_alive_timeout = secs("KEEP_ALIVE_TIMEOUT_SECS", &value)?;
+        }
+        if let Some(value) = var("MAX_CONCURRENT_STREAMS") {
+            server.max_concurrent_streams = parse("MAX_CONCURRENT_STREAMS", &value)?;
+        }
+        let tls = match (var("TLS_CERT_PATH"), var("TLS_KEY_PATH")) {
+            (Some(cert), Some(key)) => Some(TlsPaths { cert: cert.into(), key: key.into() }),
+            (None, None) => None,
+            _ => bail!("TLS_CERT_PATH and TLS_KEY_PATH must be set together"),
+        };
+
+        Ok(Self {
+            listen: SocketAddr::new(ip, port),
+            api_key: var("API_KEY").filter(|key| !key.is_empty()),
+            key_file: var("ENCLAVE_KEY_FILE").map(PathBuf::from),
+            rust_log,
+            log_level,
+            log_format,
+            reject_control_chars: optional(&var, "REJECT_CONTROL_CHARS")?,
+            collect_validation_errors: optional(&var, "COLLECT_VALIDATION_ERRORS")?,
+            strict_walrus_blob_id: optional(&var, "STRICT_WALRUS_BLOB_ID")?,
+            health_canary_url: var("HEALTH_CANARY_URL"),
+            fetch_allowlist: var("FETCH_ALLOWLIST").map(|value| list(&value)),
+            permitted_ip_ranges,
+            ipfs_gateway: var("IPFS_GATEWAY"),
+            proxy: ProxyConfig::from_vars(&var)?,
+            max_redirects: optional(&var, "MAX_REDIRECTS")?,
+            metadata_hash_algorithm,
+            webhook: Webhook::from_vars(&var)?,
+            supported_schema_versions: var("SUPPORTED_SCHEMA_VERSIONS").map(|value| list(&value)),
+            allowed_formats: var("ALLOWED_FORMATS").map(|value| list(&value)),
+            csv_schemas,
+            limits: Limits::from_vars(&var)?,
+            signature_cache_size: optional(&var, "SIGNATURE_CACHE_SIZE")?,
+            idempotency,
+            audit_log_size: optional(&var, "AUDIT_LOG_SIZE")?,
+            rejection_log_size: optional(&var, "REJECTION_LOG_SIZE")?,
+            dedup,
+            rejection_floor: optional(&var, "REJECTION_FLOOR_MS")?.map(Duration::from_millis),
+            max_concurrent_fetches: optional(&var, "MAX_CONCURRENT_FETCHES")?,
+            fetch_permit_timeout: optional(&var, "FETCH_PERMIT_TIMEOUT_SECS")?
+                .map(Duration::from_secs),
+            fetch_queue_depth: optional(&var, "FETCH_QUEUE_DEPTH")?,
+            policy_binding_capacity,
+            rate_limit,
+            key_rotation,
+            cors_allowed_origins,
+            server,
+            tls,
+            shutdown_drain: optional(&var, "SHUTDOWN_DRAIN_SECS")?
+                .map_or(server::DEFAULT_SHUTDOWN_DRAIN, Duration::from_secs),
+        })
+    }
+
+    /// App state signing with `signer`, with every configured setting
+    /// applied over the `AppState::new` defaults.
+    pub fn build_state(&self, signer: impl signer::Signer + 'static) -> AppState {
+        let mut state = match &self.api_key {
+            Some(api_key) => AppState::new(signer, api_key.clone()),
+            None => {
+                let mut state = AppState::new(signer, "local-dev-key".to_string());
+                state.require_api_key = false;
+                state
+            }
+        };
+        if let Some(value) = self.reject_control_chars {
+            state.reject_control_chars = value;
+        }
+        if let Some(value) = self.collect_validation_errors {
+            state.collect_validation_errors = value;
+        }
+        if let Some(value) = self.strict_walrus_blob_id {
+            state.strict_walrus_blob_id = value;
+        }
+        state.health_canary_url = self.health_canary_url.clone();
+        if let Some(allowlist) = &self.fetch_allowlist {
+            state.url_policy.allowlist = allowlist.clone();
+        }
+        if let Some(ranges) = &self.permitted_ip_ranges {
+            state.url_policy.permitted_ip_ranges = ranges.clone();
+        }
+        state.url_policy.proxy_host = self.proxy.as_ref().and_then(ProxyConfig::host);
+        if let Some(gateway) = &self.ipfs_gateway {
+            state.ipfs_gateway = gateway.clone();
+        }
+        // Rebuilt so the clients' resolver enforces FETCH_PERMITTED_IP_RANGES
+        let pinned = Some(&state.url_policy);
+        state.http_client = build_http_client(true, self.proxy.as_ref(), pinned);
+        state.raw_http_client = build_http_client(false, self.proxy.as_ref(), pinned);
+        state.webhook_client = build_http_client(false, self.proxy.as_ref(), None);
+        if let Some(max_redirects) = self.max_redirects {
+            state.max_redirects = max_redirects;
+        }
+        if let Some(algorithm) = self.metadata_hash_algorithm {
+            state.metadata_hash_algorithm = algorithm;
+        }
+        state.webhook = self.webhook.clone();
+        if let Some(versions) = &self.supported_schema_versions {
+            state.supported_schema_versions = versions.clone();
+        }
+        if let Some(formats) = &self.allowed_formats {
+            state.allowed_formats = formats.clone();
+        }
+        if let Some(schemas) = &self.csv_schemas {
+            state.csv_schemas = schemas.clone();
+        }
+        state.limits = self.limits.clone();
+        if let Some(size) = self.signature_cache_size {
+            state.signature_cache = cache::SignatureCache::new(size);
+        }
+        if let Some((ttl, capacity)) = self.idempotency {
+            state.idempotency = IdempotencyCache::new(ttl, capacity);
+        }
+        if let Some(size) = self.audit_log_size {
+            state.audit_log = audit::AuditLog::new(size);
+        }
+        if let Some(size) = self.rejection_log_size {
+            state.rejection_log = rejections::RejectionLog::new(size);
+        }
+        if let Some((window_ms, capacity)) = self.dedup {
+            state.recent_hashes = Some(RecentHashes::new(window_ms, capacity));
+        }
+        state.rejection_floor = self.rejection_floor;
+        if let Some(max_concurrent) = self.max_concurrent_fetches {
+            let wait_timeout = state.fetch_limiter.wait_timeout;
+            state.fetch_limiter = FetchLimiter::new(max_concurrent, wait_timeout);
+        }
+        if let Some(timeout) = self.fetch_permit_timeout {
+            state.fetch_limiter.wait_timeout = timeout;
+        }
+        if let Some(depth) = self.fetch_queue_depth {
+            state.fetch_limiter.max_queued = depth;
+        }
+        if let Some(capacity) = self.policy_binding_capacity {
+            state.policy_bindings = Some(PolicyBindings::new(capacity));
+        }
+        if let Some((requests_per_sec, burst)) = self.rate_limit {
+            let capacity =
+                NonZeroUsize::new(DEFAULT_RATE_LIMIT_CAPACITY).expect("non-zero capacity");
+            state.rate_limiter = Some(RateLimiter::new(requests_per_sec, burst, capacity));
+        }
+        state
+    }
+}
+
+/// Variable names and values of a JSON config file.
+pub fn parse_config_file(contents: &str) -> anyhow::Result<HashMap<String, String>> {
+    let Value::Object(entries) = serde_json::from_str(contents)? else {
+        bail!("expected a JSON object of variable names to values");
+    };
+    let mut vars = HashMap::new();
+    for (name, value) in entries {
+        if !CONFIG_VARS.contains(&name.as_str()) {
+            bail!("unknown setting {:?}", name);
+        }
+        let value = match value {
+            Value::Null => continue,
+            Value::String(value) => value,
+            Value::Array(items) => items
+                .into_iter()
+                .map(|item| match item {
+                    Value::String(item) => item,
+                    other => other.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(","),
+            other => other.to_string(),
+        };
+        vars.insert(name, value);
+    }
+    Ok(vars)
+}
+
+fn parse<T: std::str::FromStr>(name: &str, value: &str) -> anyhow::Result<T>
+where
+    T::Err: std::fmt::Display,
+{
+    value
+        .trim()
+        .parse()
+        .map_err(|e| anyhow!("invalid {} {:?}: {}", name, value, e))
+}
+
+fn optional<T: std::str::FromStr>(
+    var: &impl Fn(&str) -> Option<String>,
+    name: &str,
+) -> anyhow::Result<Option<T>>
+where
+    T::Err: std::fmt::Display,
+{
+    var(name).map(|value| parse(name, &value)).transpose()
+}
+
+pub(crate) fn secs(name: &str, value: &str) -> anyhow::Result<Duration> {
+    parse(name, value).map(Duration::from_secs)
+}
+
+/// Trimmed, non-empty entries of a comma-separated list.
+fn list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|entry| entry.trim().to_string())
+        .filter(|entry| !entry.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fastcrypto::ed25519::Ed25519KeyPair;
+    use fastcrypto::traits::KeyPair;
+
+    fn config(vars: &[(&str, &str)]) -> anyhow::Result<Config> {
+        let vars: HashMap<_, _> = vars.iter().copied().collect();
+        Config::from_vars(|name| vars.get(name).map(|value| value.to_string()))
+    }
+
+    fn error(vars: &[(&str, &str)]) -> String {
+        match config(vars) {
+            Ok(_) => panic!("expected {:?} to be rejected", vars),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn keypair() -> Ed25519KeyPair {
+        Ed25519KeyPair::generate(&mut rand::thread_rng())
+    }
+
+    #[test]
+    fn test_empty_config_keeps_defaults() {
+        let config = config(&[]).unwrap();
+        assert_eq!(config.listen, "127.0.0.1:3000".parse().unwrap());
+        assert_eq!(config.server, ServerConfig::default());
+        assert_eq!(config.shutdown_drain, server::DEFAULT_SHUTDOWN_DRAIN);
+        assert!(config.tls.is_none() && config.rate_limit.is_none());
+
+        let state = config.build_state(keypair());
+        let defaults = AppState::new(keypair(), "local-dev-key".to_string());
+        assert!(!state.require_api_key);
+        assert_eq!(state.limits, defaults.limits);
+        assert_eq!(state.max_redirects, defaults.max_redirects);
+        assert_eq!(state.metadata_hash_algorithm, defaults.metadata_hash_algorithm);
+        assert_eq!(state.supported_schema_versions, defaults.supported_schema_versions);
+        assert!(state.rate_limiter.is_none() && state.webhook.is_none());
+    }
+
+    #[test]
+    fn test_valid_config_is_applied_to_state() {
+        let config = config(&[
+            ("HOST", "0.0.0.0"),
+            ("PORT", "8080"),
+            ("API_KEY", "secret"),
+            ("FETCH_ALLOWLIST", "data.example.com, https://cdn.example.com/ ,"),
+            ("FETCH_PERMITTED_IP_RANGES", "10.1.0.0/16"),
+            ("FETCH_PROXY", "http://proxy.internal:3128"),
+            ("METADATA_HASH_ALGORITHM", "none"),
+            ("ALLOWED_FORMATS", "CSV,PARQUET"),
+            ("CSV_SCHEMAS", r#"{"v1.0": ["id", "label"]}"#),
+            ("MAX_DATASET_BYTES", "2048"),
+            ("DEDUP_WINDOW_MS", "60000"),
+            ("RATE_LIMIT_PER_SEC", "2.5"),
+            ("REJECTION_FLOOR_MS", "250"),
+            ("KEEP_ALIVE_INTERVAL_SECS", "0"),
+            ("TLS_CERT_PATH", "cert.pem"),
+            ("TLS_KEY_PATH", "key.pem"),
+        ])
+        .unwrap();
+        assert_eq!(config.listen, "0.0.0.0:8080".parse().unwrap());
+        assert_eq!(config.rate_limit, Some((2.5, 3)));
+        assert_eq!(config.server.keep_alive_interval, None);
+        assert_eq!(
+            config.tls,
+            Some(TlsPaths { cert: "cert.pem".into(), key: "key.pem".into() })
+        );
+
+        let state = config.build_state(keypair());
+        assert!(state.require_api_key);
+        assert_eq!(state.api_key, "secret");
+        assert_eq!(
+            state.url_policy.allowlist,
+            ["data.example.com", "https://cdn.example.com/"]
+        );
+        assert_eq!(state.url_policy.permitted_ip_ranges.len(), 1);
+        assert_eq!(state.url_policy.proxy_host.as_deref(), Some("proxy.internal"));
+        assert_eq!(state.metadata_hash_algorithm, None);
+        assert_eq!(state.allowed_formats, ["CSV", "PARQUET"]);
+        assert_eq!(state.csv_schemas["v1.0"], ["id", "label"]);
+        assert_eq!(state.limits.max_dataset_bytes, 2048);
+        assert!(state.recent_hashes.is_some() && state.rate_limiter.is_some());
+        assert_eq!(state.rejection_floor, Some(Duration::from_millis(250)));
+    }
+
+    #[test]
+    fn test_missing_required_settings_are_rejected() {
+        assert_eq!(
+            error(&[("TLS_CERT_PATH", "cert.pem")]),
+            "TLS_CERT_PATH and TLS_KEY_PATH must be set together"
+        );
+        assert_eq!(
+            error(&[("RATE_LIMIT_BURST", "10")]),
+            "RATE_LIMIT_BURST is set but RATE_LIMIT_PER_SEC is missing"
+        );
+        assert_eq!(
+            error(&[("WEBHOOK_SECRET", "s3cret")]),
+            "WEBHOOK_SECRET is set but WEBHOOK_URL is missing"
+        );
+    }
+
+    #[test]
+    fn test_invalid_values_name_the_variable() {
+        assert_eq!(
+            error(&[("PORT", "http")]),
+            "invalid PORT \"http\": invalid digit found in string"
+        );
+        assert!(error(&[("HOST", "localhost")]).starts_with("invalid HOST \"localhost\""));
+        assert!(error(&[("REJECT_CONTROL_CHARS", "yes")])
+            .starts_with("invalid REJECT_CONTROL_CHARS \"yes\""));
+        assert!(error(&[("MAX_DATASET_BYTES", "-1")]).starts_with("invalid MAX_DATASET_BYTES"));
+        assert!(error(&[("METADATA_HASH_ALGORITHM", "md5")])
+            .starts_with("invalid METADATA_HASH_ALGORITHM \"md5\""));
+        assert!(error(&[("CSV_SCHEMAS", "[id]")]).starts_with("invalid CSV_SCHEMAS"));
+        assert!(error(&[("FETCH_PERMITTED_IP_RANGES", "10.0.0.0/33")])
+            .starts_with("invalid FETCH_PERMITTED_IP_RANGES"));
+        assert!(error(&[("LOG_FORMAT", "xml")]).starts_with("LOG_FORMAT must be text or json"));
+        assert_eq!(
+            error(&[("KEY_ROTATION_INTERVAL_SECS", "0")]),
+            "KEY_ROTATION_INTERVAL_SECS must be positive"
+        );
+        assert_eq!(
+            error(&[("RATE_LIMIT_PER_SEC", "0")]),
+            "RATE_LIMIT_PER_SEC must be positive, got 0"
+        );
+        assert_eq!(
+            error(&[("DEDUP_WINDOW_MS", "1000"), ("DEDUP_CAPACITY", "0")]),
+            "DEDUP_CAPACITY must be positive"
+        );
+        let webhook = [("WEBHOOK_URL", "https://hooks.example.com"), ("WEBHOOK_TIMEOUT_SECS", "5s")];
+        assert_eq!(
+            error(&webhook),
+            "invalid WEBHOOK_TIMEOUT_SECS \"5s\": invalid digit found in string"
+        );
+    }
+
+    #[test]
+    fn test_config_file_values() {
+        let file = parse_config_file(
+            r#"{
+                "PORT": 8080,
+                "HTTP2": false,
+                "ALLOWED_FORMATS": ["CSV", "PARQUET"],
+                "CSV_SCHEMAS": {"v1.0": ["id"]},
+                "HEALTH_CANARY_URL": null
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(file["PORT"], "8080");
+        assert_eq!(file["HTTP2"], "false");
+        assert_eq!(file["ALLOWED_FORMATS"], "CSV,PARQUET");
+        assert_eq!(file["CSV_SCHEMAS"], r#"{"v1.0":["id"]}"#);
+        assert!(!file.contains_key("HEALTH_CANARY_URL"));
+
+        // The environment takes precedence over the file
+        let env: HashMap<_, _> = [("PORT", "9090")].into_iter().collect();
+        let config = Config::from_vars(|name| {
+            env.get(name).map(|value| value.to_string()).or_else(|| file.get(name).cloned())
+        })
+        .unwrap();
+        assert_eq!(config.listen.port(), 9090);
+        assert!(!config.server.http2);
+        assert_eq!(config.csv_schemas.unwrap()["v1.0"], ["id"]);
+
+        let err = parse_config_file(r#"{"MAX_DATASET_BYTE": 10}"#).unwrap_err();
+        assert_eq!(err.to_string(), "unknown setting \"MAX_DATASET_BYTE\"");
+        assert!(parse_config_file("[1, 2]").is_err());
+    }
+}
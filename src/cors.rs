@@ -0,0 +1,95 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::auth::API_KEY_HEADER;
+use anyhow::{anyhow, bail};
+use axum::http::header::{AUTHORIZATION, CONTENT_TYPE};
+use axum::http::{HeaderName, HeaderValue, Method};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+
+/// Origins allowed to make cross-origin requests.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AllowedOrigins {
+    /// Any origin (`*`): only for development
+    Any,
+    /// Explicit list of origins
+    List(Vec<HeaderValue>),
+}
+
+/// Parse a comma-separated origin list such as
+/// `https://app.sealtrust.xyz,https://staging.sealtrust.xyz`, or `*` for any origin.
+/// Every entry must be a valid `http(s)://host[:port]` header value.
+pub fn parse_allowed_origins(value: &str) -> anyhow::Result<AllowedOrigins> {
+    if value.trim() == "*" {
+        return Ok(AllowedOrigins::Any);
+    }
+
+    let mut origins = Vec::new();
+    for origin in value.split(',').map(str::trim).filter(|o| !o.is_empty()) {
+        let Some(host) = origin
+            .strip_prefix("https://")
+            .or_else(|| origin.strip_prefix("http://"))
+        else {
+            bail!("CORS origin must start with http:// or https://: {}", origin);
+        };
+        if host.is_empty() || host.contains('/') {
+            bail!("CORS origin must not contain a path: {}", origin);
+        }
+        let header = HeaderValue::from_str(origin)
+            .map_err(|e| anyhow!("Invalid CORS origin {}: {}", origin, e))?;
+        origins.push(header);
+    }
+    if origins.is_empty() {
+        bail!("CORS origin list is empty");
+    }
+    Ok(AllowedOrigins::List(origins))
+}
+
+/// Build the CORS layer for the given origins.
+pub fn cors_layer(origins: AllowedOrigins) -> CorsLayer {
+    match origins {
+        AllowedOrigins::Any => CorsLayer::new()
+            .allow_origin(Any)
+            .allow_methods(Any)
+            .allow_headers(Any),
+        AllowedOrigins::List(origins) => CorsLayer::new()
+            .allow_origin(AllowOrigin::list(origins))
+            .allow_methods([Method::GET, Method::POST, Method::OPTIONS])
+            .allow_headers([
+                CONTENT_TYPE,
+                AUTHORIZATION,
+                HeaderName::from_static(API_KEY_HEADER),
+            ]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_wildcard() {
+        assert_eq!(parse_allowed_origins("*").unwrap(), AllowedOrigins::Any);
+    }
+
+    #[test]
+    fn test_parse_origin_list() {
+        let origins =
+            parse_allowed_origins("https://app.example.com, http://localhost:5173").unwrap();
+        assert_eq!(
+            origins,
+            AllowedOrigins::List(vec![
+                HeaderValue::from_static("https://app.example.com"),
+                HeaderValue::from_static("http://localhost:5173"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_origins() {
+        assert!(parse_allowed_origins("app.example.com").is_err());
+        assert!(parse_allowed_origins("https://app.example.com/path").is_err());
+        assert!(parse_allowed_origins("https://bad\norigin").is_err());
+        assert!(parse_allowed_origins(" , ").is_err());
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::EnclaveError;
+use std::io::Read;
+
+/// Compression a dataset file is stored with, undone before hashing so that
+/// `original_hash` covers the decompressed content. This is independent of
+/// any HTTP `Content-Encoding`, which the fetch already decodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Decompression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Decompression {
+    /// All supported values, in the order they are advertised.
+    pub const ALL: [Decompression; 3] =
+        [Decompression::None, Decompression::Gzip, Decompression::Zstd];
+
+    /// Parse an optional `decompress` value from a request, defaulting to none.
+    pub fn parse(name: Option<&str>) -> Result<Self, EnclaveError> {
+        match name.map(str::to_ascii_lowercase).as_deref() {
+            None | Some("none") => Ok(Decompression::None),
+            Some("gzip") => Ok(Decompression::Gzip),
+            Some("zstd") => Ok(Decompression::Zstd),
+            Some(_) => Err(EnclaveError::InvalidInput(format!(
+                "Unsupported decompress: {} (expected none, gzip or zstd)",
+                name.unwrap_or_default()
+            ))),
+        }
+    }
+
+    /// Canonical lowercase name of the compression.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Decompression::None => "none",
+            Decompression::Gzip => "gzip",
+            Decompression::Zstd => "zstd",
+        }
+    }
+
+    /// Decompress `data`, failing with `PayloadTooLarge` as soon as the output
+    /// exceeds `max_bytes` so a small compressed bomb cannot exhaust memory.
+    pub fn decompress(&self, data: &[u8], max_bytes: u64) -> Result<Vec<u8>, EnclaveError> {
+        let reader: Box<dyn Read + '_> = match self {
+            Decompression::None => return Ok(data.to_vec()),
+            Decompression::Gzip => Box::new(flate2::read::GzDecoder::new(data)),
+            Decompression::Zstd => {
+                let decoder = zstd::stream::read::Decoder::new(data).map_err(|e| {
+                    EnclaveError::InvalidInput(format!("dataset is not valid zstd: {}", e))
+                })?;
+                Box::new(decoder)
+            }
+        };
+
+        let mut decompressed = Vec::new();
+        reader
+            .take(max_bytes.saturating_add(1))
+            .read_to_end(&mut decompressed)
+            .map_err(|e| {
+                EnclaveError::InvalidInput(format!("dataset is not valid {}: {}", self.name(), e))
+            })?;
+        if decompressed.len() as u64 > max_bytes {
+            return Err(EnclaveError::PayloadTooLarge(format!(
+                "decompressed dataset exceeds the size limit of {} bytes",
+                max_bytes
+            )));
+        }
+        Ok(decompressed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Decompression::parse(None).unwrap(), Decompression::None);
+        assert_eq!(Decompression::parse(Some("ZSTD")).unwrap(), Decompression::Zstd);
+        assert_eq!(Decompression::parse(Some("gzip")).unwrap(), Decompression::Gzip);
+        assert!(Decompression::parse(Some("brotli")).is_err());
+    }
+
+    #[test]
+    fn test_zstd_and_gzip_round_trip() {
+        let original = b"id,label,value\n1,dog,100\n2,cat,200\n".repeat(100);
+
+        let zstd = zstd::encode_all(&original[..], 0).unwrap();
+        assert_eq!(Decompression::Zstd.decompress(&zstd, 1 << 20).unwrap(), original);
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&original).unwrap();
+        let gzip = encoder.finish().unwrap();
+        assert_eq!(Decompression::Gzip.decompress(&gzip, 1 << 20).unwrap(), original);
+
+        let err = Decompression::Zstd.decompress(b"plain text", 1 << 20).unwrap_err();
+        assert!(matches!(err, EnclaveError::InvalidInput(_)));
+    }
+
+    #[test]
+    fn test_decompression_bomb_is_bounded() {
+        let bomb = zstd::encode_all(&vec![0u8; 1 << 20][..], 19).unwrap();
+        assert!(bomb.len() < 1024);
+
+        let limit = 64 * 1024;
+        let err = Decompression::Zstd.decompress(&bomb, limit).unwrap_err();
+        assert!(matches!(err, EnclaveError::PayloadTooLarge(_)));
+        assert!(Decompression::Zstd.decompress(&bomb, 1 << 20).is_ok());
+    }
+}
@@ -0,0 +1,140 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::EnclaveError;
+use lru::LruCache;
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+
+/// Default window within which a re-verified `original_hash` is rejected: 24 hours.
+pub const DEFAULT_DEDUP_WINDOW_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// Default number of recently verified hashes remembered.
+pub const DEFAULT_DEDUP_CAPACITY: usize = 100_000;
+
+/// Bounded record of recently signed `original_hash` values, used to refuse
+/// double registration of the same dataset. Once full, the least recently
+/// seen hash is forgotten first.
+pub struct RecentHashes {
+    window_ms: u64,
+    entries: Mutex<LruCache<Vec<u8>, u64>>,
+}
+
+impl RecentHashes {
+    pub fn new(window_ms: u64, capacity: NonZeroUsize) -> Self {
+        Self {
+            window_ms,
+            entries: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+
+    /// Reject `hash` if it was recorded within the window before `now_ms`,
+    /// otherwise record it as seen at `now_ms`.
+    pub fn check_and_record(&self, hash: &[u8], now_ms: u64) -> Result<(), EnclaveError> {
+        let mut entries = self.entries.lock().expect("dedup lock poisoned");
+        if let Some(&seen_ms) = entries.get(hash) {
+            if now_ms.saturating_sub(seen_ms) < self.window_ms {
+                return Err(EnclaveError::Conflict("dataset already verified".to_string()));
+            }
+        }
+        entries.put(hash.to_vec(), now_ms);
+        Ok(())
+    }
+}
+
+impl Default for RecentHashes {
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_DEDUP_WINDOW_MS,
+            NonZeroUsize::new(DEFAULT_DEDUP_CAPACITY).expect("non-zero capacity"),
+        )
+    }
+}
+
+/// Default number of `seal_policy_id` to `dataset_id` bindings remembered.
+pub const DEFAULT_POLICY_BINDING_CAPACITY: usize = 100_000;
+
+/// Bounded record of which `dataset_id` each `seal_policy_id` was first
+/// signed with, used to refuse reusing one access policy across datasets.
+/// Once full, the least recently used binding is forgotten first.
+pub struct PolicyBindings {
+    entries: Mutex<LruCache<Vec<u8>, Vec<u8>>>,
+}
+
+impl PolicyBindings {
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        Self {
+            entries: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+
+    /// Reject `seal_policy_id` if it is bound to a different dataset,
+    /// otherwise bind it to `dataset_id`.
+    pub fn check_and_bind(
+        &self,
+        seal_policy_id: &[u8],
+        dataset_id: &[u8],
+    ) -> Result<(), EnclaveError> {
+        let mut entries = self.entries.lock().expect("policy bindings lock poisoned");
+        if let Some(bound) = entries.get(seal_policy_id) {
+            if bound.as_slice() != dataset_id {
+                return Err(EnclaveError::Conflict(format!(
+                    "seal_policy_id {:?} is already bound to dataset_id {:?}, not {:?}",
+                    String::from_utf8_lossy(seal_policy_id),
+                    String::from_utf8_lossy(bound),
+                    String::from_utf8_lossy(dataset_id)
+                )));
+            }
+            return Ok(());
+        }
+        entries.put(seal_policy_id.to_vec(), dataset_id.to_vec());
+        Ok(())
+    }
+}
+
+impl Default for PolicyBindings {
+    fn default() -> Self {
+        Self::new(NonZeroUsize::new(DEFAULT_POLICY_BINDING_CAPACITY).expect("non-zero capacity"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_is_rejected_within_window() {
+        let recent = RecentHashes::new(1000, NonZeroUsize::new(2).unwrap());
+
+        assert!(recent.check_and_record(b"hash-a", 10_000).is_ok());
+        let err = recent.check_and_record(b"hash-a", 10_999).unwrap_err();
+        assert_eq!(err.code(), "conflict");
+
+        // Outside the window the hash may be verified again
+        assert!(recent.check_and_record(b"hash-a", 11_000).is_ok());
+    }
+
+    #[test]
+    fn test_capacity_is_bounded() {
+        let recent = RecentHashes::new(1000, NonZeroUsize::new(1).unwrap());
+
+        assert!(recent.check_and_record(b"hash-a", 0).is_ok());
+        assert!(recent.check_and_record(b"hash-b", 0).is_ok());
+        // hash-a was evicted to make room for hash-b
+        assert!(recent.check_and_record(b"hash-a", 0).is_ok());
+    }
+
+    #[test]
+    fn test_policy_cannot_be_rebound_to_another_dataset() {
+        let bindings = PolicyBindings::new(NonZeroUsize::new(8).unwrap());
+
+        assert!(bindings.check_and_bind(b"policy-1", b"dataset-1").is_ok());
+        assert!(bindings.check_and_bind(b"policy-1", b"dataset-1").is_ok());
+        assert!(bindings.check_and_bind(b"policy-2", b"dataset-1").is_ok());
+
+        let err = bindings.check_and_bind(b"policy-1", b"dataset-2").unwrap_err();
+        assert_eq!(err.code(), "conflict");
+        assert!(err.message().contains("\"dataset-1\""));
+        assert!(err.message().contains("\"dataset-2\""));
+    }
+}
@@ -0,0 +1,92 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! DEV ONLY: persist the ephemeral keypair of the local server across restarts
+//! so its on-chain registration stays valid during development. A real enclave
+//! must always generate its key inside the enclave and never write it to disk.
+
+use crate::text::decode_hex;
+use anyhow::{anyhow, Context};
+use fastcrypto::ed25519::{Ed25519KeyPair, Ed25519PrivateKey};
+use fastcrypto::encoding::{Encoding, Hex};
+use fastcrypto::traits::{KeyPair, ToFromBytes};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tracing::warn;
+
+/// Marker stored in the key file so it is never mistaken for a production key.
+pub const DEV_KEY_WARNING: &str =
+    "DEV ONLY - persisted SealTrust enclave key. NEVER use this key in a real enclave.";
+
+/// On-disk format of a persisted dev key.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DevKeyFile {
+    pub warning: String,
+    /// Hex encoded Ed25519 private key
+    pub private_key: String,
+}
+
+/// Load the keypair stored at `path`, or generate one and write it there if
+/// the file does not exist yet.
+pub fn load_or_generate(path: &Path) -> anyhow::Result<Ed25519KeyPair> {
+    warn!(
+        "Using persisted DEV keypair at {}. Persisted keys must NEVER be used in a real enclave!",
+        path.display()
+    );
+
+    if path.exists() {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read key file {}", path.display()))?;
+        let key_file: DevKeyFile = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse key file {}", path.display()))?;
+        let bytes = decode_hex("private key", &key_file.private_key)
+            .map_err(|e| anyhow!("{} in {}", e, path.display()))?;
+        let private_key = Ed25519PrivateKey::from_bytes(&bytes)
+            .map_err(|e| anyhow!("Invalid private key in {}: {}", path.display(), e))?;
+        return Ok(Ed25519KeyPair::from(private_key));
+    }
+
+    let kp = Ed25519KeyPair::generate(&mut rand::thread_rng());
+    let key_file = DevKeyFile {
+        warning: DEV_KEY_WARNING.to_string(),
+        private_key: Hex::encode(kp.copy().private().as_bytes()),
+    };
+    write_private(path, &serde_json::to_string_pretty(&key_file)?)
+        .with_context(|| format!("Failed to write key file {}", path.display()))?;
+    Ok(kp)
+}
+
+/// Write `content` to `path`, readable only by the owner on unix.
+fn write_private(path: &Path, content: &str) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(content.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_or_generate_is_stable() {
+        let path = std::env::temp_dir().join(format!("sealtrust-dev-key-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let first = load_or_generate(&path).expect("should generate key");
+        let second = load_or_generate(&path).expect("should reload key");
+        assert_eq!(first.public(), second.public());
+
+        let stored: DevKeyFile =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(stored.warning, DEV_KEY_WARNING);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,249 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::EnclaveError;
+
+/// Formats whose content can be checked by [`check_content_format`].
+pub const SNIFFABLE_FORMATS: [&str; 4] = ["CSV", "JSON", "JSONL", "PARQUET"];
+
+/// Format named by a `Content-Type` header value, ignoring parameters such as
+/// `charset`. `None` for types that name no dataset format, such as
+/// `text/plain` or `application/octet-stream`.
+pub fn format_from_content_type(content_type: &str) -> Option<&'static str> {
+    let essence = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    match essence.as_str() {
+        "text/csv" | "application/csv" => Some("CSV"),
+        "application/json" | "text/json" => Some("JSON"),
+        "application/x-ndjson" | "application/jsonl" | "application/jsonlines" => Some("JSONL"),
+        "application/vnd.apache.parquet" | "application/x-parquet" => Some("PARQUET"),
+        _ => None,
+    }
+}
+
+/// Magic bytes at the start and end of every Parquet file.
+const PARQUET_MAGIC: &[u8] = b"PAR1";
+
+/// Check that `content` actually looks like the `declared` format:
+/// - CSV: UTF-8 text whose rows all have the same number of fields
+/// - JSON: a single valid JSON document
+/// - JSONL: one valid JSON document per non-empty line
+/// - PARQUET: starts and ends with the `PAR1` magic
+///
+/// Formats are matched case-insensitively; any other format is rejected since
+/// its content cannot be checked.
+pub fn check_content_format(declared: &str, content: &[u8]) -> Result<(), EnclaveError> {
+    let format = declared.trim().to_ascii_uppercase();
+    let valid = match format.as_str() {
+        "CSV" => is_valid_csv(content),
+        "JSON" => serde_json::from_slice::<serde_json::Value>(content).is_ok(),
+        "JSONL" => is_valid_json_lines(content),
+        "PARQUET" => {
+            content.len() >= 2 * PARQUET_MAGIC.len()
+                && content.starts_with(PARQUET_MAGIC)
+                && content.ends_with(PARQUET_MAGIC)
+        }
+        _ => {
+            return Err(EnclaveError::InvalidInput(format!(
+                "strict_format is not supported for format {} (supported: {})",
+                declared,
+                SNIFFABLE_FORMATS.join(", ")
+            )))
+        }
+    };
+
+    if !valid {
+        return Err(EnclaveError::InvalidInput(format!(
+            "format mismatch: declared {} but content is not valid {}",
+            declared, format
+        )));
+    }
+    Ok(())
+}
+
+fn is_valid_csv(content: &[u8]) -> bool {
+    let Ok(text) = std::str::from_utf8(content) else {
+        return false;
+    };
+    match csv_record_widths(text.trim_start_matches('\u{feff}')) {
+        Some(widths) => !widths.is_empty() && widths.iter().all(|w| *w == widths[0]),
+        None => false,
+    }
+}
+
+/// Number of fields in each non-empty record, honouring double-quoted fields
+/// that may contain commas or newlines. `None` if a quote is left open.
+fn csv_record_widths(text: &str) -> Option<Vec<usize>> {
+    let mut widths = Vec::new();
+    let mut fields = 1;
+    let mut in_quotes = false;
+    let mut record_empty = true;
+
+    for c in text.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                record_empty = false;
+            }
+            ',' if !in_quotes => {
+                fields += 1;
+                record_empty = false;
+            }
+            '\n' if !in_quotes => {
+                if !record_empty {
+                    widths.push(fields);
+                }
+                fields = 1;
+                record_empty = true;
+            }
+            '\r' if !in_quotes => {}
+            _ => record_empty = false,
+        }
+    }
+
+    if in_quotes {
+        return None;
+    }
+    if !record_empty {
+        widths.push(fields);
+    }
+    Some(widths)
+}
+
+/// Check that the header row of CSV `content` contains every `required`
+/// column. A UTF-8 BOM is ignored, header fields may be double-quoted (with
+/// `""` as an escaped quote), and names are compared after trimming whitespace.
+pub fn check_csv_columns(content: &[u8], required: &[String]) -> Result<(), EnclaveError> {
+    let text = std::str::from_utf8(content)
+        .map_err(|_| EnclaveError::InvalidInput("CSV content is not valid UTF-8".to_string()))?;
+    let header = csv_header(text.trim_start_matches('\u{feff}')).ok_or_else(|| {
+        EnclaveError::InvalidInput("CSV header row is missing or has an unterminated quote".to_string())
+    })?;
+
+    let missing: Vec<&str> = required
+        .iter()
+        .filter(|column| !header.iter().any(|h| h == column.trim()))
+        .map(String::as_str)
+        .collect();
+    if !missing.is_empty() {
+        return Err(EnclaveError::InvalidInput(format!(
+            "CSV header is missing required columns: {} (found: {})",
+            missing.join(", "),
+            header.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+/// Fields of the first CSV record. `None` if the text is empty or the
+/// record leaves a quote open.
+fn csv_header(text: &str) -> Option<Vec<String>> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut field).trim().to_string()),
+            '\n' if !in_quotes => break,
+            '\r' if !in_quotes => {}
+            _ => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return None;
+    }
+    fields.push(field.trim().to_string());
+    if fields.len() == 1 && fields[0].is_empty() {
+        return None;
+    }
+    Some(fields)
+}
+
+fn is_valid_json_lines(content: &[u8]) -> bool {
+    let Ok(text) = std::str::from_utf8(content) else {
+        return false;
+    };
+    let mut lines = text.lines().filter(|l| !l.trim().is_empty()).peekable();
+    lines.peek().is_some()
+        && lines.all(|line| serde_json::from_str::<serde_json::Value>(line).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_from_content_type() {
+        assert_eq!(format_from_content_type("text/csv"), Some("CSV"));
+        assert_eq!(format_from_content_type("Text/CSV; charset=utf-8"), Some("CSV"));
+        assert_eq!(format_from_content_type("application/json"), Some("JSON"));
+        assert_eq!(format_from_content_type("application/x-ndjson"), Some("JSONL"));
+        assert_eq!(format_from_content_type("application/vnd.apache.parquet"), Some("PARQUET"));
+        assert_eq!(format_from_content_type("application/octet-stream"), None);
+        assert_eq!(format_from_content_type(""), None);
+    }
+
+    #[test]
+    fn test_csv_detection() {
+        assert!(check_content_format("CSV", b"id,label,value\n1,dog,100\n2,cat,200\n").is_ok());
+        assert!(check_content_format("csv", b"\xef\xbb\xbfid,note\r\n1,\"a, b\"\r\n").is_ok());
+        assert!(check_content_format("CSV", b"id,label\n1,dog,extra\n").is_err());
+        assert!(check_content_format("CSV", b"id,\"unterminated\n").is_err());
+        assert!(check_content_format("CSV", b"").is_err());
+        assert!(check_content_format("CSV", &[0xff, 0xfe, 0x00]).is_err());
+    }
+
+    #[test]
+    fn test_json_detection() {
+        assert!(check_content_format("JSON", br#"{"rows": [1, 2, 3]}"#).is_ok());
+        assert!(check_content_format("JSON", b"id,label\n1,dog\n").is_err());
+        assert!(check_content_format("JSONL", b"{\"a\":1}\n{\"a\":2}\n").is_ok());
+        assert!(check_content_format("JSONL", b"{\"a\":1}\nnot json\n").is_err());
+    }
+
+    #[test]
+    fn test_parquet_detection() {
+        assert!(check_content_format("Parquet", b"PAR1....PAR1").is_ok());
+        assert!(check_content_format("PARQUET", b"PAR1").is_err());
+    }
+
+    #[test]
+    fn test_csv_required_columns() {
+        let required = vec!["id".to_string(), "label".to_string()];
+        assert!(check_csv_columns(b"id,label,value\n1,dog,100\n", &required).is_ok());
+        assert!(check_csv_columns(b"\xef\xbb\xbf\"id\", \"label\"\r\n1,dog\r\n", &required).is_ok());
+
+        let err = check_csv_columns(b"id,value\n1,100\n", &required).unwrap_err();
+        assert_eq!(
+            err.message(),
+            "CSV header is missing required columns: label (found: id, value)"
+        );
+        assert!(check_csv_columns(b"", &required).is_err());
+        assert!(check_csv_columns(b"id,\"label\n", &required).is_err());
+    }
+
+    #[test]
+    fn test_csv_header_quotes() {
+        assert_eq!(
+            csv_header("\"a, b\",\"say \"\"hi\"\"\"\nrest").unwrap(),
+            vec!["a, b".to_string(), "say \"hi\"".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_mismatch_message_names_formats() {
+        let err = check_content_format("CSV", b"id,label\n1,dog,extra\n").unwrap_err();
+        assert_eq!(
+            err.message(),
+            "format mismatch: declared CSV but content is not valid CSV"
+        );
+        assert!(check_content_format("XLSX", b"anything").is_err());
+    }
+}
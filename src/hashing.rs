@@ -0,0 +1,119 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::EnclaveError;
+use fastcrypto::hash::{Blake2b256, HashFunction};
+use sha2::{Digest, Sha256, Sha512};
+
+/// Hash algorithms the enclave can use to compute a dataset digest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Sha256,
+    Sha512,
+    Blake2b256,
+}
+
+impl HashAlgorithm {
+    /// All supported algorithms, in the order they are advertised.
+    pub const ALL: [HashAlgorithm; 3] = [
+        HashAlgorithm::Sha256,
+        HashAlgorithm::Sha512,
+        HashAlgorithm::Blake2b256,
+    ];
+
+    /// Parse an optional algorithm name from a request, defaulting to SHA256.
+    pub fn parse(name: Option<&str>) -> Result<Self, EnclaveError> {
+        match name {
+            None => Ok(HashAlgorithm::Sha256),
+            Some(name) => match name.to_ascii_lowercase().as_str() {
+                "sha256" => Ok(HashAlgorithm::Sha256),
+                "sha512" => Ok(HashAlgorithm::Sha512),
+                "blake2b256" => Ok(HashAlgorithm::Blake2b256),
+                _ => Err(EnclaveError::InvalidInput(format!(
+                    "Unsupported hash algorithm: {} (expected sha256, sha512 or blake2b256)",
+                    name
+                ))),
+            },
+        }
+    }
+
+    /// Canonical lowercase name of the algorithm.
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Sha512 => "sha512",
+            HashAlgorithm::Blake2b256 => "blake2b256",
+        }
+    }
+
+    /// Digest length in bytes.
+    pub fn digest_len(&self) -> usize {
+        match self {
+            HashAlgorithm::Sha256 => 32,
+            HashAlgorithm::Sha512 => 64,
+            HashAlgorithm::Blake2b256 => 32,
+        }
+    }
+
+    /// Create an incremental hasher for this algorithm.
+    pub fn hasher(&self) -> DatasetHasher {
+        match self {
+            HashAlgorithm::Sha256 => DatasetHasher::Sha256(Sha256::new()),
+            HashAlgorithm::Sha512 => DatasetHasher::Sha512(Sha512::new()),
+            HashAlgorithm::Blake2b256 => DatasetHasher::Blake2b256(Blake2b256::new()),
+        }
+    }
+
+    /// One-shot digest of `data`.
+    pub fn digest(&self, data: &[u8]) -> Vec<u8> {
+        let mut hasher = self.hasher();
+        hasher.update(data);
+        hasher.finalize()
+    }
+}
+
+/// Incremental hasher over any supported [`HashAlgorithm`].
+pub enum DatasetHasher {
+    Sha256(Sha256),
+    Sha512(Sha512),
+    Blake2b256(Blake2b256),
+}
+
+impl DatasetHasher {
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            DatasetHasher::Sha256(h) => h.update(data),
+            DatasetHasher::Sha512(h) => h.update(data),
+            DatasetHasher::Blake2b256(h) => h.update(data),
+        }
+    }
+
+    pub fn finalize(self) -> Vec<u8> {
+        match self {
+            DatasetHasher::Sha256(h) => h.finalize().to_vec(),
+            DatasetHasher::Sha512(h) => h.finalize().to_vec(),
+            DatasetHasher::Blake2b256(h) => h.finalize().digest.to_vec(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_defaults_to_sha256() {
+        assert_eq!(HashAlgorithm::parse(None).unwrap(), HashAlgorithm::Sha256);
+        assert_eq!(HashAlgorithm::parse(Some("SHA512")).unwrap(), HashAlgorithm::Sha512);
+        assert!(HashAlgorithm::parse(Some("md5")).is_err());
+    }
+
+    #[test]
+    fn test_digest_lengths() {
+        let data = b"id,label,value\n1,dog,100";
+        for algorithm in HashAlgorithm::ALL {
+            assert_eq!(algorithm.digest(data).len(), algorithm.digest_len());
+        }
+        assert_eq!(HashAlgorithm::Sha256.digest(data), Sha256::digest(data).to_vec());
+    }
+}
@@ -0,0 +1,173 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Signing what a dataset URL reports about itself, without downloading it.
+//!
+//! `/verify_head` issues a HEAD request and signs a `DatasetVerification`
+//! whose `size` is the `Content-Length` the server claims. The content is
+//! never read, so `original_hash` is left empty and the `description` says
+//! the content is unverified. It is signed under `IntentScope::HeadMetadata`,
+//! never `ProcessData`, so it cannot pass for a hash-checked verification.
+//! Use `process_data` or `verify_dataset` for that.
+
+use crate::common::{
+    to_signed_response, IntentMessage, IntentScope, ProcessedDataResponse, SigningQuery,
+};
+use crate::{
+    audit, check_format_allowed, fetch, ipfs, AppState, DatasetVerification, EnclaveError,
+    DATASET_VERIFICATION_VERSION,
+};
+use axum::extract::{Query, State};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::info;
+
+/// `dataset_id` of every HEAD-only verification.
+pub const HEAD_DATASET_ID: &[u8] = b"head";
+
+/// Request to sign the size a dataset URL reports.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VerifyHeadRequest {
+    pub dataset_url: String,
+    pub format: String,
+}
+
+/// Endpoint signing the `Content-Length` of `dataset_url` as `size`, with an
+/// empty `original_hash`.
+pub async fn verify_head(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SigningQuery>,
+    Json(request): Json<VerifyHeadRequest>,
+) -> Result<Json<ProcessedDataResponse<IntentMessage<DatasetVerification>>>, EnclaveError> {
+    let result = sign_head(&state, request)
+        .await
+        .and_then(|Json(response)| query.apply(&state.signer, response).map(Json));
+    state.metrics.record("verify_head", &result);
+    audit::record(&state, "verify_head", &result);
+    result
+}
+
+async fn sign_head(
+    state: &AppState,
+    request: VerifyHeadRequest,
+) -> Result<Json<ProcessedDataResponse<IntentMessage<DatasetVerification>>>, EnclaveError> {
+    check_format_allowed(state, &request.format)?;
+    info!("Reading headers of dataset: {}", request.dataset_url);
+
+    let current_timestamp = state.clock.now_millis()?;
+    let fetch_url = ipfs::resolve_dataset_url(&state.ipfs_gateway, &request.dataset_url)?;
+    let head = fetch::fetch_head(state, &fetch_url).await?;
+    state.limits.check_min_dataset_size(head.content_length)?;
+
+    let description = format!(
+        "Unverified content: size from HTTP HEAD, content type {}",
+        head.content_type.as_deref().unwrap_or("unknown")
+    );
+    Ok(Json(to_signed_response(
+        state.signer.current().as_ref(),
+        DatasetVerification {
+            version: DATASET_VERIFICATION_VERSION,
+            dataset_id: HEAD_DATASET_ID.to_vec(),
+            name: request.dataset_url.into_bytes(),
+            description: description.into_bytes(),
+            format: request.format.into_bytes(),
+            size: head.content_length,
+            original_hash: Vec::new(),
+            walrus_blob_id: Vec::new(),
+            seal_policy_id: Vec::new(),
+            timestamp: current_timestamp,
+            uploader: Vec::new(),
+        },
+        current_timestamp,
+        IntentScope::HeadMetadata,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fastcrypto::ed25519::Ed25519KeyPair;
+    use fastcrypto::traits::KeyPair;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn test_state() -> Arc<AppState> {
+        let mut state = AppState::new(
+            Ed25519KeyPair::generate(&mut rand::thread_rng()),
+            "test-key".to_string(),
+        );
+        state.url_policy.allowlist = vec!["127.0.0.1".to_string()];
+        Arc::new(state)
+    }
+
+    /// Answer one request with `head` and no body, returning the URL and the
+    /// request line received.
+    async fn serve_head_once(head: &'static str) -> (String, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/data.csv", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = vec![0u8; 4096];
+            let read = socket.read(&mut request).await.unwrap();
+            socket.write_all(head.as_bytes()).await.unwrap();
+            String::from_utf8_lossy(&request[..read]).lines().next().unwrap().to_string()
+        });
+        (url, server)
+    }
+
+    fn request(dataset_url: String) -> VerifyHeadRequest {
+        VerifyHeadRequest { dataset_url, format: "CSV".to_string() }
+    }
+
+    #[tokio::test]
+    async fn test_head_size_is_signed_without_download() {
+        let (url, server) = serve_head_once(
+            "HTTP/1.1 200 OK\r\nContent-Length: 5000\r\nContent-Type: text/csv\r\n\
+             Connection: close\r\n\r\n",
+        )
+        .await;
+
+        let state = test_state();
+        let Json(response) = sign_head(&state, request(url.clone())).await.unwrap();
+        assert!(server.await.unwrap().starts_with("HEAD /data.csv"));
+
+        let data = &response.response.data;
+        assert_eq!(response.response.intent, IntentScope::HeadMetadata);
+        assert_eq!(data.size, 5000);
+        assert!(data.original_hash.is_empty());
+        assert_eq!(data.name, url.as_bytes());
+        let description = String::from_utf8(data.description.clone()).unwrap();
+        assert!(description.starts_with("Unverified content"));
+        assert!(description.contains("text/csv"));
+    }
+
+    #[tokio::test]
+    async fn test_disallowed_format_is_rejected_before_fetch() {
+        let mut state = AppState::new(
+            Ed25519KeyPair::generate(&mut rand::thread_rng()),
+            "test-key".to_string(),
+        );
+        state.allowed_formats = vec!["PARQUET".to_string()];
+        // Nothing listens here; a fetch would fail with FetchFailed instead
+        let request = request("http://127.0.0.1:9/data.csv".to_string());
+        let err = sign_head(&state, request).await.unwrap_err();
+        assert!(matches!(err, EnclaveError::Forbidden(_)), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn test_missing_content_length_is_rejected() {
+        let (url, _server) = serve_head_once("HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n").await;
+        let err = sign_head(&test_state(), request(url)).await.unwrap_err();
+        assert!(matches!(err, EnclaveError::FetchFailed(_)));
+        assert!(err.message().contains("Content-Length"));
+
+        let (url, _server) = serve_head_once(
+            "HTTP/1.1 200 OK\r\nContent-Length: 80\r\nContent-Encoding: gzip\r\n\
+             Connection: close\r\n\r\n",
+        )
+        .await;
+        let err = sign_head(&test_state(), request(url)).await.unwrap_err();
+        assert!(err.message().contains("Content-Encoding gzip"));
+    }
+}
@@ -0,0 +1,312 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Idempotent signing requests.
+//!
+//! A client (or a gateway retrying on its behalf) may send an
+//! `Idempotency-Key` header on any protected endpoint. The first successful
+//! response for that key is kept for the TTL and replayed byte for byte to
+//! later requests with the same key, marked `Idempotent-Replayed: true`, so
+//! a retried request never yields a second signature with a new timestamp.
+//! Concurrent requests with one key wait for the first to finish. Failed
+//! requests are not kept, so they may be retried with the same key.
+//!
+//! Keys are scoped to the presenting API key. Reusing a key for a different
+//! request (another endpoint or body) is refused with 409.
+
+use crate::auth::extract_api_key;
+use crate::{AppState, EnclaveError};
+use axum::body::{Body, Bytes};
+use axum::extract::{Request, State};
+use axum::http::header::CONTENT_TYPE;
+use axum::http::{HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use lru::LruCache;
+use sha2::{Digest, Sha256};
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+
+/// Request header carrying the client-chosen idempotency key.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Response header set on responses replayed from the cache.
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// Longest idempotency key accepted.
+pub const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+
+/// Default time a response stays replayable: 24 hours.
+pub const DEFAULT_IDEMPOTENCY_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Default number of idempotency keys remembered.
+pub const DEFAULT_IDEMPOTENCY_CAPACITY: usize = 10_000;
+
+/// A successful response, kept to be replayed.
+#[derive(Debug, Clone)]
+struct StoredResponse {
+    status: StatusCode,
+    content_type: Option<HeaderValue>,
+    body: Bytes,
+}
+
+impl StoredResponse {
+    fn to_response(&self, replayed: bool) -> Response {
+        let mut response = Response::new(Body::from(self.body.clone()));
+        *response.status_mut() = self.status;
+        if let Some(content_type) = &self.content_type {
+            response.headers_mut().insert(CONTENT_TYPE, content_type.clone());
+        }
+        if replayed {
+            response
+                .headers_mut()
+                .insert(IDEMPOTENT_REPLAYED_HEADER, HeaderValue::from_static("true"));
+        }
+        response
+    }
+}
+
+struct Entry {
+    /// Digest of the request first sent with the key
+    request_digest: [u8; 32],
+    created_at: Instant,
+    /// Empty until a request with the key succeeds
+    response: Arc<OnceCell<StoredResponse>>,
+}
+
+/// Responses by idempotency key, each replayable for `ttl`. Once full, the
+/// least recently used key is forgotten first.
+pub struct IdempotencyCache {
+    ttl: Duration,
+    /// `None` when idempotency keys are ignored
+    entries: Option<Mutex<LruCache<[u8; 32], Entry>>>,
+}
+
+impl IdempotencyCache {
+    /// Remember up to `capacity` keys for `ttl` each; a zero `ttl` or
+    /// `capacity` disables idempotency keys.
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        let capacity = NonZeroUsize::new(capacity).filter(|_| !ttl.is_zero());
+        Self {
+            ttl,
+            entries: capacity.map(|capacity| Mutex::new(LruCache::new(capacity))),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.entries.is_some()
+    }
+
+    /// The response slot for `key`, or `Conflict` if the key was used for a
+    /// different request within the TTL. An expired key starts over.
+    fn slot(
+        &self,
+        entries: &Mutex<LruCache<[u8; 32], Entry>>,
+        key: [u8; 32],
+        request_digest: [u8; 32],
+        now: Instant,
+    ) -> Result<Arc<OnceCell<StoredResponse>>, EnclaveError> {
+        let mut entries = entries.lock().expect("idempotency lock poisoned");
+        if let Some(entry) = entries.get(&key) {
+            if now.saturating_duration_since(entry.created_at) < self.ttl {
+                if entry.request_digest != request_digest {
+                    return Err(EnclaveError::Conflict(
+                        "Idempotency-Key was already used for a different request".to_string(),
+                    ));
+                }
+                return Ok(entry.response.clone());
+            }
+        }
+        let response = Arc::new(OnceCell::new());
+        entries.put(key, Entry { request_digest, created_at: now, response: response.clone() });
+        Ok(response)
+    }
+}
+
+impl Default for IdempotencyCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_IDEMPOTENCY_TTL, DEFAULT_IDEMPOTENCY_CAPACITY)
+    }
+}
+
+/// Middleware replaying the stored response for a repeated `Idempotency-Key`.
+/// Must run after `require_api_key`, since keys are scoped per API key.
+pub async fn idempotency(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(entries) = &state.idempotency.entries else {
+        return next.run(request).await;
+    };
+    let Some(header) = request.headers().get(IDEMPOTENCY_KEY_HEADER) else {
+        return next.run(request).await;
+    };
+    let idempotency_key = match header.to_str().map(str::trim) {
+        Ok(key) if !key.is_empty() && key.len() <= MAX_IDEMPOTENCY_KEY_LEN => key.to_string(),
+        _ => {
+            return EnclaveError::InvalidInput(format!(
+                "Idempotency-Key must be 1 to {} visible ASCII characters",
+                MAX_IDEMPOTENCY_KEY_LEN
+            ))
+            .into_response()
+        }
+    };
+
+    // Scope the key to the client, without keeping the API key itself
+    let client = extract_api_key(request.headers()).unwrap_or_default();
+    let key: [u8; 32] = Sha256::new()
+        .chain_update(client.as_bytes())
+        .chain_update([0])
+        .chain_update(idempotency_key.as_bytes())
+        .finalize()
+        .into();
+
+    let (parts, body) = request.into_parts();
+    let Ok(bytes) = axum::body::to_bytes(body, state.limits.max_batch_body_bytes).await else {
+        return EnclaveError::PayloadTooLarge("request body exceeds the size limit".to_string())
+            .into_response();
+    };
+    let request_digest: [u8; 32] = Sha256::new()
+        .chain_update(parts.method.as_str().as_bytes())
+        .chain_update([0])
+        .chain_update(parts.uri.to_string().as_bytes())
+        .chain_update([0])
+        .chain_update(&bytes)
+        .finalize()
+        .into();
+
+    let slot = match state.idempotency.slot(entries, key, request_digest, Instant::now()) {
+        Ok(slot) => slot,
+        Err(e) => return e.into_response(),
+    };
+    let request = Request::from_parts(parts, Body::from(bytes));
+    let mut handled = false;
+    let stored = slot
+        .get_or_try_init(|| async {
+            handled = true;
+            let response = next.run(request).await;
+            if !response.status().is_success() {
+                return Err(response);
+            }
+            let (parts, body) = response.into_parts();
+            let body = axum::body::to_bytes(body, usize::MAX).await.map_err(|e| {
+                EnclaveError::GenericError(format!("Failed to read response body: {}", e))
+                    .into_response()
+            })?;
+            Ok(StoredResponse {
+                status: parts.status,
+                content_type: parts.headers.get(CONTENT_TYPE).cloned(),
+                body,
+            })
+        })
+        .await;
+    match stored {
+        Ok(stored) => stored.to_response(!handled),
+        Err(response) => response,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::post;
+    use axum::{middleware, Json, Router};
+    use fastcrypto::ed25519::Ed25519KeyPair;
+    use fastcrypto::traits::KeyPair;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tower::ServiceExt;
+
+    /// Router whose handler returns a new counter value (like a new timestamp)
+    /// on every call, failing on `{"fail": true}`.
+    fn app(calls: Arc<AtomicUsize>) -> Router {
+        let state = Arc::new(AppState::new(
+            Ed25519KeyPair::generate(&mut rand::thread_rng()),
+            "test-key".to_string(),
+        ));
+        let handler = move |Json(body): Json<serde_json::Value>| async move {
+            let call = calls.fetch_add(1, Ordering::SeqCst) + 1;
+            if body["fail"] == true {
+                return Err(EnclaveError::InvalidInput("failed".to_string()));
+            }
+            Ok(Json(serde_json::json!({ "call": call })))
+        };
+        Router::new()
+            .route("/sign", post(handler))
+            .layer(middleware::from_fn_with_state(state, idempotency))
+    }
+
+    async fn send(app: &Router, key: Option<&str>, body: &str) -> Response {
+        let mut request = Request::post("/sign").header(CONTENT_TYPE, "application/json");
+        if let Some(key) = key {
+            request = request.header(IDEMPOTENCY_KEY_HEADER, key);
+        }
+        app.clone().oneshot(request.body(Body::from(body.to_string())).unwrap()).await.unwrap()
+    }
+
+    async fn body(response: Response) -> Bytes {
+        axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_same_key_replays_identical_response() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let app = app(calls.clone());
+
+        let first = send(&app, Some("request-1"), "{}").await;
+        assert!(first.headers().get(IDEMPOTENT_REPLAYED_HEADER).is_none());
+        let first = body(first).await;
+        let replay = send(&app, Some("request-1"), "{}").await;
+        assert_eq!(replay.headers()[IDEMPOTENT_REPLAYED_HEADER], "true");
+        assert_eq!(body(replay).await, first);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Without a key, or with another key, the handler runs again
+        assert_ne!(body(send(&app, None, "{}").await).await, first);
+        assert_ne!(body(send(&app, Some("request-2"), "{}").await).await, first);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_key_reused_for_other_request_is_refused() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let app = app(calls.clone());
+
+        assert!(send(&app, Some("request-1"), "{}").await.status().is_success());
+        let conflict = send(&app, Some("request-1"), r#"{"other": 1}"#).await;
+        assert_eq!(conflict.status(), StatusCode::CONFLICT);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_failures_are_not_replayed() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let app = app(calls.clone());
+
+        let body = r#"{"fail": true}"#;
+        assert_eq!(send(&app, Some("request-1"), body).await.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(send(&app, Some("request-1"), body).await.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_expired_key_starts_over() {
+        let cache = IdempotencyCache::new(Duration::from_secs(60), 10);
+        let entries = cache.entries.as_ref().unwrap();
+        let now = Instant::now();
+        let slot = cache.slot(entries, [1; 32], [2; 32], now).unwrap();
+        slot.set(StoredResponse {
+            status: StatusCode::OK,
+            content_type: None,
+            body: Bytes::from_static(b"signed"),
+        })
+        .unwrap();
+
+        assert!(cache.slot(entries, [1; 32], [2; 32], now).unwrap().get().is_some());
+        let later = now + Duration::from_secs(61);
+        assert!(cache.slot(entries, [1; 32], [3; 32], later).unwrap().get().is_none());
+        assert!(!IdempotencyCache::new(Duration::ZERO, 10).is_enabled());
+    }
+}
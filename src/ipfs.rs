@@ -0,0 +1,165 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::EnclaveError;
+use fastcrypto::encoding::{Base58, Encoding};
+
+/// Gateway `ipfs://` dataset URLs are resolved through when none is configured.
+pub const DEFAULT_IPFS_GATEWAY: &str = "https://ipfs.io";
+
+/// URL scheme of content-addressed IPFS datasets.
+pub const IPFS_SCHEME: &str = "ipfs://";
+
+/// Rewrite `ipfs://<cid>[/path]` to `<gateway>/ipfs/<cid>[/path]`. Other URLs
+/// are returned unchanged. The CID is validated before anything is fetched.
+pub fn resolve_dataset_url(gateway: &str, dataset_url: &str) -> Result<String, EnclaveError> {
+    let Some(rest) = dataset_url.strip_prefix(IPFS_SCHEME) else {
+        return Ok(dataset_url.to_string());
+    };
+    if rest.contains(['?', '#']) {
+        return Err(EnclaveError::InvalidInput(format!(
+            "IPFS URL cannot carry a query or fragment: {}",
+            dataset_url
+        )));
+    }
+    let (cid, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    validate_cid(cid)?;
+    if path.split('/').any(|segment| segment == "..") {
+        return Err(EnclaveError::InvalidInput(format!(
+            "IPFS path cannot contain '..': {}",
+            dataset_url
+        )));
+    }
+    if !gateway.starts_with("https://") && !gateway.starts_with("http://") {
+        return Err(EnclaveError::InvalidInput(format!(
+            "IPFS gateway must be an http(s) URL: {}",
+            gateway
+        )));
+    }
+    Ok(format!("{}/ipfs/{}{}", gateway.trim_end_matches('/'), cid, path))
+}
+
+/// Check that `cid` is a CIDv0 (base58btc `Qm...`) or a base32 CIDv1 (`b...`)
+/// wrapping a well-formed multihash.
+pub fn validate_cid(cid: &str) -> Result<(), EnclaveError> {
+    let valid = if let Some(encoded) = cid.strip_prefix('b') {
+        decode_base32(encoded.as_bytes()).is_some_and(|bytes| is_cid_v1(&bytes))
+    } else {
+        // A CIDv0 is a bare sha2-256 multihash
+        cid.len() == 46
+            && cid.starts_with("Qm")
+            && Base58::decode(cid)
+                .is_ok_and(|bytes| bytes.starts_with(&[0x12, 0x20]) && is_multihash(&bytes))
+    };
+    if !valid {
+        return Err(EnclaveError::InvalidInput(format!(
+            "invalid IPFS CID {:?}: expected a CIDv0 (Qm...) or a base32 CIDv1 (b...)",
+            cid
+        )));
+    }
+    Ok(())
+}
+
+/// `<version = 1><codec><multihash>`, all prefixes unsigned varints.
+fn is_cid_v1(bytes: &[u8]) -> bool {
+    let Some((1, rest)) = read_varint(bytes) else {
+        return false;
+    };
+    read_varint(rest).is_some_and(|(_codec, multihash)| is_multihash(multihash))
+}
+
+/// `<hash function code><digest length><digest>` with a non-empty digest.
+fn is_multihash(bytes: &[u8]) -> bool {
+    let Some((_code, rest)) = read_varint(bytes) else {
+        return false;
+    };
+    matches!(
+        read_varint(rest),
+        Some((length, digest)) if length > 0 && digest.len() as u64 == length
+    )
+}
+
+/// Read an unsigned LEB128 varint of at most 9 bytes, as multiformats allow.
+fn read_varint(bytes: &[u8]) -> Option<(u64, &[u8])> {
+    let mut value = 0u64;
+    for (i, &byte) in bytes.iter().enumerate().take(9) {
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some((value, &bytes[i + 1..]));
+        }
+    }
+    None
+}
+
+/// Decode unpadded lowercase RFC 4648 base32, rejecting set trailing bits.
+fn decode_base32(encoded: &[u8]) -> Option<Vec<u8>> {
+    fn quintet(c: u8) -> Option<u32> {
+        match c {
+            b'a'..=b'z' => Some(u32::from(c - b'a')),
+            b'2'..=b'7' => Some(u32::from(c - b'2') + 26),
+            _ => None,
+        }
+    }
+
+    let mut decoded = Vec::with_capacity(encoded.len() * 5 / 8);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for &c in encoded {
+        buffer = (buffer << 5) | quintet(c)?;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    (buffer == 0 && bits < 5).then_some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CID_V0: &str = "QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG";
+    const CID_V1: &str = "bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi";
+
+    #[test]
+    fn test_validate_cid() {
+        assert!(validate_cid(CID_V0).is_ok());
+        assert!(validate_cid(CID_V1).is_ok());
+
+        assert!(validate_cid("").is_err());
+        assert!(validate_cid("Qm123").is_err());
+        assert!(validate_cid(&CID_V0.replace('Y', "0")).is_err());
+        assert!(validate_cid(&CID_V1[..CID_V1.len() - 4]).is_err());
+        assert!(validate_cid(&CID_V1.to_uppercase()).is_err());
+
+        let err = validate_cid("not-a-cid").unwrap_err();
+        assert!(err.message().starts_with("invalid IPFS CID \"not-a-cid\""));
+    }
+
+    #[test]
+    fn test_resolve_dataset_url() {
+        assert_eq!(
+            resolve_dataset_url("https://ipfs.io/", &format!("ipfs://{}", CID_V1)).unwrap(),
+            format!("https://ipfs.io/ipfs/{}", CID_V1)
+        );
+        assert_eq!(
+            resolve_dataset_url("https://ipfs.io", &format!("ipfs://{}/train.csv", CID_V0))
+                .unwrap(),
+            format!("https://ipfs.io/ipfs/{}/train.csv", CID_V0)
+        );
+
+        // Regular URLs pass through untouched
+        let url = "https://example.com/data.csv?version=2";
+        assert_eq!(resolve_dataset_url("https://ipfs.io", url).unwrap(), url);
+
+        assert!(resolve_dataset_url("https://ipfs.io", "ipfs://bogus").is_err());
+        let escape = format!("ipfs://{}/../etc/passwd", CID_V0);
+        assert!(resolve_dataset_url("https://ipfs.io", &escape).is_err());
+        let query = format!("ipfs://{}?x=1", CID_V0);
+        assert!(resolve_dataset_url("https://ipfs.io", &query).is_err());
+        let cid = format!("ipfs://{}", CID_V0);
+        assert!(resolve_dataset_url("file:///ipfs", &cid).is_err());
+    }
+}
@@ -0,0 +1,379 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Scheduled rotation of the enclave signing key.
+//!
+//! Rotation is off by default. When enabled, every interval a fresh key
+//! replaces the current one and the prior key stays verifiable for a grace
+//! period, listed by `GET /public_key`. The attestation document only ever
+//! commits to the current key, so rotation is incompatible with a public key
+//! registered once on-chain: consumers must re-register (or accept the
+//! previous key) within the grace period, or signatures from the new key will
+//! be rejected by the contract.
+
+use crate::clock::{Clock, SystemClock};
+use crate::signer::Signer;
+use crate::{AppState, EnclaveError};
+use axum::extract::{Query, State};
+use axum::Json;
+use fastcrypto::encoding::{Encoding, Hex};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Default time a rotated-out key remains valid for verification: 10 minutes.
+pub const DEFAULT_KEY_GRACE_PERIOD: Duration = Duration::from_secs(10 * 60);
+
+/// A key that was rotated out and is still within its grace period.
+struct RetiredKey {
+    signer: Arc<dyn Signer>,
+    retired_at_ms: u64,
+    valid_until_ms: u64,
+}
+
+struct Keys {
+    current: Arc<dyn Signer>,
+    /// Most recently retired first
+    retired: Vec<RetiredKey>,
+}
+
+/// The current signing key plus recently retired keys. Handlers sign with
+/// `current()`; signatures from a retired key still verify until its grace
+/// period ends.
+pub struct KeyRing {
+    keys: RwLock<Keys>,
+    /// Times rotations and grace period expiry
+    clock: Box<dyn Clock>,
+}
+
+impl KeyRing {
+    pub fn new(signer: impl Signer + 'static) -> Self {
+        Self::with_clock(signer, SystemClock)
+    }
+
+    /// A key ring whose grace periods follow `clock` instead of the system clock.
+    pub fn with_clock(signer: impl Signer + 'static, clock: impl Clock + 'static) -> Self {
+        Self {
+            keys: RwLock::new(Keys {
+                current: Arc::new(signer),
+                retired: Vec::new(),
+            }),
+            clock: Box::new(clock),
+        }
+    }
+
+    /// The key new signatures must be made with. Take it once per response so
+    /// the signature and any reported public key come from the same key.
+    pub fn current(&self) -> Arc<dyn Signer> {
+        self.keys.read().expect("key ring lock poisoned").current.clone()
+    }
+
+    /// Make `signer` the current key. The previous key stays verifiable for
+    /// `grace_period`; keys whose grace period has ended are dropped.
+    pub fn rotate(
+        &self,
+        signer: impl Signer + 'static,
+        grace_period: Duration,
+    ) -> Result<(), EnclaveError> {
+        let now_ms = self.clock.now_millis()?;
+        let mut keys = self.keys.write().expect("key ring lock poisoned");
+        let previous = std::mem::replace(&mut keys.current, Arc::new(signer));
+        keys.retired.insert(
+            0,
+            RetiredKey {
+                signer: previous,
+                retired_at_ms: now_ms,
+                valid_until_ms: now_ms.saturating_add(grace_period.as_millis() as u64),
+            },
+        );
+        keys.retired.retain(|key| key.valid_until_ms > now_ms);
+        Ok(())
+    }
+
+    /// Whether `signature` over `msg` was made by the current key or a
+    /// retired key still within its grace period.
+    pub fn verify(&self, msg: &[u8], signature: &[u8]) -> bool {
+        matches!(self.signer_of(msg, signature), Ok(Some(_)))
+    }
+
+    /// The current key or retired key within its grace period that made
+    /// `signature` over `msg`.
+    pub fn signer_of(
+        &self,
+        msg: &[u8],
+        signature: &[u8],
+    ) -> Result<Option<Arc<dyn Signer>>, EnclaveError> {
+        let keys = self.keys.read().expect("key ring lock poisoned");
+        if keys.current.verify(msg, signature) {
+            return Ok(Some(keys.current.clone()));
+        }
+        let now_ms = self.clock.now_millis()?;
+        Ok(keys
+            .retired
+            .iter()
+            .filter(|key| key.valid_until_ms > now_ms)
+            .find(|key| key.signer.verify(msg, signature))
+            .map(|key| key.signer.clone()))
+    }
+
+    /// Current and still-valid retired public keys, formatted with `encoding`.
+    pub fn history(&self, encoding: PublicKeyEncoding) -> Result<PublicKeyResponse, EnclaveError> {
+        let now_ms = self.clock.now_millis()?;
+        let keys = self.keys.read().expect("key ring lock poisoned");
+        Ok(PublicKeyResponse {
+            scheme: keys.current.scheme().to_string(),
+            encoding: encoding.name().to_string(),
+            current: encoding.encode(keys.current.as_ref())?,
+            previous: keys
+                .retired
+                .iter()
+                .filter(|key| key.valid_until_ms > now_ms)
+                .map(|key| {
+                    Ok(RetiredPublicKey {
+                        public_key: encoding.encode(key.signer.as_ref())?,
+                        retired_at_ms: key.retired_at_ms,
+                        valid_until_ms: key.valid_until_ms,
+                    })
+                })
+                .collect::<Result<_, EnclaveError>>()?,
+        })
+    }
+}
+
+/// Sui signature scheme flag for `scheme`, the byte Sui prefixes to a public
+/// key to identify its scheme.
+pub fn sui_scheme_flag(scheme: &str) -> Option<u8> {
+    match scheme {
+        "ed25519" => Some(0x00),
+        "secp256k1" => Some(0x01),
+        "secp256r1" => Some(0x02),
+        _ => None,
+    }
+}
+
+/// Sui serialized signature of `signature` by `signer`: the scheme flag, the
+/// raw signature and the raw public key, e.g. `00 || sig || pk` for Ed25519.
+pub fn sui_signature(signer: &dyn Signer, signature: &[u8]) -> Result<Vec<u8>, EnclaveError> {
+    let flag = sui_scheme_flag(signer.scheme()).ok_or_else(|| {
+        EnclaveError::InvalidInput(format!("{} keys have no Sui scheme flag", signer.scheme()))
+    })?;
+    let mut serialized = vec![flag];
+    serialized.extend_from_slice(signature);
+    serialized.extend_from_slice(&signer.public_key_bytes());
+    Ok(serialized)
+}
+
+/// How `/public_key` formats each key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PublicKeyEncoding {
+    /// Hex of the raw key bytes, as committed to in the attestation document
+    #[default]
+    Raw,
+    /// `0x`-prefixed hex of the raw key bytes, as `sui client call` takes a
+    /// `vector<u8>` argument
+    Hex,
+    /// Hex of the Sui scheme flag followed by the raw key bytes, e.g.
+    /// `00 || pk` for Ed25519
+    Sui,
+}
+
+impl PublicKeyEncoding {
+    /// Parse the `encoding` query parameter, defaulting to `Raw`.
+    pub fn parse(encoding: Option<&str>) -> Result<Self, EnclaveError> {
+        match encoding.map(|e| e.trim().to_ascii_lowercase()).as_deref() {
+            None | Some("raw") => Ok(Self::Raw),
+            Some("hex") => Ok(Self::Hex),
+            Some("sui") => Ok(Self::Sui),
+            Some(other) => Err(EnclaveError::InvalidInput(format!(
+                "unknown public key encoding {:?} (expected raw, hex or sui)",
+                other
+            ))),
+        }
+    }
+
+    /// Canonical lowercase name of the encoding.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Raw => "raw",
+            Self::Hex => "hex",
+            Self::Sui => "sui",
+        }
+    }
+
+    /// Format the public key of `signer`.
+    pub fn encode(&self, signer: &dyn Signer) -> Result<String, EnclaveError> {
+        let public_key = signer.public_key_bytes();
+        match self {
+            Self::Raw => Ok(Hex::encode(public_key)),
+            Self::Hex => Ok(format!("0x{}", Hex::encode(public_key))),
+            Self::Sui => {
+                let flag = sui_scheme_flag(signer.scheme()).ok_or_else(|| {
+                    EnclaveError::InvalidInput(format!(
+                        "{} keys have no Sui scheme flag",
+                        signer.scheme()
+                    ))
+                })?;
+                let mut flagged = vec![flag];
+                flagged.extend_from_slice(&public_key);
+                Ok(Hex::encode(flagged))
+            }
+        }
+    }
+}
+
+/// Query parameters for `/public_key`.
+#[derive(Debug, Default, Deserialize)]
+pub struct PublicKeyQuery {
+    /// Key encoding: "raw" (default), "hex" or "sui".
+    pub encoding: Option<String>,
+}
+
+/// Public keys the enclave currently signs and verifies with.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PublicKeyResponse {
+    /// Signature scheme, e.g. "ed25519"
+    pub scheme: String,
+    /// Encoding of every key in the response: "raw", "hex" or "sui"
+    pub encoding: String,
+    /// Public key new signatures are made with
+    pub current: String,
+    /// Rotated-out keys still within their grace period, most recent first
+    pub previous: Vec<RetiredPublicKey>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RetiredPublicKey {
+    /// Public key, in the response's encoding
+    pub public_key: String,
+    /// When the key stopped signing, in milliseconds since the Unix epoch
+    pub retired_at_ms: u64,
+    /// When the key stops being accepted, in milliseconds since the Unix epoch
+    pub valid_until_ms: u64,
+}
+
+/// Endpoint returning the current public key and its recent predecessors.
+pub async fn public_key(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<PublicKeyQuery>,
+) -> Result<Json<PublicKeyResponse>, EnclaveError> {
+    let encoding = PublicKeyEncoding::parse(query.encoding.as_deref())?;
+    Ok(Json(state.signer.history(encoding)?))
+}
+
+/// Spawn a task that replaces the signing key with `generate()` every
+/// `interval`, keeping the prior key verifiable for `grace_period`. Cached
+/// signatures are dropped on rotation so new responses use the new key.
+pub fn spawn_rotation<S, F>(
+    state: Arc<AppState>,
+    interval: Duration,
+    grace_period: Duration,
+    generate: F,
+) -> tokio::task::JoinHandle<()>
+where
+    S: Signer + 'static,
+    F: Fn() -> S + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        // The first tick completes immediately; the boot key serves one full interval
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            if let Err(e) = state.signer.rotate(generate(), grace_period) {
+                warn!("Key rotation skipped: {}", e);
+                continue;
+            }
+            // Entries are keyed by signer, so this only frees old-key responses
+            state.signature_cache.clear();
+            info!(
+                "Rotated signing key, new public key: {}",
+                Hex::encode(state.signer.current().public_key_bytes())
+            );
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use fastcrypto::ed25519::Ed25519KeyPair;
+    use fastcrypto::traits::KeyPair;
+
+    const NOW_MS: u64 = 1_700_000_000_000;
+
+    fn generate() -> Ed25519KeyPair {
+        Ed25519KeyPair::generate(&mut rand::thread_rng())
+    }
+
+    #[test]
+    fn test_rotation_keeps_previous_key_during_grace_period() {
+        let ring = KeyRing::with_clock(generate(), ManualClock::new(NOW_MS));
+        let old_signature = ring.current().sign(b"message");
+        let old_public_key = Hex::encode(ring.current().public_key_bytes());
+
+        ring.rotate(generate(), Duration::from_secs(60)).unwrap();
+        assert!(ring.verify(b"message", &old_signature));
+
+        let history = ring.history(PublicKeyEncoding::Raw).unwrap();
+        assert_ne!(history.current, old_public_key);
+        assert_eq!(history.previous.len(), 1);
+        assert_eq!(history.previous[0].public_key, old_public_key);
+        assert_eq!(history.previous[0].retired_at_ms, NOW_MS);
+        assert_eq!(history.previous[0].valid_until_ms, NOW_MS + 60_000);
+
+        // New signatures come from the new key only
+        let new_signature = ring.current().sign(b"message");
+        assert_ne!(new_signature, old_signature);
+        assert!(ring.verify(b"message", &new_signature));
+    }
+
+    #[test]
+    fn test_retired_key_expires_after_grace_period() {
+        let clock = Arc::new(ManualClock::new(NOW_MS));
+        let ring = KeyRing::with_clock(generate(), clock.clone());
+        let old_signature = ring.current().sign(b"message");
+
+        ring.rotate(generate(), Duration::from_secs(60)).unwrap();
+        clock.advance(60_000 - 1);
+        assert!(ring.verify(b"message", &old_signature));
+        assert_eq!(ring.history(PublicKeyEncoding::Raw).unwrap().previous.len(), 1);
+
+        clock.advance(1);
+        assert!(!ring.verify(b"message", &old_signature));
+        assert!(ring.history(PublicKeyEncoding::Raw).unwrap().previous.is_empty());
+
+        // A zero grace period retires the key immediately
+        let old_signature = ring.current().sign(b"message");
+        ring.rotate(generate(), Duration::ZERO).unwrap();
+        assert!(!ring.verify(b"message", &old_signature));
+    }
+
+    #[test]
+    fn test_public_key_encodings() {
+        let ring = KeyRing::new(generate());
+        let raw = ring.current().public_key_bytes();
+        assert_eq!(raw.len(), 32);
+        let encoded = |encoding: Option<&str>| {
+            let encoding = PublicKeyEncoding::parse(encoding).unwrap();
+            ring.history(encoding).unwrap().current
+        };
+
+        // Default is the raw key, as committed to in the attestation document
+        assert_eq!(Hex::decode(&encoded(None)).unwrap(), raw);
+        assert_eq!(Hex::decode(&encoded(Some("raw"))).unwrap(), raw);
+
+        let hex = encoded(Some("hex"));
+        assert!(hex.starts_with("0x"));
+        assert_eq!(Hex::decode(&hex[2..]).unwrap(), raw);
+
+        // Ed25519 flag 0x00, then the 32 key bytes
+        let sui = Hex::decode(&encoded(Some("SUI"))).unwrap();
+        assert_eq!(sui.len(), 33);
+        assert_eq!(sui[0], 0x00);
+        assert_eq!(&sui[1..], raw.as_slice());
+
+        assert!(PublicKeyEncoding::parse(Some("base58")).is_err());
+    }
+}
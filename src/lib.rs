@@ -1,68 +1,540 @@
 // Copyright (c), Mysten Labs, Inc.
 // SPDX-License-Identifier: Apache-2.0
 
+pub mod access_log;
+pub mod attested;
+pub mod audit;
+pub mod auth;
+pub mod batch;
+pub mod batch_commitment;
+pub mod batch_dataset;
+pub mod bcs_guard;
+pub mod bcs_preview;
+pub mod cache;
+pub mod capabilities;
+pub mod catch_panic;
+pub mod clock;
 pub mod common;
-
-use common::{to_signed_response, IntentMessage, IntentScope, ProcessDataRequest, ProcessedDataResponse};
+pub mod config;
+pub mod cors;
+pub mod decompress;
+pub mod dedup;
+pub mod dev_key;
+pub mod fetch;
+pub mod format;
+pub mod hashing;
+pub mod head;
+pub mod idempotency;
+pub mod ipfs;
+pub mod key_rotation;
+pub mod limits;
+pub mod logging;
+pub mod merkle;
+pub mod metrics;
+pub mod multi_scope;
+pub mod preflight;
+pub mod provenance;
+pub mod rate_limit;
+pub mod receipt;
+pub mod rejections;
+pub mod request_id;
+pub mod router;
+#[cfg(feature = "schema")]
+pub mod schema;
+pub mod selftest;
+pub mod server;
+pub mod sign_intent;
+pub mod signer;
+pub mod text;
+pub mod timestamp;
+pub mod url_policy;
+pub mod walrus;
+pub mod webhook;
+
+use common::{
+    to_signed_response, IntentMessage, IntentScope, ProcessDataRequest, ProcessedDataResponse,
+    SigningQuery,
+};
 
 // Re-export attestation and health_check functions from common
-pub use common::{get_attestation, health_check};
-use axum::extract::State;
+pub use common::{get_attestation, health, health_check, version};
+use axum::extract::{Query, State};
 use axum::Json;
+use decompress::Decompression;
 use fastcrypto::encoding::{Encoding, Hex};
-use fastcrypto::ed25519::Ed25519KeyPair;
+use hashing::HashAlgorithm;
+use metrics::Metrics;
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
 use std::sync::Arc;
 use std::fmt;
 use tracing::info;
 
+/// Intent scopes dataset metadata may be signed under. Other scopes sign
+/// other payload types and are refused to avoid cross-type confusion.
+pub const METADATA_INTENT_SCOPES: [IntentScope; 2] =
+    [IntentScope::ProcessData, IntentScope::VerifyMetadata];
+
+/// Default window (in either direction) within which a client-provided
+/// metadata timestamp is considered fresh: 5 minutes.
+pub const DEFAULT_TIMESTAMP_FRESHNESS_MS: u64 = 5 * 60 * 1000;
+
+/// Default request body limit for single-dataset endpoints: 1 MiB.
+/// Metadata for one dataset never needs more than a few KB.
+pub const DEFAULT_MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// Default request body limit for batch endpoints: 16 MiB.
+pub const DEFAULT_MAX_BATCH_BODY_BYTES: usize = 16 * 1024 * 1024;
+
+/// Default maximum number of items in a batch request.
+pub const DEFAULT_MAX_BATCH_ITEMS: usize = 1000;
+
+/// Default budget for the declared sizes of all items in one
+/// `batch_verify_dataset` request: 16 GiB, sixteen maximum-size datasets.
+pub const DEFAULT_MAX_BATCH_TOTAL_BYTES: u64 = 16 * 1024 * 1024 * 1024;
+
+/// Default limit on the hex digits of a client's `expected_hash`: enough for
+/// a 64-byte digest, the longest any supported algorithm produces.
+pub const DEFAULT_MAX_EXPECTED_HASH_HEX_LEN: usize = 128;
+
+/// Default limit on one BCS-encoded item sent by a client: 64 KiB. A
+/// `DatasetVerification` within the default field limits is under 5 KiB.
+pub const DEFAULT_MAX_BCS_ITEM_BYTES: usize = 64 * 1024;
+
+/// Default number of batch items fetched at once. Kept below
+/// `fetch::DEFAULT_MAX_CONCURRENT_FETCHES` so one batch leaves fetch slots
+/// free for single requests.
+pub const DEFAULT_MAX_BATCH_FETCH_CONCURRENCY: usize = 4;
+
+/// Default limit on the size of a fetched dataset, all parts combined: 1 GiB.
+pub const DEFAULT_MAX_DATASET_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// Default minimum size of a fetched dataset: anything but an empty body.
+pub const DEFAULT_MIN_DATASET_BYTES: u64 = 1;
+
+/// Schema versions accepted by default in dataset requests.
+pub const DEFAULT_SUPPORTED_SCHEMA_VERSIONS: &[&str] = &["v1.0"];
+
+/// Maximum number of URLs in a multi-part dataset request.
+pub const MAX_DATASET_PARTS: usize = 100;
+
 /// App state, at minimum needs to maintain the ephemeral keypair
 pub struct AppState {
-    /// Ephemeral keypair on boot
-    pub eph_kp: Ed25519KeyPair,
-    /// API key for external services (unused in dataset verification)
+    /// Ephemeral signing key generated on boot, plus recently rotated-out keys
+    pub signer: key_rotation::KeyRing,
+    /// API key clients must present on protected endpoints
     pub api_key: String,
+    /// Whether protected endpoints enforce `api_key`
+    pub require_api_key: bool,
+    /// Require `uploader` to be a 0x-prefixed, 32-byte hex Sui address.
+    /// Disable for fixtures that use short placeholders like `0xA`.
+    pub strict_uploader_address: bool,
+    /// Reject control characters in `name`, `description` and `format` (line
+    /// breaks and tabs stay allowed in `description`). Invalid UTF-8 is always
+    /// rejected.
+    pub reject_control_chars: bool,
+    /// Require `walrus_blob_id` to be a base64url-encoded 32-byte Walrus blob
+    /// ID. Off by default for fixtures that use placeholders like `blob-123`.
+    pub strict_walrus_blob_id: bool,
+    /// Report every failed metadata check in one `ValidationFailed` error
+    /// instead of only the first one.
+    pub collect_validation_errors: bool,
+    /// Prometheus metrics shared across handlers
+    pub metrics: Metrics,
+    /// HTTP client shared by all dataset fetches, decoding gzip/deflate bodies
+    pub http_client: reqwest::Client,
+    /// HTTP client returning bodies exactly as sent on the wire
+    pub raw_http_client: reqwest::Client,
+    /// HTTP client for webhook deliveries. `WEBHOOK_URL` is operator
+    /// configured, so it may resolve to an internal address.
+    pub webhook_client: reqwest::Client,
+    /// Retry policy for transient dataset fetch failures
+    pub fetch_retry: fetch::RetryPolicy,
+    /// Limit on concurrent outbound dataset fetches
+    pub fetch_limiter: fetch::FetchLimiter,
+    /// Maximum number of redirects followed for one dataset fetch
+    pub max_redirects: usize,
+    /// Serve a clearly-marked mock attestation when no NSM device is present (dev only)
+    pub allow_mock_attestation: bool,
+    /// URL probed by `/health_check` to confirm outbound network access
+    pub health_canary_url: Option<String>,
+    /// When the app state was created, used to report uptime
+    pub started_at: std::time::Instant,
+    /// Wall clock for signed timestamps and freshness and dedup windows
+    pub clock: Box<dyn clock::Clock>,
+    /// Size, length, batch, freshness and timeout bounds
+    pub limits: limits::Limits,
+    /// Gateway `ipfs://<cid>` dataset URLs are fetched through
+    pub ipfs_gateway: String,
+    /// Digest `verify_metadata` requires `original_hash` to be the length of.
+    /// `None` accepts any non-empty hash.
+    pub metadata_hash_algorithm: Option<HashAlgorithm>,
+    /// Which URLs dataset fetches may target
+    pub url_policy: url_policy::UrlPolicy,
+    /// Previously signed responses for client-provided metadata
+    pub signature_cache: cache::SignatureCache,
+    /// `schema_version` values accepted in dataset requests
+    pub supported_schema_versions: Vec<String>,
+    /// `format` values `process_data` and `verify_metadata` will sign,
+    /// compared case-insensitively. Empty (the default) allows any format.
+    pub allowed_formats: Vec<String>,
+    /// Columns a CSV header must contain, per `schema_version`
+    pub csv_schemas: std::collections::HashMap<String, Vec<String>>,
+    /// Recently verified `original_hash` values `verify_metadata` refuses to
+    /// sign again. `None` (the default) allows re-verification.
+    pub recent_hashes: Option<dedup::RecentHashes>,
+    /// `dataset_id` each `seal_policy_id` was first verified with; when set,
+    /// `verify_metadata` refuses to pair a policy with another dataset.
+    pub policy_bindings: Option<dedup::PolicyBindings>,
+    /// Per-client request throttling on protected endpoints. `None` (the
+    /// default) disables it.
+    pub rate_limiter: Option<rate_limit::RateLimiter>,
+    /// Receiver notified of every successful `verify_metadata` and
+    /// `process_data`. `None` (the default) sends nothing.
+    pub webhook: Option<webhook::Webhook>,
+    /// Set once shutdown begins. Signing requests are then refused with 503
+    /// and `/health_check` reports "draining" while in-flight work completes.
+    pub draining: std::sync::atomic::AtomicBool,
+    /// Payload types `/sign_intent` may sign. Empty by default.
+    pub payload_types: sign_intent::PayloadRegistry,
+    /// Recent signed dataset verifications, served by `/audit`. In memory
+    /// only: lost on restart.
+    pub audit_log: audit::AuditLog,
+    /// Recent error responses of protected endpoints, served by `/rejections`.
+    /// In memory only: lost on restart.
+    pub rejection_log: rejections::RejectionLog,
+    /// Responses kept per `Idempotency-Key`, replayed instead of signing again
+    pub idempotency: idempotency::IdempotencyCache,
+    /// Minimum time before `verify_metadata` and its `_scopes` and
+    /// `_attested` variants return an error, so rejection latency doesn't
+    /// reveal which check failed. `None` (the default) returns errors as
+    /// soon as they occur.
+    pub rejection_floor: Option<std::time::Duration>,
+}
+
+impl AppState {
+    /// Create app state with default policy settings.
+    pub fn new(signer: impl signer::Signer + 'static, api_key: String) -> Self {
+        Self {
+            signer: key_rotation::KeyRing::new(signer),
+            api_key,
+            require_api_key: true,
+            strict_uploader_address: true,
+            reject_control_chars: true,
+            collect_validation_errors: true,
+            strict_walrus_blob_id: false,
+            metrics: Metrics::new(),
+            http_client: fetch::build_http_client(true, None, Some(&Default::default())),
+            raw_http_client: fetch::build_http_client(false, None, Some(&Default::default())),
+            webhook_client: fetch::build_http_client(false, None, None),
+            fetch_retry: fetch::RetryPolicy::default(),
+            fetch_limiter: fetch::FetchLimiter::default(),
+            max_redirects: fetch::DEFAULT_MAX_REDIRECTS,
+            allow_mock_attestation: false,
+            health_canary_url: None,
+            started_at: std::time::Instant::now(),
+            clock: Box::new(clock::SystemClock),
+            limits: limits::Limits::default(),
+            ipfs_gateway: ipfs::DEFAULT_IPFS_GATEWAY.to_string(),
+            metadata_hash_algorithm: Some(HashAlgorithm::Sha256),
+            url_policy: url_policy::UrlPolicy::default(),
+            signature_cache: cache::SignatureCache::default(),
+            supported_schema_versions: DEFAULT_SUPPORTED_SCHEMA_VERSIONS
+                .iter()
+                .map(|version| version.to_string())
+                .collect(),
+            allowed_formats: Vec::new(),
+            recent_hashes: None,
+            policy_bindings: None,
+            rate_limiter: None,
+            webhook: None,
+            draining: std::sync::atomic::AtomicBool::new(false),
+            payload_types: sign_intent::PayloadRegistry::default(),
+            audit_log: audit::AuditLog::default(),
+            rejection_log: rejections::RejectionLog::default(),
+            idempotency: idempotency::IdempotencyCache::default(),
+            csv_schemas: std::collections::HashMap::new(),
+            rejection_floor: None,
+        }
+    }
+}
+
+impl AppState {
+    /// Stop accepting signing requests, so a load balancer polling
+    /// `/health_check` deregisters the instance before it exits.
+    pub fn begin_drain(&self) {
+        self.draining.store(true, std::sync::atomic::Ordering::SeqCst);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    /// Hold an error result until `rejection_floor` has passed since
+    /// `started`. Successes are returned at once.
+    pub async fn pad_rejection<T>(
+        &self,
+        started: tokio::time::Instant,
+        result: &Result<T, EnclaveError>,
+    ) {
+        if let (Some(floor), Err(_)) = (self.rejection_floor, result) {
+            tokio::time::sleep_until(started + floor).await;
+        }
+    }
+
+    /// Refuse new signing work once draining has begun.
+    pub fn check_accepting(&self) -> Result<(), EnclaveError> {
+        if self.is_draining() {
+            return Err(EnclaveError::Draining(
+                "server is shutting down, retry on another instance".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Middleware refusing every protected request once draining has begun, so
+/// no signing endpoint can miss the check.
+pub async fn refuse_while_draining(
+    State(state): State<Arc<AppState>>,
+    request: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> Result<axum::response::Response, EnclaveError> {
+    state.check_accepting()?;
+    Ok(next.run(request).await)
 }
 
 /// Enclave errors enum
 #[derive(Debug)]
 pub enum EnclaveError {
+    /// Catch-all for unexpected internal failures
     GenericError(String),
+    /// Missing or invalid API key
+    Unauthorized(String),
+    /// Request failed validation
+    InvalidInput(String),
+    /// Upstream dataset could not be fetched
+    FetchFailed(String),
+    /// Computed hash does not match the expected one
+    HashMismatch(String),
+    /// Upstream did not respond in time
+    Timeout(String),
+    /// NSM attestation could not be produced
+    AttestationUnavailable(String),
+    /// Requested upstream resource does not exist
+    NotFound(String),
+    /// Request body exceeds the configured size limit
+    PayloadTooLarge(String),
+    /// Request is understood but not permitted by policy
+    Forbidden(String),
+    /// System clock is unusable, e.g. set before the Unix epoch
+    Clock(String),
+    /// Request conflicts with work the enclave already did
+    Conflict(String),
+    /// Client exceeded its request rate limit
+    TooManyRequests(String),
+    /// Fetched content size does not match the declared one
+    SizeMismatch(String),
+    /// Too much fetch work is already queued; retry later
+    Overloaded(String),
+    /// The server is shutting down and takes no new signing requests
+    Draining(String),
+    /// Request failed several validation checks at once. `message` joins
+    /// `errors`, each of which is listed under `validation_errors`.
+    ValidationFailed { message: String, errors: Vec<String> },
+}
+
+impl EnclaveError {
+    /// Collect the messages of `errors` into one `ValidationFailed`.
+    pub fn validation_failed(errors: Vec<EnclaveError>) -> Self {
+        let errors: Vec<String> = errors.iter().map(|e| e.message().to_string()).collect();
+        EnclaveError::ValidationFailed { message: errors.join("; "), errors }
+    }
+
+    /// Machine-readable error code returned in the JSON body.
+    pub fn code(&self) -> &'static str {
+        match self {
+            EnclaveError::GenericError(_) => "generic_error",
+            EnclaveError::Unauthorized(_) => "unauthorized",
+            EnclaveError::InvalidInput(_) => "invalid_input",
+            EnclaveError::FetchFailed(_) => "fetch_failed",
+            EnclaveError::HashMismatch(_) => "hash_mismatch",
+            EnclaveError::Timeout(_) => "timeout",
+            EnclaveError::AttestationUnavailable(_) => "attestation_unavailable",
+            EnclaveError::NotFound(_) => "not_found",
+            EnclaveError::PayloadTooLarge(_) => "payload_too_large",
+            EnclaveError::Forbidden(_) => "forbidden",
+            EnclaveError::Clock(_) => "clock_error",
+            EnclaveError::Conflict(_) => "conflict",
+            EnclaveError::TooManyRequests(_) => "rate_limited",
+            EnclaveError::SizeMismatch(_) => "size_mismatch",
+            EnclaveError::Overloaded(_) => "overloaded",
+            EnclaveError::Draining(_) => "draining",
+            // Same code as a single validation failure, so clients keyed on it keep working
+            EnclaveError::ValidationFailed { .. } => "invalid_input",
+        }
+    }
+
+    /// HTTP status the error maps to.
+    pub fn status_code(&self) -> axum::http::StatusCode {
+        use axum::http::StatusCode;
+        match self {
+            EnclaveError::GenericError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            EnclaveError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            EnclaveError::InvalidInput(_) => StatusCode::BAD_REQUEST,
+            EnclaveError::FetchFailed(_) => StatusCode::BAD_GATEWAY,
+            EnclaveError::HashMismatch(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            EnclaveError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            EnclaveError::AttestationUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            EnclaveError::NotFound(_) => StatusCode::NOT_FOUND,
+            EnclaveError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            EnclaveError::Forbidden(_) => StatusCode::FORBIDDEN,
+            EnclaveError::Clock(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            EnclaveError::Conflict(_) => StatusCode::CONFLICT,
+            EnclaveError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            EnclaveError::SizeMismatch(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            EnclaveError::Overloaded(_) => StatusCode::SERVICE_UNAVAILABLE,
+            EnclaveError::Draining(_) => StatusCode::SERVICE_UNAVAILABLE,
+            EnclaveError::ValidationFailed { .. } => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    /// Human-readable error message, without the variant prefix.
+    pub fn message(&self) -> &str {
+        match self {
+            EnclaveError::GenericError(msg)
+            | EnclaveError::Unauthorized(msg)
+            | EnclaveError::InvalidInput(msg)
+            | EnclaveError::FetchFailed(msg)
+            | EnclaveError::HashMismatch(msg)
+            | EnclaveError::Timeout(msg)
+            | EnclaveError::AttestationUnavailable(msg)
+            | EnclaveError::NotFound(msg)
+            | EnclaveError::PayloadTooLarge(msg)
+            | EnclaveError::Forbidden(msg)
+            | EnclaveError::Clock(msg)
+            | EnclaveError::Conflict(msg)
+            | EnclaveError::TooManyRequests(msg)
+            | EnclaveError::SizeMismatch(msg)
+            | EnclaveError::Overloaded(msg)
+            | EnclaveError::Draining(msg)
+            | EnclaveError::ValidationFailed { message: msg, .. } => msg,
+        }
+    }
 }
 
 impl fmt::Display for EnclaveError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             EnclaveError::GenericError(e) => write!(f, "{}", e),
+            EnclaveError::Unauthorized(e) => write!(f, "Unauthorized: {}", e),
+            EnclaveError::InvalidInput(e) => write!(f, "Invalid input: {}", e),
+            EnclaveError::FetchFailed(e) => write!(f, "Fetch failed: {}", e),
+            EnclaveError::HashMismatch(e) => write!(f, "Hash mismatch: {}", e),
+            EnclaveError::Timeout(e) => write!(f, "Timeout: {}", e),
+            EnclaveError::AttestationUnavailable(e) => write!(f, "Attestation unavailable: {}", e),
+            EnclaveError::NotFound(e) => write!(f, "Not found: {}", e),
+            EnclaveError::PayloadTooLarge(e) => write!(f, "Payload too large: {}", e),
+            EnclaveError::Forbidden(e) => write!(f, "Forbidden: {}", e),
+            EnclaveError::Clock(e) => write!(f, "Clock error: {}", e),
+            EnclaveError::Conflict(e) => write!(f, "Conflict: {}", e),
+            EnclaveError::TooManyRequests(e) => write!(f, "Too many requests: {}", e),
+            EnclaveError::SizeMismatch(e) => write!(f, "Size mismatch: {}", e),
+            EnclaveError::Overloaded(e) => write!(f, "Overloaded: {}", e),
+            EnclaveError::Draining(e) => write!(f, "Draining: {}", e),
+            EnclaveError::ValidationFailed { message, .. } => {
+                write!(f, "Validation failed: {}", message)
+            }
         }
     }
 }
 
 impl std::error::Error for EnclaveError {}
 
+impl From<reqwest::Error> for EnclaveError {
+    fn from(e: reqwest::Error) -> Self {
+        fetch::fetch_error("HTTP request failed", e)
+    }
+}
+
+impl From<std::time::SystemTimeError> for EnclaveError {
+    fn from(e: std::time::SystemTimeError) -> Self {
+        EnclaveError::Clock(format!("Failed to get current timestamp: {}", e))
+    }
+}
+
+impl From<hex::FromHexError> for EnclaveError {
+    fn from(e: hex::FromHexError) -> Self {
+        EnclaveError::InvalidInput(format!("Invalid hex: {}", e))
+    }
+}
+
+impl From<serde_json::Error> for EnclaveError {
+    fn from(e: serde_json::Error) -> Self {
+        EnclaveError::InvalidInput(format!("Invalid JSON: {}", e))
+    }
+}
+
 // Implement IntoResponse for Axum compatibility
 impl axum::response::IntoResponse for EnclaveError {
     fn into_response(self) -> axum::response::Response {
-        let (status, error_message) = match self {
-            EnclaveError::GenericError(msg) => (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                msg
-            ),
-        };
-
-        let body = serde_json::json!({
-            "error": error_message
+        let mut body = serde_json::json!({
+            "error": self.message(),
+            "code": self.code(),
         });
+        if let EnclaveError::ValidationFailed { errors, .. } = &self {
+            body["validation_errors"] = serde_json::json!(errors);
+        }
+
+        let mut response = (self.status_code(), axum::Json(body)).into_response();
+        response.extensions_mut().insert(access_log::ErrorCode(self.code()));
+        if let EnclaveError::Overloaded(_) = self {
+            response.headers_mut().insert(
+                axum::http::header::RETRY_AFTER,
+                axum::http::HeaderValue::from(fetch::QUEUE_FULL_RETRY_AFTER_SECS),
+            );
+        }
+        response
+    }
+}
 
-        (status, axum::Json(body)).into_response()
+/// Replace axum's plain-text 413 (raised when a body exceeds `DefaultBodyLimit`)
+/// with the structured `EnclaveError` JSON.
+pub async fn map_payload_too_large(response: axum::response::Response) -> axum::response::Response {
+    use axum::response::IntoResponse;
+
+    if response.status() == axum::http::StatusCode::PAYLOAD_TOO_LARGE {
+        return EnclaveError::PayloadTooLarge("request body exceeds the size limit".to_string())
+            .into_response();
     }
+    response
+}
+
+/// Layout version of `DatasetVerification` this enclave signs.
+///
+/// `version` is the first byte of the struct's BCS, so a verifier can branch
+/// on it before decoding the rest. BCS has no field names or tags: adding,
+/// removing or reordering a field changes the bytes of every field after it,
+/// so any such change must bump this constant together with the Move struct.
+pub const DATASET_VERIFICATION_VERSION: u8 = 1;
+
+fn current_dataset_verification_version() -> u8 {
+    DATASET_VERIFICATION_VERSION
 }
 
 /// Inner type for IntentMessage<T> - MUST match Move contract exactly
 /// V3 Architecture: Verify metadata only (not fetch datasets)
+///
+/// Migration: version 1 added the leading `version` field. Signatures made
+/// before it cover a layout without that byte and no longer verify against
+/// this struct; the Move `DatasetVerification` must gain `version: u8` as its
+/// first field in the same release.
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DatasetVerification {
+    /// Layout version; defaults to `DATASET_VERIFICATION_VERSION` when
+    /// omitted from JSON
+    #[serde(default = "current_dataset_verification_version")]
+    pub version: u8,
     pub dataset_id: Vec<u8>,          // Unique dataset ID
     pub name: Vec<u8>,                // Dataset name
     pub description: Vec<u8>,          // Dataset description
@@ -76,65 +548,513 @@ pub struct DatasetVerification {
 }
 
 /// Inner type for ProcessDataRequest<T>
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DatasetRequest {
     pub dataset_url: String,
     pub expected_hash: Option<String>,
+    /// Dataset format, e.g. "CSV". When empty it is inferred from the
+    /// response's `Content-Type`, which requires buffering the dataset.
+    pub format: String,
+    pub schema_version: String,
+    /// One of "sha256" (default), "sha512" or "blake2b256"
+    pub hash_algorithm: Option<String>,
+    /// Check that the fetched content really is `format` before signing
+    pub strict_format: Option<bool>,
+    /// Hash the compressed bytes as served instead of decoding a gzip/deflate
+    /// `Content-Encoding` first
+    pub hash_wire_bytes: Option<bool>,
+    /// For CSV datasets, check the header has the columns registered for
+    /// `schema_version`. Ignored for other formats.
+    pub validate_csv_schema: Option<bool>,
+    /// Inclusive `[start, end]` byte offsets to hash instead of the whole
+    /// dataset. The range is recorded in the signed `description`.
+    pub byte_range: Option<(u64, u64)>,
+    /// One of "none" (default), "gzip" or "zstd": how the dataset file itself
+    /// is compressed. It is decompressed before hashing, so `original_hash` and
+    /// `size` describe the decompressed content.
+    pub decompress: Option<String>,
+    /// Credentials for a dataset host that requires authentication. Used for
+    /// this fetch only, never stored, logged or signed.
+    pub auth: Option<fetch::FetchAuth>,
+    /// Sign a `dataset_id` and `description` derived from the fetch instead
+    /// of the legacy placeholders. See `populated_dataset_id` and
+    /// `populated_description`.
+    pub populate_metadata: Option<bool>,
+    /// Fail with `fetch_failed`, naming the target, when `dataset_url`
+    /// redirects, instead of following it.
+    pub no_redirects: Option<bool>,
+}
+
+/// Dataset split across several URLs, hashed as one concatenation in order
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MultipartDatasetRequest {
+    pub dataset_urls: Vec<String>,
+    pub expected_hash: Option<String>,
     pub format: String,
     pub schema_version: String,
+    /// One of "sha256" (default), "sha512" or "blake2b256"
+    pub hash_algorithm: Option<String>,
+    /// Hash the compressed bytes as served instead of decoding a gzip/deflate
+    /// `Content-Encoding` first
+    pub hash_wire_bytes: Option<bool>,
+}
+
+/// Request to compare a dataset against an expected hash, without signing
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CheckHashRequest {
+    pub dataset_url: String,
+    pub expected_hash: String,
+    /// One of "sha256" (default), "sha512" or "blake2b256"
+    pub hash_algorithm: Option<String>,
+}
+
+/// Outcome of a hash check; the computed hash is returned even on mismatch
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CheckHashResponse {
+    pub matches: bool,
+    /// Hex encoded hash of the fetched content
+    pub computed_hash: String,
+    /// Size of the fetched content in bytes
+    pub size: u64,
+}
+
+/// Request to sign metadata carrying a precomputed `original_hash`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SignDigestRequest {
+    pub metadata: DatasetVerification,
+    /// Algorithm that produced `original_hash`: "sha256" (default), "sha512" or "blake2b256"
+    pub hash_algorithm: Option<String>,
+}
+
+/// Request to hash a blob stored on Walrus
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WalrusBlobRequest {
+    pub walrus_blob_id: String,
+    /// Base URL of the Walrus aggregator, e.g. https://aggregator.walrus-testnet.walrus.space
+    pub aggregator_url: String,
+    pub format: String,
+    pub expected_hash: Option<String>,
+    /// One of "sha256" (default), "sha512" or "blake2b256"
+    pub hash_algorithm: Option<String>,
+}
+
+/// Metadata to sign once the referenced Walrus blob is confirmed to match it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VerifyAndFetchWalrusRequest {
+    pub metadata: DatasetVerification,
+    /// Base URL of the Walrus aggregator serving `metadata.walrus_blob_id`
+    pub aggregator_url: String,
+    /// Algorithm that produced `original_hash`: "sha256" (default), "sha512" or "blake2b256"
+    pub hash_algorithm: Option<String>,
+}
+
+/// Metadata to sign once the dataset at `dataset_url` is confirmed to have
+/// exactly the declared `size` and `original_hash`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VerifyDatasetRequest {
+    pub metadata: DatasetVerification,
+    pub dataset_url: String,
+    /// Algorithm that produced `original_hash`: "sha256" (default), "sha512" or "blake2b256"
+    pub hash_algorithm: Option<String>,
+    /// Hash the compressed bytes as served instead of decoding a gzip/deflate
+    /// `Content-Encoding` first
+    pub hash_wire_bytes: Option<bool>,
 }
 
 /// V3 Architecture: Metadata verification request
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct MetadataVerificationRequest {
     pub metadata: DatasetVerification,
+    /// Scope to sign under, by name or discriminant, for contract deployments
+    /// expecting a different one. Defaults to `VerifyMetadata`.
+    pub intent_scope: Option<common::IntentScopeSelector>,
+}
+
+/// Current wall-clock time in milliseconds since the Unix epoch.
+pub(crate) fn current_timestamp_ms() -> Result<u64, EnclaveError> {
+    Ok(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_millis() as u64)
+}
+
+/// Reject timestamps further than `window_ms` from `now_ms` in either direction,
+/// so old metadata cannot be replayed to obtain a fresh signature.
+pub fn check_timestamp_freshness(timestamp: u64, now_ms: u64, window_ms: u64) -> Result<(), EnclaveError> {
+    if timestamp.saturating_add(window_ms) < now_ms {
+        return Err(EnclaveError::InvalidInput(format!(
+            "timestamp {} is stale: more than {} ms behind enclave time {}",
+            timestamp, window_ms, now_ms
+        )));
+    }
+    if timestamp > now_ms.saturating_add(window_ms) {
+        return Err(EnclaveError::InvalidInput(format!(
+            "timestamp {} is in the future: more than {} ms ahead of enclave time {}",
+            timestamp, window_ms, now_ms
+        )));
+    }
+    Ok(())
+}
+
+/// Length in bytes of a Sui address.
+pub const SUI_ADDRESS_LENGTH: usize = 32;
+
+/// Check that `address` is the UTF-8 encoding of a 0x-prefixed hex Sui address
+/// decoding to exactly 32 bytes.
+pub fn validate_sui_address(address: &[u8]) -> Result<(), EnclaveError> {
+    let invalid = || EnclaveError::InvalidInput("invalid uploader address".to_string());
+    let hex_part = std::str::from_utf8(address)
+        .ok()
+        .and_then(|a| a.strip_prefix("0x"))
+        .ok_or_else(invalid)?;
+    let decoded = hex::decode(hex_part).map_err(|_| invalid())?;
+    if decoded.len() != SUI_ADDRESS_LENGTH {
+        return Err(invalid());
+    }
+    Ok(())
+}
+
+/// Reject a `schema_version` the enclave does not support, so an incompatible
+/// client cannot obtain a signature the contract would misinterpret.
+pub fn check_schema_version(state: &AppState, schema_version: &str) -> Result<(), EnclaveError> {
+    if !state.supported_schema_versions.iter().any(|v| v == schema_version) {
+        return Err(EnclaveError::InvalidInput(format!(
+            "unsupported schema_version: {:?} (supported: {})",
+            schema_version,
+            state.supported_schema_versions.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+/// Reject a `format` outside the configured allow-list, so a misconfigured
+/// client cannot get a signature for a format the deployment never handles.
+pub fn check_format_allowed(state: &AppState, format: &str) -> Result<(), EnclaveError> {
+    if state.allowed_formats.is_empty()
+        || state.allowed_formats.iter().any(|allowed| allowed.eq_ignore_ascii_case(format.trim()))
+    {
+        return Ok(());
+    }
+    Err(EnclaveError::Forbidden(format!(
+        "format not permitted: {:?} (allowed: {})",
+        format,
+        state.allowed_formats.join(", ")
+    )))
+}
+
+/// Format named by a dataset's `Content-Type`, for requests that leave
+/// `format` empty.
+fn inferred_format(content_type: Option<&str>) -> Result<String, EnclaveError> {
+    content_type
+        .and_then(format::format_from_content_type)
+        .map(str::to_string)
+        .ok_or_else(|| {
+            EnclaveError::InvalidInput(format!(
+                "format is empty and Content-Type {:?} does not name a known format",
+                content_type.unwrap_or_default()
+            ))
+        })
 }
 
 pub async fn process_data(
     State(state): State<Arc<AppState>>,
+    Query(query): Query<SigningQuery>,
     Json(request): Json<ProcessDataRequest<DatasetRequest>>,
 ) -> Result<Json<ProcessedDataResponse<IntentMessage<DatasetVerification>>>, EnclaveError> {
-    let dataset_url = request.payload.dataset_url.clone();
+    let result = process_dataset(&state, request.payload)
+        .await
+        .and_then(|Json(response)| query.apply(&state.signer, response).map(Json));
+    state.metrics.record("process_data", &result);
+    audit::record(&state, "process_data", &result);
+    webhook::notify(&state, "process_data", &result);
+    result
+}
+
+async fn process_dataset(
+    state: &AppState,
+    payload: DatasetRequest,
+) -> Result<Json<ProcessedDataResponse<IntentMessage<DatasetVerification>>>, EnclaveError> {
+    let (verification, _) = describe_dataset(state, payload).await?;
+    let timestamp = verification.timestamp;
+    Ok(Json(to_signed_response(
+        state.signer.current().as_ref(),
+        verification,
+        timestamp,
+        IntentScope::ProcessData,
+    )))
+}
+
+/// Fetch, check and hash the dataset of a `process_data` request, returning
+/// the `DatasetVerification` to sign and what the dataset's server reported.
+pub(crate) async fn describe_dataset(
+    state: &AppState,
+    payload: DatasetRequest,
+) -> Result<(DatasetVerification, fetch::ResponseInfo), EnclaveError> {
+    if let Some(expected) = &payload.expected_hash {
+        state.limits.check_expected_hash_len(expected)?;
+    }
+    let dataset_url = payload.dataset_url.clone();
     info!("Processing dataset from URL: {}", dataset_url);
 
-    let current_timestamp = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .map_err(|e| EnclaveError::GenericError(format!("Failed to get current timestamp: {}", e)))?
-        .as_millis() as u64;
+    check_schema_version(state, &payload.schema_version)?;
+    // An empty format is taken from the response's Content-Type
+    let infer_format = payload.format.trim().is_empty();
+    if !infer_format {
+        check_format_allowed(state, &payload.format)?;
+    }
 
-    // Fetch dataset content
-    let dataset_content = reqwest::get(&dataset_url)
-        .await
-        .map_err(|e| EnclaveError::GenericError(format!("Failed to fetch dataset: {}", e)))?
-        .bytes()
-        .await
-        .map_err(|e| EnclaveError::GenericError(format!("Failed to read dataset bytes: {}", e)))?;
+    let current_timestamp = state.clock.now_millis()?;
+    let algorithm = HashAlgorithm::parse(payload.hash_algorithm.as_deref())?;
+    // ipfs:// URLs are fetched through the gateway; the signed name keeps the CID
+    let fetch_url = ipfs::resolve_dataset_url(&state.ipfs_gateway, &dataset_url)?;
+
+    if let Some(range) = payload.byte_range {
+        fetch::check_byte_range(range, state.limits.max_dataset_bytes)?;
+    }
+    let decompression = Decompression::parse(payload.decompress.as_deref())?;
+    if decompression != Decompression::None && payload.byte_range.is_some() {
+        return Err(EnclaveError::InvalidInput(
+            "byte_range cannot be combined with decompress".to_string(),
+        ));
+    }
+    let fetch_options = fetch::FetchOptions {
+        wire_bytes: payload.hash_wire_bytes.unwrap_or(false),
+        byte_range: payload.byte_range,
+        auth: payload.auth.clone(),
+        no_redirects: payload.no_redirects.unwrap_or(false),
+        ..Default::default()
+    };
+    let check_format = payload.strict_format.unwrap_or(false);
+    let validate_csv_schema = payload.validate_csv_schema.unwrap_or(false);
+
+    // Content checks, decompression and format inference need the whole body;
+    // otherwise stream it into the hasher
+    let buffered = check_format
+        || validate_csv_schema
+        || infer_format
+        || decompression != Decompression::None;
+    let (dataset_hash, dataset_size, dataset_format, response_info) = if buffered {
+        let (mut dataset_content, response_info) =
+            fetch::fetch_dataset_with_info(state, &fetch_url, &fetch_options).await?;
+        let dataset_format = if infer_format {
+            let dataset_format = inferred_format(response_info.content_type.as_deref())?;
+            check_format_allowed(state, &dataset_format)?;
+            dataset_format
+        } else {
+            payload.format.clone()
+        };
+        if decompression != Decompression::None {
+            let max_bytes = state.limits.max_dataset_bytes;
+            dataset_content = decompression.decompress(&dataset_content, max_bytes)?.into();
+        }
+
+        if check_format {
+            format::check_content_format(&dataset_format, &dataset_content)?;
+        }
+        if validate_csv_schema && dataset_format.trim().eq_ignore_ascii_case("CSV") {
+            let columns = state.csv_schemas.get(&payload.schema_version).ok_or_else(|| {
+                EnclaveError::InvalidInput(format!(
+                    "no CSV schema registered for schema_version {}",
+                    payload.schema_version
+                ))
+            })?;
+            format::check_csv_columns(&dataset_content, columns)?;
+        }
 
-    // Compute SHA256 hash
-    let mut hasher = Sha256::new();
-    hasher.update(&dataset_content);
-    let hash_result = hasher.finalize();
-    let dataset_hash = hash_result.to_vec();
+        let hash = algorithm.digest(&dataset_content);
+        (hash, dataset_content.len() as u64, dataset_format, response_info)
+    } else {
+        let (hash, size, response_info) =
+            fetch::fetch_hashed_with_info(state, &fetch_url, algorithm, &fetch_options).await?;
+        (hash, size, payload.format.clone(), response_info)
+    };
+    // Catches misconfigured URLs answering 200 with an empty body
+    state.limits.check_min_dataset_size(dataset_size)?;
 
     // Optionally verify against expected hash
-    if let Some(expected) = &request.payload.expected_hash {
-        let expected_bytes = hex::decode(expected)
-            .map_err(|_| EnclaveError::GenericError("Invalid expected hash format".to_string()))?;
+    if let Some(expected) = &payload.expected_hash {
+        let expected_bytes = text::decode_hex("expected hash", expected)?;
+        if dataset_hash != expected_bytes {
+            return Err(EnclaveError::HashMismatch("Dataset hash mismatch".to_string()));
+        }
+    }
+
+    info!(
+        "Dataset verified: {}={}, size={} bytes",
+        algorithm.name(),
+        Hex::encode(&dataset_hash),
+        dataset_size
+    );
+
+    let (dataset_id, description) = if payload.populate_metadata.unwrap_or(false) {
+        let description = populated_description(
+            algorithm,
+            dataset_size,
+            &payload.schema_version,
+            payload.byte_range,
+        );
+        (populated_dataset_id(&dataset_url), description.into_bytes())
+    } else {
+        let description = match payload.byte_range {
+            // Tell consumers the hash only covers part of the dataset
+            Some((start, end)) => {
+                format!("Partial hash of bytes {}-{} - legacy endpoint", start, end)
+            }
+            None => "Legacy endpoint - use verify_metadata instead".to_string(),
+        };
+        (b"legacy".to_vec(), description.into_bytes())
+    };
+
+    let verification = DatasetVerification {
+        version: DATASET_VERIFICATION_VERSION,
+        dataset_id,
+        name: dataset_url.as_bytes().to_vec(),
+        description,
+        format: dataset_format.into_bytes(),
+        size: dataset_size,
+        original_hash: dataset_hash,
+        walrus_blob_id: b"".to_vec(),
+        seal_policy_id: b"".to_vec(),
+        timestamp: current_timestamp,
+        uploader: b"".to_vec(),
+    };
+    Ok((verification, response_info))
+}
+
+/// `dataset_id` signed by `process_data` with `populate_metadata`: the hex
+/// SHA-256 of `dataset_url` exactly as sent, so one URL always maps to one id.
+pub fn populated_dataset_id(dataset_url: &str) -> Vec<u8> {
+    Hex::encode(HashAlgorithm::Sha256.digest(dataset_url.as_bytes())).into_bytes()
+}
+
+/// `description` signed by `process_data` with `populate_metadata`, stating
+/// what the enclave hashed, e.g. `sha256 of 1024 bytes, schema_version v1.0`
+/// or `sha256 of bytes 0-99, schema_version v1.0` for a byte range.
+pub fn populated_description(
+    algorithm: HashAlgorithm,
+    size: u64,
+    schema_version: &str,
+    byte_range: Option<(u64, u64)>,
+) -> String {
+    let hashed = match byte_range {
+        Some((start, end)) => format!("bytes {}-{}", start, end),
+        None => format!("{} bytes", size),
+    };
+    format!("{} of {}, schema_version {}", algorithm.name(), hashed, schema_version)
+}
+
+/// Fetch a dataset and report whether it matches `expected_hash`. Never signs.
+pub async fn check_hash(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<CheckHashRequest>,
+) -> Result<Json<CheckHashResponse>, EnclaveError> {
+    let result = compare_dataset_hash(&state, request).await;
+    state.metrics.record("check_hash", &result);
+    result
+}
+
+async fn compare_dataset_hash(
+    state: &AppState,
+    request: CheckHashRequest,
+) -> Result<Json<CheckHashResponse>, EnclaveError> {
+    let algorithm = HashAlgorithm::parse(request.hash_algorithm.as_deref())?;
+    state.limits.check_expected_hash_len(&request.expected_hash)?;
+    let expected = text::decode_hex("expected hash", &request.expected_hash)?;
+
+    let (computed, size) = fetch::fetch_hashed(
+        state,
+        &request.dataset_url,
+        algorithm,
+        &fetch::FetchOptions::default(),
+    )
+    .await?;
+
+    let matches = computed == expected;
+    info!(
+        "Hash check for {}: {}={}, matches={}",
+        request.dataset_url,
+        algorithm.name(),
+        Hex::encode(&computed),
+        matches
+    );
+
+    Ok(Json(CheckHashResponse {
+        matches,
+        computed_hash: Hex::encode(&computed),
+        size,
+    }))
+}
+
+/// Fetch every part of a multi-part dataset in order and sign one hash over
+/// their concatenation.
+pub async fn process_multipart_data(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<ProcessDataRequest<MultipartDatasetRequest>>,
+) -> Result<Json<ProcessedDataResponse<IntentMessage<DatasetVerification>>>, EnclaveError> {
+    let result = process_multipart_dataset(&state, request.payload).await;
+    state.metrics.record("process_multipart_data", &result);
+    audit::record(&state, "process_multipart_data", &result);
+    result
+}
+
+async fn process_multipart_dataset(
+    state: &AppState,
+    payload: MultipartDatasetRequest,
+) -> Result<Json<ProcessedDataResponse<IntentMessage<DatasetVerification>>>, EnclaveError> {
+    if payload.dataset_urls.is_empty() {
+        return Err(EnclaveError::InvalidInput("dataset_urls cannot be empty".to_string()));
+    }
+    if payload.dataset_urls.len() > MAX_DATASET_PARTS {
+        return Err(EnclaveError::InvalidInput(format!(
+            "too many dataset_urls: {} (max {})",
+            payload.dataset_urls.len(),
+            MAX_DATASET_PARTS
+        )));
+    }
+    if let Some(expected) = &payload.expected_hash {
+        state.limits.check_expected_hash_len(expected)?;
+    }
+    info!("Processing multi-part dataset with {} parts", payload.dataset_urls.len());
+
+    check_schema_version(state, &payload.schema_version)?;
+    check_format_allowed(state, &payload.format)?;
+
+    let current_timestamp = state.clock.now_millis()?;
+    let algorithm = HashAlgorithm::parse(payload.hash_algorithm.as_deref())?;
+
+    let fetch_options = fetch::FetchOptions {
+        wire_bytes: payload.hash_wire_bytes.unwrap_or(false),
+        ..Default::default()
+    };
+    let (dataset_hash, size) =
+        fetch::fetch_parts_hashed(state, &payload.dataset_urls, algorithm, &fetch_options).await?;
+    state.limits.check_min_dataset_size(size)?;
+
+    if let Some(expected) = &payload.expected_hash {
+        let expected_bytes = text::decode_hex("expected hash", expected)?;
         if dataset_hash != expected_bytes {
-            return Err(EnclaveError::GenericError("Dataset hash mismatch".to_string()));
+            return Err(EnclaveError::HashMismatch("Dataset hash mismatch".to_string()));
         }
     }
 
-    info!("Dataset verified: hash={}, size={} bytes", Hex::encode(&dataset_hash), dataset_content.len());
+    info!(
+        "Multi-part dataset verified: {}={}, size={} bytes",
+        algorithm.name(),
+        Hex::encode(&dataset_hash),
+        size
+    );
 
     Ok(Json(to_signed_response(
-        &state.eph_kp,
+        state.signer.current().as_ref(),
         DatasetVerification {
-            dataset_id: b"legacy".to_vec(),
-            name: dataset_url.as_bytes().to_vec(),
-            description: b"Legacy endpoint - use verify_metadata instead".to_vec(),
-            format: request.payload.format.as_bytes().to_vec(),
-            size: dataset_content.len() as u64,
+            version: DATASET_VERIFICATION_VERSION,
+            dataset_id: b"multipart".to_vec(),
+            name: payload.dataset_urls.join(",").into_bytes(),
+            description: format!("Multi-part dataset ({} parts)", payload.dataset_urls.len())
+                .into_bytes(),
+            format: payload.format.as_bytes().to_vec(),
+            size,
             original_hash: dataset_hash,
             walrus_blob_id: b"".to_vec(),
             seal_policy_id: b"".to_vec(),
@@ -146,39 +1066,253 @@ pub async fn process_data(
     )))
 }
 
-/// V3 Architecture: Verify metadata and sign (no dataset fetching)
-/// This is the NEW endpoint that should be used for production
-pub async fn verify_metadata(
+/// Fetch a blob from a Walrus aggregator, hash the stored bytes and sign a
+/// verification carrying the real `walrus_blob_id`.
+pub async fn verify_walrus_blob(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<MetadataVerificationRequest>,
+    Json(request): Json<WalrusBlobRequest>,
 ) -> Result<Json<ProcessedDataResponse<IntentMessage<DatasetVerification>>>, EnclaveError> {
-    info!("Verifying dataset metadata (V3 architecture)");
-
-    let metadata = request.metadata;
+    let result = hash_walrus_blob(&state, request).await;
+    state.metrics.record("verify_walrus_blob", &result);
+    audit::record(&state, "verify_walrus_blob", &result);
+    result
+}
 
-    // Validate metadata fields
-    if metadata.dataset_id.is_empty() {
-        return Err(EnclaveError::GenericError("dataset_id cannot be empty".to_string()));
+async fn hash_walrus_blob(
+    state: &AppState,
+    request: WalrusBlobRequest,
+) -> Result<Json<ProcessedDataResponse<IntentMessage<DatasetVerification>>>, EnclaveError> {
+    info!("Verifying Walrus blob: {}", request.walrus_blob_id);
+    if let Some(expected) = &request.expected_hash {
+        state.limits.check_expected_hash_len(expected)?;
     }
 
-    if metadata.name.is_empty() {
-        return Err(EnclaveError::GenericError("name cannot be empty".to_string()));
+    let current_timestamp = state.clock.now_millis()?;
+    let algorithm = HashAlgorithm::parse(request.hash_algorithm.as_deref())?;
+    let blob_content = fetch_walrus_blob(state, &request.aggregator_url, &request.walrus_blob_id).await?;
+
+    let blob_hash = algorithm.digest(&blob_content);
+
+    if let Some(expected) = &request.expected_hash {
+        let expected_bytes = text::decode_hex("expected hash", expected)?;
+        if blob_hash != expected_bytes {
+            return Err(EnclaveError::HashMismatch("Walrus blob hash mismatch".to_string()));
+        }
     }
 
-    if metadata.original_hash.is_empty() {
-        return Err(EnclaveError::GenericError("original_hash cannot be empty".to_string()));
+    info!(
+        "Walrus blob verified: {}={}, size={} bytes",
+        algorithm.name(),
+        Hex::encode(&blob_hash),
+        blob_content.len()
+    );
+
+    Ok(Json(to_signed_response(
+        state.signer.current().as_ref(),
+        DatasetVerification {
+            version: DATASET_VERIFICATION_VERSION,
+            dataset_id: request.walrus_blob_id.as_bytes().to_vec(),
+            name: request.walrus_blob_id.as_bytes().to_vec(),
+            description: b"Walrus blob content verified by enclave".to_vec(),
+            format: request.format.as_bytes().to_vec(),
+            size: blob_content.len() as u64,
+            original_hash: blob_hash,
+            walrus_blob_id: request.walrus_blob_id.as_bytes().to_vec(),
+            seal_policy_id: b"".to_vec(),
+            timestamp: current_timestamp,
+            uploader: b"".to_vec(),
+        },
+        current_timestamp,
+        IntentScope::ProcessData,
+    )))
+}
+
+/// Fetch the stored content of `blob_id` from a Walrus aggregator.
+async fn fetch_walrus_blob(
+    state: &AppState,
+    aggregator_url: &str,
+    blob_id: &str,
+) -> Result<bytes::Bytes, EnclaveError> {
+    let url = walrus::blob_url(aggregator_url, blob_id)?;
+
+    fetch::fetch_dataset(state, &url, &fetch::FetchOptions::default())
+        .await
+        .map_err(|e| match e {
+            EnclaveError::NotFound(_) => EnclaveError::NotFound(format!(
+                "Walrus blob {} is not available from the aggregator",
+                blob_id
+            )),
+            e => e,
+        })
+}
+
+/// Validate metadata like `verify_metadata`, then fetch the referenced Walrus
+/// blob and only sign if its hash equals the claimed `original_hash`.
+pub async fn verify_and_fetch_walrus(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<VerifyAndFetchWalrusRequest>,
+) -> Result<Json<ProcessedDataResponse<IntentMessage<DatasetVerification>>>, EnclaveError> {
+    let result = verify_metadata_against_walrus(&state, request).await;
+    state.metrics.record("verify_and_fetch_walrus", &result);
+    audit::record(&state, "verify_and_fetch_walrus", &result);
+    result
+}
+
+async fn verify_metadata_against_walrus(
+    state: &AppState,
+    request: VerifyAndFetchWalrusRequest,
+) -> Result<Json<ProcessedDataResponse<IntentMessage<DatasetVerification>>>, EnclaveError> {
+    let algorithm = HashAlgorithm::parse(request.hash_algorithm.as_deref())?;
+    let metadata = prepare_client_metadata(state, request.metadata)?;
+
+    let blob_id = std::str::from_utf8(&metadata.walrus_blob_id).map_err(|_| {
+        EnclaveError::InvalidInput("walrus_blob_id must be valid UTF-8".to_string())
+    })?;
+    info!("Verifying metadata against Walrus blob: {}", blob_id);
+
+    let blob_content = fetch_walrus_blob(state, &request.aggregator_url, blob_id).await?;
+    let blob_hash = algorithm.digest(&blob_content);
+
+    if blob_hash != metadata.original_hash {
+        return Err(EnclaveError::HashMismatch(format!(
+            "Walrus blob {} hash mismatch: computed {}={}, claimed {}",
+            blob_id,
+            algorithm.name(),
+            Hex::encode(&blob_hash),
+            Hex::encode(&metadata.original_hash)
+        )));
     }
 
-    if metadata.walrus_blob_id.is_empty() {
-        return Err(EnclaveError::GenericError("walrus_blob_id cannot be empty".to_string()));
+    info!(
+        "Walrus blob matches metadata: {}={}, size={} bytes",
+        algorithm.name(),
+        Hex::encode(&blob_hash),
+        blob_content.len()
+    );
+
+    record_client_metadata(state, &metadata)?;
+    let signer = state.signer.current();
+    Ok(Json(sign_client_metadata(state, signer.as_ref(), metadata, IntentScope::VerifyMetadata)))
+}
+
+/// Fetch the dataset and sign the metadata only if both the fetched byte
+/// count equals `size` and its hash equals `original_hash`. A wrong size is
+/// reported as `SizeMismatch`, a wrong hash as `HashMismatch`. The download
+/// stops as soon as it passes the declared size.
+pub async fn verify_dataset(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<VerifyDatasetRequest>,
+) -> Result<Json<ProcessedDataResponse<IntentMessage<DatasetVerification>>>, EnclaveError> {
+    let result = verify_dataset_claims(&state, request).await;
+    state.metrics.record("verify_dataset", &result);
+    audit::record(&state, "verify_dataset", &result);
+    result
+}
+
+pub(crate) async fn verify_dataset_claims(
+    state: &AppState,
+    request: VerifyDatasetRequest,
+) -> Result<Json<ProcessedDataResponse<IntentMessage<DatasetVerification>>>, EnclaveError> {
+    let algorithm = HashAlgorithm::parse(request.hash_algorithm.as_deref())?;
+    let metadata = prepare_client_metadata(state, request.metadata)?;
+
+    info!("Verifying declared size and hash of dataset: {}", request.dataset_url);
+
+    let options = fetch::FetchOptions {
+        wire_bytes: request.hash_wire_bytes.unwrap_or(false),
+        declared_size: Some(metadata.size),
+        ..Default::default()
+    };
+    let (hash, size) =
+        fetch::fetch_hashed(state, &request.dataset_url, algorithm, &options).await?;
+
+    state.limits.check_min_dataset_size(size)?;
+    if size != metadata.size {
+        return Err(EnclaveError::SizeMismatch(format!(
+            "dataset is {} bytes, declared size is {}",
+            size, metadata.size
+        )));
+    }
+    if hash != metadata.original_hash {
+        return Err(EnclaveError::HashMismatch(format!(
+            "computed {}={}, declared original_hash {}",
+            algorithm.name(),
+            Hex::encode(&hash),
+            Hex::encode(&metadata.original_hash)
+        )));
     }
 
-    if metadata.seal_policy_id.is_empty() {
-        return Err(EnclaveError::GenericError("seal_policy_id cannot be empty".to_string()));
+    info!(
+        "Dataset matches declared size and hash: {}={}, size={} bytes",
+        algorithm.name(),
+        Hex::encode(&hash),
+        size
+    );
+
+    record_client_metadata(state, &metadata)?;
+    let signer = state.signer.current();
+    Ok(Json(sign_client_metadata(state, signer.as_ref(), metadata, IntentScope::VerifyMetadata)))
+}
+
+/// V3 Architecture: Verify metadata and sign (no dataset fetching)
+/// This is the NEW endpoint that should be used for production
+pub async fn verify_metadata(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SigningQuery>,
+    Json(request): Json<MetadataVerificationRequest>,
+) -> Result<Json<ProcessedDataResponse<IntentMessage<DatasetVerification>>>, EnclaveError> {
+    let started = tokio::time::Instant::now();
+    let result = metadata_intent_scope(request.intent_scope.as_ref())
+        .and_then(|intent| verify_and_sign_metadata(&state, request.metadata, intent))
+        .and_then(|Json(response)| query.apply(&state.signer, response).map(Json));
+    state.pad_rejection(started, &result).await;
+    state.metrics.record("verify_metadata", &result);
+    audit::record(&state, "verify_metadata", &result);
+    webhook::notify(&state, "verify_metadata", &result);
+    result
+}
+
+/// Resolve the scope requested for signing metadata, defaulting to `VerifyMetadata`.
+pub(crate) fn metadata_intent_scope(
+    selector: Option<&common::IntentScopeSelector>,
+) -> Result<IntentScope, EnclaveError> {
+    let Some(selector) = selector else {
+        return Ok(IntentScope::VerifyMetadata);
+    };
+    let intent = IntentScope::from_selector(selector)?;
+    if !METADATA_INTENT_SCOPES.contains(&intent) {
+        return Err(EnclaveError::InvalidInput(format!(
+            "intent_scope {:?} cannot be used to sign dataset metadata",
+            intent
+        )));
     }
+    Ok(intent)
+}
+
+fn verify_and_sign_metadata(
+    state: &AppState,
+    metadata: DatasetVerification,
+    intent: IntentScope,
+) -> Result<Json<ProcessedDataResponse<IntentMessage<DatasetVerification>>>, EnclaveError> {
+    let metadata = check_client_metadata(state, metadata)?;
+    info!("Metadata verified successfully, signing under {:?}...", intent);
+
+    // Sign the metadata and return
+    let signer = state.signer.current();
+    Ok(Json(sign_client_metadata(state, signer.as_ref(), metadata, intent)))
+}
+
+/// Run every `verify_metadata` check on client metadata, binding its policy
+/// and recording its hash, and return it canonicalized, ready to sign.
+pub(crate) fn check_client_metadata(
+    state: &AppState,
+    metadata: DatasetVerification,
+) -> Result<DatasetVerification, EnclaveError> {
+    info!("Verifying dataset metadata (V3 architecture)");
 
-    if metadata.uploader.is_empty() {
-        return Err(EnclaveError::GenericError("uploader cannot be empty".to_string()));
+    let metadata = prepare_client_metadata(state, metadata)?;
+    if let Some(algorithm) = state.metadata_hash_algorithm {
+        check_original_hash_len(&metadata, algorithm)?;
     }
 
     // Log verification details
@@ -190,29 +1324,267 @@ pub async fn verify_metadata(
         String::from_utf8_lossy(&metadata.walrus_blob_id)
     );
 
-    // Use the timestamp from metadata (client-provided)
-    let timestamp = metadata.timestamp;
+    record_client_metadata(state, &metadata)?;
+    Ok(metadata)
+}
 
-    info!("Metadata verified successfully, signing...");
+/// Canonicalize client metadata, validate the result and check its format
+/// against the allow-list. Every path that signs or dry-runs client metadata
+/// starts here, so they all accept the same metadata and sign the same bytes
+/// for it.
+pub(crate) fn prepare_client_metadata(
+    state: &AppState,
+    metadata: DatasetVerification,
+) -> Result<DatasetVerification, EnclaveError> {
+    // Equivalent submissions must sign identical bytes; see `canonicalize_metadata`
+    let metadata = text::canonicalize_metadata(metadata)?;
+    validate_metadata(state, &metadata)?;
+    check_format_allowed(state, &String::from_utf8_lossy(&metadata.format))?;
+    Ok(metadata)
+}
 
-    // Sign the metadata and return
-    Ok(Json(to_signed_response(
-        &state.eph_kp,
-        metadata,
-        timestamp,
-        IntentScope::ProcessData,
-    )))
+/// Bind the `seal_policy_id` and record the `original_hash` of metadata
+/// about to be signed, refusing a policy bound to another dataset or a hash
+/// already signed within the dedup window. Every path signing client
+/// metadata calls it, so no endpoint signs what another one would refuse.
+pub(crate) fn record_client_metadata(
+    state: &AppState,
+    metadata: &DatasetVerification,
+) -> Result<(), EnclaveError> {
+    if let Some(policy_bindings) = &state.policy_bindings {
+        policy_bindings.check_and_bind(&metadata.seal_policy_id, &metadata.dataset_id)?;
+    }
+    if let Some(recent_hashes) = &state.recent_hashes {
+        let now_ms = state.clock.now_millis()?;
+        recent_hashes.check_and_record(&metadata.original_hash, now_ms)?;
+    }
+    Ok(())
+}
+
+/// Result of a successful dry-run validation.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ValidateMetadataResponse {
+    pub valid: bool,
+}
+
+/// Run the `verify_metadata` validation without signing, so clients can check
+/// metadata before committing to an on-chain transaction.
+pub async fn validate_metadata_only(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<MetadataVerificationRequest>,
+) -> Result<Json<ValidateMetadataResponse>, EnclaveError> {
+    let result = prepare_client_metadata(&state, request.metadata)
+        .and_then(|metadata| match state.metadata_hash_algorithm {
+            Some(algorithm) => check_original_hash_len(&metadata, algorithm),
+            None => Ok(()),
+        })
+        .map(|()| Json(ValidateMetadataResponse { valid: true }));
+    state.metrics.record("validate_metadata", &result);
+    result
+}
+
+/// Reject an `original_hash` that is not a digest of `algorithm`. The contract
+/// stores the hash as-is, so a truncated one would be signed and accepted.
+pub fn check_original_hash_len(
+    metadata: &DatasetVerification,
+    algorithm: HashAlgorithm,
+) -> Result<(), EnclaveError> {
+    if metadata.original_hash.len() != algorithm.digest_len() {
+        return Err(EnclaveError::InvalidInput(format!(
+            "original_hash has wrong length: expected {} bytes for {}, got {}",
+            algorithm.digest_len(),
+            algorithm.name(),
+            metadata.original_hash.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Sign metadata whose `original_hash` was computed by a trusted upstream
+/// pipeline. Runs the same validation as `verify_metadata` and additionally
+/// requires the hash to have the digest length of the declared algorithm.
+pub async fn sign_digest(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SigningQuery>,
+    Json(request): Json<SignDigestRequest>,
+) -> Result<Json<ProcessedDataResponse<IntentMessage<DatasetVerification>>>, EnclaveError> {
+    let result = sign_precomputed_digest(&state, request)
+        .and_then(|Json(response)| query.apply(&state.signer, response).map(Json));
+    state.metrics.record("sign_digest", &result);
+    audit::record(&state, "sign_digest", &result);
+    result
+}
+
+fn sign_precomputed_digest(
+    state: &AppState,
+    request: SignDigestRequest,
+) -> Result<Json<ProcessedDataResponse<IntentMessage<DatasetVerification>>>, EnclaveError> {
+    let algorithm = HashAlgorithm::parse(request.hash_algorithm.as_deref())?;
+    let metadata = prepare_client_metadata(state, request.metadata)?;
+
+    check_original_hash_len(&metadata, algorithm)?;
+
+    info!(
+        "Signing precomputed digest for dataset_id: {:?}, {}={}",
+        String::from_utf8_lossy(&metadata.dataset_id),
+        algorithm.name(),
+        Hex::encode(&metadata.original_hash)
+    );
+
+    record_client_metadata(state, &metadata)?;
+    let signer = state.signer.current();
+    Ok(Json(sign_client_metadata(state, signer.as_ref(), metadata, IntentScope::VerifyMetadata)))
+}
+
+/// Sign validated client-provided metadata with `signer` under `intent`,
+/// using the metadata's own timestamp. Identical metadata is served from the
+/// signature cache.
+pub(crate) fn sign_client_metadata(
+    state: &AppState,
+    signer: &dyn signer::Signer,
+    metadata: DatasetVerification,
+    intent: IntentScope,
+) -> ProcessedDataResponse<IntentMessage<DatasetVerification>> {
+    state
+        .signature_cache
+        .get_or_sign(&state.metrics, &signer.public_key_bytes(), metadata, intent, |metadata| {
+            let timestamp = metadata.timestamp;
+            to_signed_response(signer, metadata, timestamp, intent)
+        })
+}
+
+/// Re-sign metadata previously signed by this enclave with the current time,
+/// so a transaction that aged out of the contract's freshness window can be
+/// retried. Only the timestamp changes; the intent scope and every other
+/// field are kept.
+pub async fn resign_metadata(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<ProcessedDataResponse<IntentMessage<DatasetVerification>>>,
+) -> Result<Json<ProcessedDataResponse<IntentMessage<DatasetVerification>>>, EnclaveError> {
+    let result = refresh_signed_metadata(&state, request);
+    state.metrics.record("resign_metadata", &result);
+    audit::record(&state, "resign_metadata", &result);
+    result
+}
+
+fn refresh_signed_metadata(
+    state: &AppState,
+    signed: ProcessedDataResponse<IntentMessage<DatasetVerification>>,
+) -> Result<Json<ProcessedDataResponse<IntentMessage<DatasetVerification>>>, EnclaveError> {
+    // Refuse anything this enclave did not sign, so unsigned data cannot be laundered
+    let signature = text::decode_hex("signature", &signed.signature)?;
+    if !state.signer.verify(&common::signing_bytes(&signed.response), &signature) {
+        return Err(EnclaveError::Forbidden(
+            "signature does not verify against the enclave public key".to_string(),
+        ));
+    }
+
+    let IntentMessage { intent, data: mut metadata, .. } = signed.response;
+    let timestamp = state.clock.now_millis()?;
+    metadata.timestamp = timestamp;
+
+    info!(
+        "Re-signing dataset_id: {:?} with timestamp {}",
+        String::from_utf8_lossy(&metadata.dataset_id),
+        timestamp
+    );
+
+    Ok(Json(to_signed_response(state.signer.current().as_ref(), metadata, timestamp, intent)))
+}
+
+/// Validation shared by every endpoint that signs client-provided metadata.
+/// Every failed check is reported at once unless `collect_validation_errors`
+/// is off, in which case the first one is returned.
+pub fn validate_metadata(state: &AppState, metadata: &DatasetVerification) -> Result<(), EnclaveError> {
+    if metadata.version != DATASET_VERIFICATION_VERSION {
+        return Err(EnclaveError::InvalidInput(format!(
+            "unsupported DatasetVerification version {} (expected {})",
+            metadata.version, DATASET_VERIFICATION_VERSION
+        )));
+    }
+
+    // Validate metadata fields
+    let required: [(&str, &[u8]); 6] = [
+        ("dataset_id", &metadata.dataset_id),
+        ("name", &metadata.name),
+        ("original_hash", &metadata.original_hash),
+        ("walrus_blob_id", &metadata.walrus_blob_id),
+        ("seal_policy_id", &metadata.seal_policy_id),
+        ("uploader", &metadata.uploader),
+    ];
+    let mut errors: Vec<EnclaveError> = required
+        .into_iter()
+        .filter(|(_, value)| value.is_empty())
+        .map(|(field, _)| EnclaveError::InvalidInput(format!("{} cannot be empty", field)))
+        .collect();
+
+    errors.extend(state.limits.fields.violations(metadata));
+
+    errors.extend(text::text_field_errors(metadata, state.reject_control_chars));
+
+    // An empty field was reported above; its format is not checked again
+    if state.strict_uploader_address && !metadata.uploader.is_empty() {
+        errors.extend(validate_sui_address(&metadata.uploader).err());
+    }
+
+    if state.strict_walrus_blob_id && !metadata.walrus_blob_id.is_empty() {
+        errors.extend(walrus::validate_blob_id(&metadata.walrus_blob_id).err());
+    }
+
+    if let Some(window_ms) = state.limits.timestamp_freshness_ms {
+        let now_ms = state.clock.now_millis()?;
+        errors.extend(check_timestamp_freshness(metadata.timestamp, now_ms, window_ms).err());
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else if state.collect_validation_errors {
+        Err(EnclaveError::validation_failed(errors))
+    } else {
+        Err(errors.swap_remove(0))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use fastcrypto::encoding::{Encoding, Hex};
+    use fastcrypto::ed25519::Ed25519KeyPair;
+    use fastcrypto::traits::KeyPair;
+    use sha2::{Digest, Sha256};
+
+    fn test_state() -> AppState {
+        AppState::new(
+            Ed25519KeyPair::generate(&mut rand::thread_rng()),
+            "test-key".to_string(),
+        )
+    }
+
+    fn no_query() -> Query<SigningQuery> {
+        Query(SigningQuery::default())
+    }
+
+    fn fresh_metadata() -> DatasetVerification {
+        DatasetVerification {
+            version: DATASET_VERIFICATION_VERSION,
+            dataset_id: b"dataset-1".to_vec(),
+            name: b"data.csv".to_vec(),
+            description: b"Test dataset".to_vec(),
+            format: b"CSV".to_vec(),
+            size: 1024,
+            original_hash: vec![0xAB; 32],
+            walrus_blob_id: b"blob-1".to_vec(),
+            seal_policy_id: b"policy-1".to_vec(),
+            timestamp: current_timestamp_ms().unwrap(),
+            uploader: format!("0x{}", "ab".repeat(SUI_ADDRESS_LENGTH)).into_bytes(),
+        }
+    }
 
     #[tokio::test]
     async fn test_serde() {
         // CRITICAL: Serialization should be consistent with move test see `fun test_bcs_serialization_consistency` in `sealtrust.move`.
         let payload = DatasetVerification {
+            version: DATASET_VERIFICATION_VERSION,
             dataset_id: b"test-123".to_vec(),
             name: b"test.csv".to_vec(),
             description: b"Test dataset".to_vec(),
@@ -264,6 +1636,7 @@ mod tests {
     fn test_dataset_verification_bcs_serialization() {
         // Test that DatasetVerification serializes correctly
         let verification = DatasetVerification {
+            version: DATASET_VERIFICATION_VERSION,
             dataset_id: b"test-456".to_vec(),
             name: b"example.csv".to_vec(),
             description: b"Example dataset".to_vec(),
@@ -294,10 +1667,233 @@ mod tests {
         assert_eq!(verification.uploader, deserialized.uploader);
     }
 
+    #[test]
+    fn test_dataset_verification_v1_bcs_layout() {
+        // Pinned bytes of version 1. If this fails the layout changed: bump
+        // DATASET_VERIFICATION_VERSION and update the Move struct with it.
+        let verification = DatasetVerification {
+            version: 1,
+            dataset_id: b"d1".to_vec(),
+            name: b"n".to_vec(),
+            description: b"".to_vec(),
+            format: b"CSV".to_vec(),
+            size: 1024,
+            original_hash: vec![0xAB, 0xCD],
+            walrus_blob_id: b"b".to_vec(),
+            seal_policy_id: b"p".to_vec(),
+            timestamp: 1700000000000,
+            uploader: b"0xA".to_vec(),
+        };
+        let bytes = bcs::to_bytes(&verification).expect("should serialize");
+        assert_eq!(
+            Hex::encode(&bytes),
+            concat!(
+                "01",                   // version
+                "026431",               // dataset_id "d1"
+                "016e",                 // name "n"
+                "00",                   // description ""
+                "03435356",             // format "CSV"
+                "0004000000000000",     // size 1024, u64 little-endian
+                "02abcd",               // original_hash
+                "0162",                 // walrus_blob_id "b"
+                "0170",                 // seal_policy_id "p"
+                "0068e5cf8b010000",     // timestamp 1700000000000
+                "03307841",             // uploader "0xA"
+            )
+        );
+        assert_eq!(DATASET_VERIFICATION_VERSION, 1);
+
+        // JSON without a version gets the current one
+        let mut json = serde_json::to_value(&verification).unwrap();
+        json.as_object_mut().unwrap().remove("version");
+        let parsed: DatasetVerification = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.version, DATASET_VERIFICATION_VERSION);
+    }
+
+    #[tokio::test]
+    async fn test_verify_metadata_rejects_unknown_version() {
+        let metadata = DatasetVerification { version: 2, ..fresh_metadata() };
+        let request = MetadataVerificationRequest { metadata, intent_scope: None };
+        let err = verify_metadata(State(Arc::new(test_state())), no_query(), Json(request))
+            .await
+            .unwrap_err();
+        assert_eq!(err.message(), "unsupported DatasetVerification version 2 (expected 1)");
+    }
+
+    #[tokio::test]
+    async fn test_verify_metadata_reports_every_validation_error() {
+        use axum::response::IntoResponse;
+
+        let metadata = || DatasetVerification {
+            dataset_id: Vec::new(),
+            name: vec![b'n'; 300],
+            uploader: b"0xA".to_vec(),
+            ..fresh_metadata()
+        };
+        let request = || MetadataVerificationRequest { metadata: metadata(), intent_scope: None };
+        let expected = [
+            "dataset_id cannot be empty",
+            "name exceeds maximum length of 256 bytes (got 300)",
+            "invalid uploader address",
+        ];
+
+        let state = Arc::new(test_state());
+        let err = verify_metadata(State(state), no_query(), Json(request())).await.unwrap_err();
+        let EnclaveError::ValidationFailed { errors, .. } = &err else {
+            panic!("expected ValidationFailed, got {:?}", err);
+        };
+        assert_eq!(errors, &expected);
+        assert_eq!(err.code(), "invalid_input");
+        assert_eq!(err.message(), expected.join("; "));
+
+        let response = err.into_response();
+        assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["validation_errors"], serde_json::json!(expected));
+
+        // The flag restores first-error-wins
+        let mut state = test_state();
+        state.collect_validation_errors = false;
+        let err = verify_metadata(State(Arc::new(state)), no_query(), Json(request()))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, EnclaveError::InvalidInput(_)));
+        assert_eq!(err.message(), "dataset_id cannot be empty");
+    }
+
+    #[tokio::test]
+    async fn test_verify_metadata_checks_allowed_formats() {
+        let mut state = test_state();
+        state.allowed_formats = vec!["CSV".to_string(), "PARQUET".to_string()];
+        let state = Arc::new(state);
+
+        // Case is ignored when comparing
+        let metadata = DatasetVerification { format: b"csv".to_vec(), ..fresh_metadata() };
+        let request = MetadataVerificationRequest { metadata, intent_scope: None };
+        assert!(verify_metadata(State(state.clone()), no_query(), Json(request)).await.is_ok());
+
+        let metadata = DatasetVerification { format: b"JSON".to_vec(), ..fresh_metadata() };
+        let request = MetadataVerificationRequest { metadata, intent_scope: None };
+        let err = verify_metadata(State(state), no_query(), Json(request)).await.unwrap_err();
+        assert!(matches!(err, EnclaveError::Forbidden(_)));
+        assert!(err.message().starts_with("format not permitted"));
+
+        // No allow-list accepts any format
+        assert!(check_format_allowed(&test_state(), "JSON").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_every_path_checks_allowed_formats() {
+        let mut state = test_state();
+        state.allowed_formats = vec!["CSV".to_string()];
+        let state = Arc::new(state);
+        let json = || DatasetVerification { format: b"JSON".to_vec(), ..fresh_metadata() };
+
+        let request = MetadataVerificationRequest { metadata: json(), intent_scope: None };
+        let result = validate_metadata_only(State(state.clone()), Json(request)).await;
+        assert!(matches!(result, Err(EnclaveError::Forbidden(_))));
+
+        let request = SignDigestRequest { metadata: json(), hash_algorithm: None };
+        let result = sign_digest(State(state.clone()), no_query(), Json(request)).await;
+        assert!(matches!(result, Err(EnclaveError::Forbidden(_))));
+
+        let request = batch::BatchVerifyMetadataRequest { items: vec![json()], items_bcs: vec![] };
+        let err = batch::batch_verify_metadata(State(state), Json(request)).await.unwrap_err();
+        assert!(err.message().starts_with("item 0: format not permitted"));
+    }
+
+    #[tokio::test]
+    async fn test_verify_metadata_rejections_wait_for_floor() {
+        const FLOOR: std::time::Duration = std::time::Duration::from_millis(300);
+        let mut state = test_state();
+        state.rejection_floor = Some(FLOOR);
+        let state = Arc::new(state);
+
+        // An early check (empty dataset_id) and a late one (version) both wait
+        for metadata in [
+            DatasetVerification { dataset_id: Vec::new(), ..fresh_metadata() },
+            DatasetVerification { version: 2, ..fresh_metadata() },
+        ] {
+            let request = MetadataVerificationRequest { metadata, intent_scope: None };
+            let started = std::time::Instant::now();
+            let result = verify_metadata(State(state.clone()), no_query(), Json(request)).await;
+            assert!(result.is_err());
+            assert!(started.elapsed() >= FLOOR);
+        }
+
+        // Successes are not delayed
+        let mut state = test_state();
+        state.rejection_floor = Some(std::time::Duration::from_secs(30));
+        let request =
+            MetadataVerificationRequest { metadata: fresh_metadata(), intent_scope: None };
+        let started = std::time::Instant::now();
+        let result = verify_metadata(State(Arc::new(state)), no_query(), Json(request)).await;
+        assert!(result.is_ok());
+        assert!(started.elapsed() < std::time::Duration::from_secs(30));
+    }
+
+    #[tokio::test]
+    async fn test_process_data_rejects_disallowed_format_before_fetch() {
+        let mut state = test_state();
+        state.allowed_formats = vec!["CSV".to_string(), "PARQUET".to_string()];
+        let request = ProcessDataRequest {
+            payload: DatasetRequest {
+                // Nothing listens here; a fetch would fail with fetch_failed
+                dataset_url: "http://127.0.0.1:1/data.json".to_string(),
+                expected_hash: None,
+                format: "json".to_string(),
+                schema_version: "v1.0".to_string(),
+                hash_algorithm: None,
+                strict_format: None,
+                hash_wire_bytes: None,
+                validate_csv_schema: None,
+                byte_range: None,
+                decompress: None,
+                auth: None,
+                populate_metadata: None,
+                no_redirects: None,
+            },
+        };
+        let err = process_data(State(Arc::new(state)), no_query(), Json(request))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, EnclaveError::Forbidden(_)));
+        assert!(err.message().starts_with("format not permitted"));
+    }
+
+    #[tokio::test]
+    async fn test_process_data_rejects_oversized_expected_hash_before_fetch() {
+        let request = ProcessDataRequest {
+            payload: DatasetRequest {
+                // Nothing listens here; a fetch would fail with fetch_failed
+                dataset_url: "http://127.0.0.1:1/data.csv".to_string(),
+                expected_hash: Some("ab".repeat(10_000)),
+                format: "CSV".to_string(),
+                schema_version: "v1.0".to_string(),
+                hash_algorithm: None,
+                strict_format: None,
+                hash_wire_bytes: None,
+                validate_csv_schema: None,
+                byte_range: None,
+                decompress: None,
+                auth: None,
+                populate_metadata: None,
+                no_redirects: None,
+            },
+        };
+        let err = process_data(State(Arc::new(test_state())), no_query(), Json(request))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, EnclaveError::InvalidInput(_)));
+        assert!(err.message().starts_with("expected_hash is too long"));
+    }
+
     #[test]
     fn test_intent_message_structure() {
         // Test IntentMessage wrapper structure
         let payload = DatasetVerification {
+            version: DATASET_VERIFICATION_VERSION,
             dataset_id: b"test-789".to_vec(),
             name: b"data.json".to_vec(),
             description: b"Test JSON dataset".to_vec(),
@@ -356,6 +1952,430 @@ mod tests {
             .expect("Should parse JSON without expected_hash");
 
         assert_eq!(request.expected_hash, None);
+        assert_eq!(request.hash_algorithm, None);
+        assert_eq!(
+            HashAlgorithm::parse(request.hash_algorithm.as_deref()).unwrap(),
+            HashAlgorithm::Sha256
+        );
+    }
+
+    #[test]
+    fn test_dataset_request_hash_algorithm() {
+        let json = r#"{
+            "dataset_url": "https://example.com/dataset.csv",
+            "format": "CSV",
+            "schema_version": "v1.0",
+            "hash_algorithm": "blake2b256"
+        }"#;
+
+        let request: DatasetRequest = serde_json::from_str(json)
+            .expect("Should parse JSON with hash_algorithm");
+        let algorithm = HashAlgorithm::parse(request.hash_algorithm.as_deref()).unwrap();
+
+        assert_eq!(algorithm, HashAlgorithm::Blake2b256);
+        assert_eq!(algorithm.digest(b"data").len(), 32);
+        assert_ne!(algorithm.digest(b"data"), HashAlgorithm::Sha256.digest(b"data"));
+    }
+
+    #[tokio::test]
+    async fn test_check_hash_reports_mismatch_with_computed_hash() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let dataset_url = format!("http://{}/data.csv", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let _ = socket.read(&mut [0u8; 4096]).await;
+            let response = "HTTP/1.1 200 OK\r\nContent-Length: 6\r\nConnection: close\r\n\r\nid,x\n\n";
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+
+        let mut state = test_state();
+        state.url_policy.allowlist = vec!["127.0.0.1".to_string()];
+        let request = CheckHashRequest {
+            dataset_url,
+            expected_hash: "00".repeat(32),
+            hash_algorithm: None,
+        };
+
+        let Json(response) = check_hash(State(Arc::new(state)), Json(request))
+            .await
+            .expect("a mismatch is a result, not an error");
+        assert!(!response.matches);
+        assert_eq!(response.computed_hash, Hex::encode(Sha256::digest(b"id,x\n\n")));
+        assert_eq!(response.size, 6);
+    }
+
+    /// Serve `body` once on a local port and return its URL.
+    async fn serve_dataset_once(body: &'static [u8]) -> String {
+        serve_typed_dataset_once(body, None).await
+    }
+
+    /// Like `serve_dataset_once`, with a `Content-Type` header when given.
+    async fn serve_typed_dataset_once(body: &'static [u8], content_type: Option<&str>) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/data.csv", listener.local_addr().unwrap());
+        let content_type = content_type
+            .map(|content_type| format!("Content-Type: {}\r\n", content_type))
+            .unwrap_or_default();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let _ = socket.read(&mut [0u8; 4096]).await;
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n",
+                body.len(),
+                content_type
+            );
+            socket.write_all(head.as_bytes()).await.unwrap();
+            socket.write_all(body).await.unwrap();
+        });
+        url
+    }
+
+    /// Serve `body` on every connection whose `Authorization` header is
+    /// `authorization`, answering 401 otherwise.
+    async fn serve_with_auth(body: &'static [u8], authorization: &'static str) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/private.csv", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = vec![0u8; 4096];
+                let read = socket.read(&mut request).await.unwrap();
+                let request = String::from_utf8_lossy(&request[..read]).to_string();
+                let authorized = request.lines().any(|line| {
+                    line.split_once(':').is_some_and(|(name, value)| {
+                        name.eq_ignore_ascii_case("authorization") && value.trim() == authorization
+                    })
+                });
+                let (status, body) = if authorized {
+                    ("200 OK", body)
+                } else {
+                    ("401 Unauthorized", &b""[..])
+                };
+                let head = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                );
+                socket.write_all(head.as_bytes()).await.unwrap();
+                socket.write_all(body).await.unwrap();
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_process_data_sends_request_credentials() {
+        const BODY: &[u8] = b"id,label\n1,dog\n";
+        let mut state = test_state();
+        state.url_policy.allowlist = vec!["127.0.0.1".to_string()];
+        let state = Arc::new(state);
+        let request = |dataset_url: &str, auth: Option<fetch::FetchAuth>| ProcessDataRequest {
+            payload: DatasetRequest {
+                dataset_url: dataset_url.to_string(),
+                expected_hash: None,
+                format: "CSV".to_string(),
+                schema_version: "v1.0".to_string(),
+                hash_algorithm: None,
+                strict_format: None,
+                hash_wire_bytes: None,
+                validate_csv_schema: None,
+                byte_range: None,
+                decompress: None,
+                auth,
+                populate_metadata: None,
+                no_redirects: None,
+            },
+        };
+
+        let url = serve_with_auth(BODY, "Bearer s3cret-token").await;
+        let bearer = fetch::FetchAuth::Bearer { token: "s3cret-token".to_string() };
+        let Json(response) =
+            process_data(State(state.clone()), no_query(), Json(request(&url, Some(bearer))))
+                .await
+                .expect("credentials should be sent");
+        assert_eq!(response.response.data.size, BODY.len() as u64);
+
+        // Wrong or missing credentials fail without echoing them
+        let wrong = fetch::FetchAuth::Bearer { token: "other-token".to_string() };
+        assert!(!format!("{:?}", wrong).contains("other-token"));
+        let err = process_data(State(state.clone()), no_query(), Json(request(&url, Some(wrong))))
+            .await
+            .unwrap_err();
+        assert!(err.message().contains("401"));
+        assert!(!err.message().contains("other-token"));
+        let result = process_data(State(state.clone()), no_query(), Json(request(&url, None)));
+        assert!(matches!(result.await, Err(EnclaveError::FetchFailed(_))));
+
+        // "user:pass" in base64
+        let url = serve_with_auth(BODY, "Basic dXNlcjpwYXNz").await;
+        let basic = fetch::FetchAuth::Basic {
+            username: "user".to_string(),
+            password: Some("pass".to_string()),
+        };
+        assert!(process_data(State(state), no_query(), Json(request(&url, Some(basic))))
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_process_data_populates_metadata_on_request() {
+        const BODY: &[u8] = b"id,label\n1,dog\n";
+        let mut state = test_state();
+        state.url_policy.allowlist = vec!["127.0.0.1".to_string()];
+        let state = Arc::new(state);
+        let request = |dataset_url: &str, populate_metadata: Option<bool>| ProcessDataRequest {
+            payload: DatasetRequest {
+                dataset_url: dataset_url.to_string(),
+                expected_hash: None,
+                format: "CSV".to_string(),
+                schema_version: "v1.0".to_string(),
+                hash_algorithm: None,
+                strict_format: None,
+                hash_wire_bytes: None,
+                validate_csv_schema: None,
+                byte_range: None,
+                decompress: None,
+                auth: None,
+                populate_metadata,
+                no_redirects: None,
+            },
+        };
+
+        let url = serve_dataset_once(BODY).await;
+        let Json(response) =
+            process_data(State(state.clone()), no_query(), Json(request(&url, Some(true))))
+                .await
+                .unwrap();
+        let message = &response.response;
+        let data = &message.data;
+        let url_hash = Sha256::digest(url.as_bytes());
+        assert_eq!(data.dataset_id, Hex::encode(url_hash).into_bytes());
+        assert_eq!(data.name, url.as_bytes());
+        assert_eq!(
+            data.description,
+            format!("sha256 of {} bytes, schema_version v1.0", BODY.len()).into_bytes()
+        );
+        assert_eq!(data.format, b"CSV");
+        assert_eq!(data.size, BODY.len() as u64);
+        assert_eq!(data.original_hash, Sha256::digest(BODY).to_vec());
+        assert_eq!(data.timestamp, message.timestamp_ms);
+
+        // Without the flag the legacy placeholders are kept
+        let url = serve_dataset_once(BODY).await;
+        let Json(response) = process_data(State(state), no_query(), Json(request(&url, None)))
+            .await
+            .unwrap();
+        assert_eq!(response.response.data.dataset_id, b"legacy");
+    }
+
+    #[tokio::test]
+    async fn test_process_data_fetches_ipfs_urls_through_gateway() {
+        const BODY: &[u8] = b"id,label\n1,dog\n";
+        const CID: &str = "bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi";
+        let mut state = test_state();
+        state.url_policy.allowlist = vec!["127.0.0.1".to_string()];
+        state.ipfs_gateway = serve_dataset_once(BODY).await.replace("/data.csv", "");
+        let state = Arc::new(state);
+        let request = |dataset_url: String| ProcessDataRequest {
+            payload: DatasetRequest {
+                dataset_url,
+                expected_hash: Some(Hex::encode(Sha256::digest(BODY))),
+                format: "CSV".to_string(),
+                schema_version: "v1.0".to_string(),
+                hash_algorithm: None,
+                strict_format: None,
+                hash_wire_bytes: None,
+                validate_csv_schema: None,
+                byte_range: None,
+                decompress: None,
+                auth: None,
+                populate_metadata: None,
+                no_redirects: None,
+            },
+        };
+
+        let ipfs_url = format!("ipfs://{}", CID);
+        let result = process_data(State(state.clone()), no_query(), Json(request(ipfs_url))).await;
+        let Json(response) = result.expect("ipfs:// dataset should be fetched through the gateway");
+        assert_eq!(response.response.data.name, format!("ipfs://{}", CID).into_bytes());
+        assert_eq!(response.response.data.size, BODY.len() as u64);
+
+        let malformed = request("ipfs://not-a-cid".to_string());
+        let result = process_data(State(state), no_query(), Json(malformed)).await;
+        assert!(matches!(result, Err(EnclaveError::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn test_process_data_rejects_dataset_below_minimum_size() {
+        let mut state = test_state();
+        state.url_policy.allowlist = vec!["127.0.0.1".to_string()];
+        state.limits.min_dataset_bytes = 16;
+        let state = Arc::new(state);
+        let request = |dataset_url: String| ProcessDataRequest {
+            payload: DatasetRequest {
+                dataset_url,
+                expected_hash: None,
+                format: "CSV".to_string(),
+                schema_version: "v1.0".to_string(),
+                hash_algorithm: None,
+                strict_format: None,
+                hash_wire_bytes: None,
+                validate_csv_schema: None,
+                byte_range: None,
+                decompress: None,
+                auth: None,
+                populate_metadata: None,
+                no_redirects: None,
+            },
+        };
+
+        let small = request(serve_dataset_once(b"id,label\n1,dog\n").await);
+        let result = process_data(State(state.clone()), no_query(), Json(small)).await;
+        let err = result.unwrap_err();
+        assert!(matches!(err, EnclaveError::InvalidInput(_)));
+        assert!(err.message().starts_with("dataset below minimum size"));
+
+        let large_enough = request(serve_dataset_once(b"id,label\n1,dog\n2,cat\n").await);
+        assert!(process_data(State(state), no_query(), Json(large_enough)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_process_data_hashes_decompressed_zstd() {
+        let original = b"id,label\n1,dog\n2,cat\n".repeat(64);
+        let compressed: &'static [u8] =
+            Box::leak(zstd::encode_all(&original[..], 0).unwrap().into_boxed_slice());
+        let mut state = test_state();
+        state.url_policy.allowlist = vec!["127.0.0.1".to_string()];
+        let state = Arc::new(state);
+        let request = |dataset_url: String, decompress: Option<&str>| ProcessDataRequest {
+            payload: DatasetRequest {
+                dataset_url,
+                expected_hash: Some(Hex::encode(Sha256::digest(&original))),
+                format: "CSV".to_string(),
+                schema_version: "v1.0".to_string(),
+                hash_algorithm: None,
+                strict_format: Some(true),
+                hash_wire_bytes: None,
+                validate_csv_schema: None,
+                byte_range: None,
+                decompress: decompress.map(str::to_string),
+                auth: None,
+                populate_metadata: None,
+                no_redirects: None,
+            },
+        };
+
+        let zstd = request(serve_dataset_once(compressed).await, Some("zstd"));
+        let Json(response) = process_data(State(state.clone()), no_query(), Json(zstd))
+            .await
+            .expect("decompressed content should match the expected hash");
+        assert_eq!(response.response.data.size, original.len() as u64);
+
+        // Without decompress the zstd frame itself is checked, and is not CSV
+        let raw = request(serve_dataset_once(compressed).await, None);
+        assert!(process_data(State(state), no_query(), Json(raw)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_process_data_infers_empty_format_from_content_type() {
+        const BODY: &[u8] = b"id,label\n1,dog\n";
+        let mut state = test_state();
+        state.url_policy.allowlist = vec!["127.0.0.1".to_string()];
+        let state = Arc::new(state);
+        let request = |dataset_url: String, format: &str| ProcessDataRequest {
+            payload: DatasetRequest {
+                dataset_url,
+                expected_hash: None,
+                format: format.to_string(),
+                schema_version: "v1.0".to_string(),
+                hash_algorithm: None,
+                strict_format: Some(true),
+                hash_wire_bytes: None,
+                validate_csv_schema: None,
+                byte_range: None,
+                decompress: None,
+                auth: None,
+                populate_metadata: None,
+                no_redirects: None,
+            },
+        };
+
+        let url = serve_typed_dataset_once(BODY, Some("text/csv; charset=utf-8")).await;
+        let Json(response) = process_data(State(state.clone()), no_query(), Json(request(url, "")))
+            .await
+            .expect("format should be inferred from Content-Type");
+        assert_eq!(response.response.data.format, b"CSV");
+
+        // An explicit format wins over the header
+        let url = serve_typed_dataset_once(BODY, Some("application/octet-stream")).await;
+        let Json(response) =
+            process_data(State(state.clone()), no_query(), Json(request(url, "CSV")))
+                .await
+                .unwrap();
+        assert_eq!(response.response.data.format, b"CSV");
+
+        // Neither the field nor the header names a format
+        let url = serve_typed_dataset_once(BODY, Some("application/octet-stream")).await;
+        let err = process_data(State(state.clone()), no_query(), Json(request(url, " ")))
+            .await
+            .unwrap_err();
+        assert!(err.message().starts_with("format is empty and Content-Type"));
+        let url = serve_typed_dataset_once(BODY, None).await;
+        assert!(process_data(State(state), no_query(), Json(request(url, ""))).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_verify_dataset_checks_size_and_hash_separately() {
+        const BODY: &[u8] = b"id,label\n1,dog\n";
+        let mut state = test_state();
+        state.url_policy.allowlist = vec!["127.0.0.1".to_string()];
+        let state = Arc::new(state);
+        let request = |url: String, size: u64, original_hash: Vec<u8>| VerifyDatasetRequest {
+            metadata: DatasetVerification { size, original_hash, ..fresh_metadata() },
+            dataset_url: url,
+            hash_algorithm: None,
+            hash_wire_bytes: None,
+        };
+        let true_hash = Sha256::digest(BODY).to_vec();
+
+        let wrong_size = request(serve_dataset_once(BODY).await, 999, true_hash.clone());
+        let result = verify_dataset(State(state.clone()), Json(wrong_size)).await;
+        assert!(matches!(result, Err(EnclaveError::SizeMismatch(_))));
+
+        let wrong_hash = request(serve_dataset_once(BODY).await, 15, vec![0xAB; 32]);
+        let result = verify_dataset(State(state.clone()), Json(wrong_hash)).await;
+        assert!(matches!(result, Err(EnclaveError::HashMismatch(_))));
+
+        let truthful = request(serve_dataset_once(BODY).await, 15, true_hash.clone());
+        let Json(response) = verify_dataset(State(state), Json(truthful))
+            .await
+            .expect("matching size and hash should be signed");
+        assert_eq!(response.response.data.size, 15);
+        assert_eq!(response.response.data.original_hash, true_hash);
+    }
+
+    #[tokio::test]
+    async fn test_multipart_rejects_empty_url_list() {
+        let state = Arc::new(test_state());
+        let request = ProcessDataRequest {
+            payload: MultipartDatasetRequest {
+                dataset_urls: Vec::new(),
+                expected_hash: None,
+                format: "CSV".to_string(),
+                schema_version: "v1.0".to_string(),
+                hash_algorithm: None,
+                hash_wire_bytes: None,
+            },
+        };
+
+        let result = process_multipart_data(State(state), Json(request)).await;
+        assert!(matches!(result, Err(EnclaveError::InvalidInput(_))));
     }
 
     #[test]
@@ -394,6 +2414,7 @@ mod tests {
     fn test_bcs_encoding_consistency() {
         // Test that identical structs produce identical BCS bytes
         let verification1 = DatasetVerification {
+            version: DATASET_VERIFICATION_VERSION,
             dataset_id: b"consistent-test".to_vec(),
             name: b"data.csv".to_vec(),
             description: b"Consistency test".to_vec(),
@@ -407,6 +2428,7 @@ mod tests {
         };
 
         let verification2 = DatasetVerification {
+            version: DATASET_VERIFICATION_VERSION,
             dataset_id: b"consistent-test".to_vec(),
             name: b"data.csv".to_vec(),
             description: b"Consistency test".to_vec(),
@@ -429,6 +2451,7 @@ mod tests {
     fn test_timestamp_handling() {
         // Test that timestamp changes affect serialization
         let base_verification = DatasetVerification {
+            version: DATASET_VERIFICATION_VERSION,
             dataset_id: b"timestamp-test".to_vec(),
             name: b"data.csv".to_vec(),
             description: b"Timestamp test".to_vec(),
@@ -460,6 +2483,15 @@ mod tests {
             expected_hash: Some("abc123".to_string()),
             format: "CSV".to_string(),
             schema_version: "v1.0".to_string(),
+            hash_algorithm: None,
+            strict_format: None,
+            hash_wire_bytes: None,
+            validate_csv_schema: None,
+            byte_range: None,
+            decompress: None,
+            auth: None,
+            populate_metadata: None,
+            no_redirects: None,
         };
 
         let full_request = ProcessDataRequest {
@@ -498,4 +2530,649 @@ mod tests {
         // IntentScope::ProcessData should serialize to 0x00
         assert_eq!(bytes, vec![0x00], "ProcessData should serialize to 0x00");
     }
+
+    #[test]
+    fn test_verify_metadata_intent_scope_serialization() {
+        // Must match the VerifyMetadata intent constant in the Move contract
+        let scope = IntentScope::VerifyMetadata;
+        let bytes = bcs::to_bytes(&scope).expect("Should serialize IntentScope");
+
+        assert_eq!(bytes, vec![0x01], "VerifyMetadata should serialize to 0x01");
+
+        let bytes = bcs::to_bytes(&IntentScope::MerkleRoot).expect("Should serialize IntentScope");
+        assert_eq!(bytes, vec![0x02], "MerkleRoot should serialize to 0x02");
+
+        let bytes = bcs::to_bytes(&IntentScope::Timestamp).expect("Should serialize IntentScope");
+        assert_eq!(bytes, vec![0x04], "Timestamp should serialize to 0x04");
+
+        let bytes = bcs::to_bytes(&IntentScope::Batch).expect("Should serialize IntentScope");
+        assert_eq!(bytes, vec![0x05], "Batch should serialize to 0x05");
+
+        let bytes = bcs::to_bytes(&IntentScope::Provenance).expect("Should serialize IntentScope");
+        assert_eq!(bytes, vec![0x06], "Provenance should serialize to 0x06");
+
+        let bytes =
+            bcs::to_bytes(&IntentScope::AttestedMetadata).expect("Should serialize IntentScope");
+        assert_eq!(bytes, vec![0x07], "AttestedMetadata should serialize to 0x07");
+    }
+
+    #[test]
+    fn test_error_codes_and_status() {
+        use axum::http::StatusCode;
+
+        let cases = [
+            (EnclaveError::InvalidInput("x".into()), "invalid_input", StatusCode::BAD_REQUEST),
+            (EnclaveError::FetchFailed("x".into()), "fetch_failed", StatusCode::BAD_GATEWAY),
+            (EnclaveError::HashMismatch("x".into()), "hash_mismatch", StatusCode::UNPROCESSABLE_ENTITY),
+            (EnclaveError::SizeMismatch("x".into()), "size_mismatch", StatusCode::UNPROCESSABLE_ENTITY),
+            (EnclaveError::Timeout("x".into()), "timeout", StatusCode::GATEWAY_TIMEOUT),
+            (EnclaveError::Unauthorized("x".into()), "unauthorized", StatusCode::UNAUTHORIZED),
+            (EnclaveError::Overloaded("x".into()), "overloaded", StatusCode::SERVICE_UNAVAILABLE),
+            (EnclaveError::Draining("x".into()), "draining", StatusCode::SERVICE_UNAVAILABLE),
+        ];
+        for (error, code, status) in cases {
+            assert_eq!(error.code(), code);
+            assert_eq!(error.status_code(), status);
+            assert_eq!(error.message(), "x");
+        }
+    }
+
+    #[test]
+    fn test_schema_version_supported() {
+        let state = test_state();
+        assert!(check_schema_version(&state, "v1.0").is_ok());
+    }
+
+    #[test]
+    fn test_schema_version_unsupported() {
+        let mut state = test_state();
+        let result = check_schema_version(&state, "v2.0");
+        assert!(matches!(result, Err(EnclaveError::InvalidInput(_))));
+
+        // Rolling forward only needs the new version in the supported set
+        state.supported_schema_versions.push("v2.0".to_string());
+        assert!(check_schema_version(&state, "v2.0").is_ok());
+    }
+
+    #[test]
+    fn test_schema_version_empty() {
+        let state = test_state();
+        let result = check_schema_version(&state, "");
+        assert!(matches!(result, Err(EnclaveError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_error_conversions() {
+        let error: EnclaveError = hex::decode("zz").unwrap_err().into();
+        assert_eq!(error.code(), "invalid_input");
+
+        let error: EnclaveError = serde_json::from_str::<DatasetRequest>("{").unwrap_err().into();
+        assert_eq!(error.code(), "invalid_input");
+
+        let error: EnclaveError = std::time::UNIX_EPOCH
+            .duration_since(std::time::SystemTime::now())
+            .unwrap_err()
+            .into();
+        assert_eq!(error.code(), "clock_error");
+        assert_eq!(error.status_code(), axum::http::StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn test_validate_sui_address_valid() {
+        let address = format!("0x{}", "ab".repeat(SUI_ADDRESS_LENGTH));
+        assert!(validate_sui_address(address.as_bytes()).is_ok());
+    }
+
+    #[test]
+    fn test_validate_sui_address_short() {
+        // Placeholder addresses used by fixtures are rejected in strict mode
+        assert!(validate_sui_address(b"0xA").is_err());
+        assert!(validate_sui_address(b"0xDEADBEEF").is_err());
+    }
+
+    #[test]
+    fn test_validate_sui_address_non_hex() {
+        let not_hex = format!("0x{}", "zz".repeat(SUI_ADDRESS_LENGTH));
+        assert!(validate_sui_address(not_hex.as_bytes()).is_err());
+        // Missing 0x prefix
+        assert!(validate_sui_address("ab".repeat(SUI_ADDRESS_LENGTH).as_bytes()).is_err());
+        // Not UTF-8 at all
+        assert!(validate_sui_address(&[0xff, 0xfe, 0x00]).is_err());
+    }
+
+    #[test]
+    fn test_strict_walrus_blob_id_is_opt_in() {
+        let mut state = test_state();
+        // fresh_metadata uses a placeholder blob ID, accepted in lenient mode
+        assert!(validate_metadata(&state, &fresh_metadata()).is_ok());
+
+        state.strict_walrus_blob_id = true;
+        let err = validate_metadata(&state, &fresh_metadata()).unwrap_err();
+        assert!(err.message().contains("walrus_blob_id"));
+
+        let mut metadata = fresh_metadata();
+        metadata.walrus_blob_id = "A".repeat(43).into_bytes();
+        assert!(validate_metadata(&state, &metadata).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_validate_metadata_only_does_not_sign() {
+        let state = Arc::new(test_state());
+        let request = MetadataVerificationRequest { metadata: fresh_metadata(), intent_scope: None };
+        let Json(response) = validate_metadata_only(State(state.clone()), Json(request))
+            .await
+            .expect("fresh metadata should be valid");
+        assert!(response.valid);
+
+        let mut metadata = fresh_metadata();
+        metadata.uploader = b"0xA".to_vec();
+        let request = MetadataVerificationRequest { metadata, intent_scope: None };
+        let err = validate_metadata_only(State(state), Json(request)).await.unwrap_err();
+        assert_eq!(err.message(), "invalid uploader address");
+    }
+
+    #[tokio::test]
+    async fn test_verify_metadata_rejects_already_verified_hash() {
+        let mut state = test_state();
+        state.recent_hashes = Some(dedup::RecentHashes::default());
+        let state = Arc::new(state);
+
+        let request = MetadataVerificationRequest { metadata: fresh_metadata(), intent_scope: None };
+        let result = verify_metadata(State(state.clone()), no_query(), Json(request)).await;
+        assert!(result.is_ok());
+
+        let mut metadata = fresh_metadata();
+        metadata.dataset_id = b"dataset-2".to_vec();
+        let request = MetadataVerificationRequest { metadata, intent_scope: None };
+        let result = verify_metadata(State(state), no_query(), Json(request)).await;
+        assert!(matches!(result, Err(EnclaveError::Conflict(_))));
+    }
+
+    #[tokio::test]
+    async fn test_hash_verified_by_one_endpoint_is_refused_by_another() {
+        let mut state = test_state();
+        state.recent_hashes = Some(dedup::RecentHashes::default());
+        let state = Arc::new(state);
+
+        let request = MetadataVerificationRequest { metadata: fresh_metadata(), intent_scope: None };
+        verify_metadata(State(state.clone()), no_query(), Json(request)).await.unwrap();
+
+        let request = SignDigestRequest { metadata: fresh_metadata(), hash_algorithm: None };
+        let result = sign_digest(State(state.clone()), no_query(), Json(request)).await;
+        assert!(matches!(result, Err(EnclaveError::Conflict(_))));
+
+        let request = batch::BatchVerifyMetadataRequest {
+            items: vec![fresh_metadata()],
+            items_bcs: vec![],
+        };
+        let result = batch::batch_verify_metadata(State(state), Json(request)).await;
+        assert!(matches!(result, Err(EnclaveError::Conflict(_))));
+    }
+
+    #[tokio::test]
+    async fn test_every_path_canonicalizes_metadata() {
+        let state = Arc::new(test_state());
+
+        // Refused by the dry run exactly as by /verify_metadata
+        let padded = DatasetVerification { dataset_id: b" dataset-1".to_vec(), ..fresh_metadata() };
+        let request = MetadataVerificationRequest { metadata: padded, intent_scope: None };
+        let err = validate_metadata_only(State(state.clone()), Json(request)).await.unwrap_err();
+        assert_eq!(err.message(), "dataset_id has leading or trailing whitespace");
+
+        let spaced = DatasetVerification { name: b" data.csv ".to_vec(), ..fresh_metadata() };
+        let request = SignDigestRequest { metadata: spaced.clone(), hash_algorithm: None };
+        let Json(digest) = sign_digest(State(state.clone()), no_query(), Json(request))
+            .await
+            .unwrap();
+        let request = MetadataVerificationRequest { metadata: spaced, intent_scope: None };
+        let Json(verified) = verify_metadata(State(state), no_query(), Json(request))
+            .await
+            .unwrap();
+        assert_eq!(digest.response.data.name, b"data.csv");
+        assert_eq!(digest.signature, verified.signature);
+    }
+
+    #[tokio::test]
+    async fn test_policy_bound_by_one_endpoint_is_refused_by_another() {
+        let mut state = test_state();
+        state.policy_bindings = Some(dedup::PolicyBindings::default());
+        let state = Arc::new(state);
+
+        let request = MetadataVerificationRequest { metadata: fresh_metadata(), intent_scope: None };
+        verify_metadata(State(state.clone()), no_query(), Json(request)).await.unwrap();
+
+        let metadata = DatasetVerification {
+            dataset_id: b"dataset-2".to_vec(),
+            original_hash: vec![0xCD; 32],
+            ..fresh_metadata()
+        };
+        let request = SignDigestRequest { metadata, hash_algorithm: None };
+        let result = sign_digest(State(state), no_query(), Json(request)).await;
+        assert!(matches!(result, Err(EnclaveError::Conflict(_))));
+    }
+
+    #[tokio::test]
+    async fn test_signing_is_recorded_in_audit_log() {
+        let state = Arc::new(test_state());
+        let metadata = fresh_metadata();
+        let request = MetadataVerificationRequest { metadata: metadata.clone(), intent_scope: None };
+        verify_metadata(State(state.clone()), no_query(), Json(request)).await.unwrap();
+
+        let Json(audit) = audit::audit(
+            State(state.clone()),
+            Query(audit::AuditQuery { dataset_id: "dataset-1".to_string() }),
+        )
+        .await;
+        assert_eq!(audit.records.len(), 1);
+        let record = &audit.records[0];
+        assert_eq!(record.endpoint, "verify_metadata");
+        assert_eq!(record.original_hash, hex::encode(&metadata.original_hash));
+        assert_eq!(record.timestamp_ms, metadata.timestamp);
+        assert_eq!(record.intent, IntentScope::VerifyMetadata);
+
+        // Failed requests leave no record
+        let mut invalid = fresh_metadata();
+        invalid.name.clear();
+        let request = MetadataVerificationRequest { metadata: invalid, intent_scope: None };
+        assert!(verify_metadata(State(state.clone()), no_query(), Json(request)).await.is_err());
+        assert_eq!(state.audit_log.find("dataset-1").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_resign_metadata_refreshes_timestamp() {
+        let state = Arc::new(test_state());
+        let mut metadata = fresh_metadata();
+        metadata.timestamp = 1700000000000;
+        let original = to_signed_response(
+            state.signer.current().as_ref(),
+            metadata.clone(),
+            metadata.timestamp,
+            IntentScope::VerifyMetadata,
+        );
+
+        let Json(resigned) = resign_metadata(State(state.clone()), Json(original))
+            .await
+            .expect("own signature should be re-signed");
+        assert!(resigned.response.timestamp_ms > 1700000000000);
+        assert_eq!(resigned.response.data.timestamp, resigned.response.timestamp_ms);
+        assert_eq!(resigned.response.intent, IntentScope::VerifyMetadata);
+        assert_eq!(resigned.response.data.original_hash, metadata.original_hash);
+        let signature = hex::decode(&resigned.signature).unwrap();
+        assert!(state
+            .signer
+            .verify(&common::signing_bytes(&resigned.response), &signature));
+    }
+
+    #[tokio::test]
+    async fn test_resign_metadata_rejects_foreign_signature() {
+        let state = Arc::new(test_state());
+        let other_kp = Ed25519KeyPair::generate(&mut rand::thread_rng());
+        let metadata = fresh_metadata();
+        let forged = to_signed_response(&other_kp, metadata.clone(), metadata.timestamp, IntentScope::VerifyMetadata);
+
+        let result = resign_metadata(State(state), Json(forged)).await;
+        assert!(matches!(result, Err(EnclaveError::Forbidden(_))));
+    }
+
+    #[tokio::test]
+    async fn test_verify_metadata_intent_scope_selection() {
+        use common::IntentScopeSelector;
+        let state = Arc::new(test_state());
+
+        let request = MetadataVerificationRequest {
+            metadata: fresh_metadata(),
+            intent_scope: Some(IntentScopeSelector::Name("process_data".to_string())),
+        };
+        let Json(response) = verify_metadata(State(state.clone()), no_query(), Json(request))
+            .await
+            .unwrap();
+        assert_eq!(response.response.intent, IntentScope::ProcessData);
+
+        let request = MetadataVerificationRequest { metadata: fresh_metadata(), intent_scope: None };
+        let Json(response) = verify_metadata(State(state.clone()), no_query(), Json(request))
+            .await
+            .unwrap();
+        assert_eq!(response.response.intent, IntentScope::VerifyMetadata);
+
+        // MerkleRoot signs commitments, never dataset metadata
+        for selector in [IntentScopeSelector::Value(2), IntentScopeSelector::Value(255)] {
+            let request = MetadataVerificationRequest {
+                metadata: fresh_metadata(),
+                intent_scope: Some(selector),
+            };
+            let result = verify_metadata(State(state.clone()), no_query(), Json(request));
+            assert!(matches!(result.await, Err(EnclaveError::InvalidInput(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_signed_bytes_are_included_on_request() {
+        let state = Arc::new(test_state());
+
+        let request = MetadataVerificationRequest { metadata: fresh_metadata(), intent_scope: None };
+        let Json(lean) = verify_metadata(State(state.clone()), no_query(), Json(request))
+            .await
+            .unwrap();
+        assert!(lean.signed_bytes.is_none());
+        assert!(!serde_json::to_string(&lean).unwrap().contains("signed_bytes"));
+
+        let query = Query(SigningQuery { include_signed_bytes: true, ..Default::default() });
+        let request = MetadataVerificationRequest { metadata: fresh_metadata(), intent_scope: None };
+        let Json(response) = verify_metadata(State(state.clone()), query, Json(request))
+            .await
+            .unwrap();
+        let signed_bytes = Hex::decode(response.signed_bytes.as_deref().unwrap()).unwrap();
+        assert_eq!(signed_bytes, common::signing_bytes(&response.response));
+        let signature = Hex::decode(&response.signature).unwrap();
+        assert!(state.signer.verify(&signed_bytes, &signature));
+    }
+
+    #[tokio::test]
+    async fn test_sign_digest_accepts_expected_length() {
+        let state = Arc::new(test_state());
+        let request = SignDigestRequest {
+            metadata: fresh_metadata(),
+            hash_algorithm: None,
+        };
+
+        let Json(response) = sign_digest(State(state), no_query(), Json(request))
+            .await
+            .expect("32-byte sha256 digest should be signed");
+        assert_eq!(response.response.data.original_hash, vec![0xAB; 32]);
+    }
+
+    #[tokio::test]
+    async fn test_verify_metadata_rejects_wrong_hash_length() {
+        let request = |original_hash: Vec<u8>| MetadataVerificationRequest {
+            metadata: DatasetVerification { original_hash, ..fresh_metadata() },
+            intent_scope: None,
+        };
+        let short_hash = || request(vec![0xAA, 0xBB, 0xCC, 0xDD]);
+
+        let strict = Arc::new(test_state());
+        let result = verify_metadata(State(strict.clone()), no_query(), Json(short_hash())).await;
+        let err = result.unwrap_err();
+        assert!(matches!(err, EnclaveError::InvalidInput(_)));
+        assert_eq!(
+            err.message(),
+            "original_hash has wrong length: expected 32 bytes for sha256, got 4"
+        );
+        let result = validate_metadata_only(State(strict), Json(short_hash())).await;
+        assert!(result.is_err());
+
+        // A 64-byte digest passes once sha512 is configured
+        let mut sha512 = test_state();
+        sha512.metadata_hash_algorithm = Some(HashAlgorithm::Sha512);
+        let sha512 = Arc::new(sha512);
+        let sha512_hash = request(vec![0xAB; 64]);
+        let result = verify_metadata(State(sha512), no_query(), Json(sha512_hash)).await;
+        assert!(result.is_ok());
+
+        // Any length is accepted with the check disabled
+        let mut unchecked = test_state();
+        unchecked.metadata_hash_algorithm = None;
+        let unchecked = Arc::new(unchecked);
+        let result = verify_metadata(State(unchecked), no_query(), Json(short_hash())).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_sign_digest_rejects_wrong_length() {
+        let state = Arc::new(test_state());
+
+        let mut metadata = fresh_metadata();
+        metadata.original_hash = vec![0xAA, 0xBB, 0xCC, 0xDD];
+        let request = SignDigestRequest { metadata, hash_algorithm: None };
+        let result = sign_digest(State(state.clone()), no_query(), Json(request)).await;
+        assert!(matches!(result, Err(EnclaveError::InvalidInput(_))));
+
+        // A 32-byte digest is too short for sha512
+        let request = SignDigestRequest {
+            metadata: fresh_metadata(),
+            hash_algorithm: Some("sha512".to_string()),
+        };
+        let result = sign_digest(State(state), no_query(), Json(request)).await;
+        assert!(matches!(result, Err(EnclaveError::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn test_verify_and_fetch_walrus_rejects_hash_mismatch() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let aggregator_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let _ = socket.read(&mut [0u8; 4096]).await;
+            let response = "HTTP/1.1 200 OK\r\nContent-Length: 4\r\nConnection: close\r\n\r\nblob";
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+
+        let mut state = test_state();
+        state.url_policy.allowlist = vec!["127.0.0.1".to_string()];
+        let request = VerifyAndFetchWalrusRequest {
+            metadata: fresh_metadata(),
+            aggregator_url,
+            hash_algorithm: None,
+        };
+
+        let err = verify_and_fetch_walrus(State(Arc::new(state)), Json(request))
+            .await
+            .err()
+            .expect("mismatching blob must not be signed");
+        assert!(matches!(err, EnclaveError::HashMismatch(_)));
+        assert!(err.message().contains(&Hex::encode(Sha256::digest(b"blob"))));
+        assert!(err.message().contains(&Hex::encode([0xAB; 32])));
+    }
+
+    #[test]
+    fn test_field_length_limits_at_and_over_boundary() {
+        let mut state = test_state();
+        state.strict_uploader_address = false;
+        let limits = limits::FieldLimits::default();
+
+        type Setter = fn(&mut DatasetVerification, Vec<u8>);
+        let cases: [(&str, usize, Setter); 8] = [
+            ("dataset_id", limits.max_dataset_id_len, |m, v| m.dataset_id = v),
+            ("name", limits.max_name_len, |m, v| m.name = v),
+            ("description", limits.max_description_len, |m, v| m.description = v),
+            ("format", limits.max_format_len, |m, v| m.format = v),
+            ("original_hash", limits.max_original_hash_len, |m, v| m.original_hash = v),
+            ("walrus_blob_id", limits.max_walrus_blob_id_len, |m, v| m.walrus_blob_id = v),
+            ("seal_policy_id", limits.max_seal_policy_id_len, |m, v| m.seal_policy_id = v),
+            ("uploader", limits.max_uploader_len, |m, v| m.uploader = v),
+        ];
+
+        for (field, max, set) in cases {
+            let mut metadata = fresh_metadata();
+            set(&mut metadata, vec![b'a'; max]);
+            assert!(validate_metadata(&state, &metadata).is_ok(), "{} at limit", field);
+
+            set(&mut metadata, vec![b'a'; max + 1]);
+            let err = validate_metadata(&state, &metadata).unwrap_err();
+            assert!(
+                err.message().starts_with(&format!("{} exceeds maximum length of {} bytes", field, max)),
+                "unexpected error for {}: {}",
+                field,
+                err
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handlers_honor_custom_limits() {
+        let mut state = test_state();
+        state.url_policy.allowlist = vec!["127.0.0.1".to_string()];
+        state.limits = limits::Limits {
+            max_dataset_bytes: 8,
+            fields: limits::FieldLimits { max_name_len: 4, ..Default::default() },
+            timestamp_freshness_ms: None,
+            ..Default::default()
+        };
+        let state = Arc::new(state);
+
+        // Field length and freshness come from state.limits
+        let mut metadata = fresh_metadata();
+        metadata.timestamp = 1;
+        metadata.name = b"abcd".to_vec();
+        let request = MetadataVerificationRequest { metadata: metadata.clone(), intent_scope: None };
+        assert!(verify_metadata(State(state.clone()), no_query(), Json(request)).await.is_ok());
+        metadata.name = b"abcde".to_vec();
+        let request = MetadataVerificationRequest { metadata, intent_scope: None };
+        let result = verify_metadata(State(state.clone()), no_query(), Json(request)).await;
+        assert_eq!(result.unwrap_err().code(), "invalid_input");
+
+        // So does the dataset size limit
+        let request = ProcessDataRequest {
+            payload: DatasetRequest {
+                dataset_url: serve_dataset_once(b"id,label\n1,dog\n").await,
+                expected_hash: None,
+                format: "CSV".to_string(),
+                schema_version: "v1.0".to_string(),
+                hash_algorithm: None,
+                strict_format: None,
+                hash_wire_bytes: None,
+                validate_csv_schema: None,
+                byte_range: None,
+                decompress: None,
+                auth: None,
+                populate_metadata: None,
+                no_redirects: None,
+            },
+        };
+        let result = process_data(State(state), no_query(), Json(request)).await;
+        assert!(matches!(result, Err(EnclaveError::PayloadTooLarge(_))));
+    }
+
+    #[tokio::test]
+    async fn test_health_check_reports_healthy() {
+        let state = Arc::new(test_state());
+
+        let (status, Json(health)) = health_check(State(state))
+            .await
+            .expect("health check should succeed");
+
+        assert_eq!(status, axum::http::StatusCode::OK);
+        assert_eq!(health.status, "healthy");
+        assert!(health.keypair_loaded);
+        assert_eq!(health.network_ok, None, "no canary configured");
+    }
+
+    #[tokio::test]
+    async fn test_draining_is_reported_by_health_check() {
+        use axum::http::StatusCode;
+
+        let state = Arc::new(test_state());
+        assert!(state.check_accepting().is_ok());
+        state.begin_drain();
+
+        let err = state.check_accepting().unwrap_err();
+        assert_eq!(err.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(err.code(), "draining");
+
+        let (status, Json(health)) = health_check(State(state)).await.unwrap();
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(health.status, "draining");
+    }
+
+    #[tokio::test]
+    async fn test_oversized_body_is_rejected() {
+        use axum::body::Body;
+        use axum::extract::DefaultBodyLimit;
+        use axum::http::{header::CONTENT_TYPE, Request, StatusCode};
+        use axum::routing::post;
+        use axum::{middleware, Router};
+        use tower::ServiceExt;
+
+        let mut state = test_state();
+        state.limits.max_body_bytes = 1024;
+        let app = Router::new()
+            .route("/verify_metadata", post(verify_metadata))
+            .layer(DefaultBodyLimit::max(state.limits.max_body_bytes))
+            .layer(middleware::map_response(map_payload_too_large))
+            .with_state(Arc::new(state));
+
+        let body = format!(r#"{{"metadata": {{"description": "{}"}}}}"#, "a".repeat(4096));
+        let request = Request::post("/verify_metadata")
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(json["code"], "payload_too_large");
+    }
+
+    #[test]
+    fn test_timestamp_freshness_window() {
+        let now = 1_700_000_000_000;
+        let window = DEFAULT_TIMESTAMP_FRESHNESS_MS;
+
+        assert!(check_timestamp_freshness(now, now, window).is_ok());
+        assert!(check_timestamp_freshness(now - window, now, window).is_ok());
+        assert!(check_timestamp_freshness(now + window, now, window).is_ok());
+
+        // Replayed (stale) and future-dated timestamps are rejected
+        assert!(check_timestamp_freshness(now - window - 1, now, window).is_err());
+        assert!(check_timestamp_freshness(now + window + 1, now, window).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stale_timestamp_is_rejected_by_manual_clock() {
+        const NOW: u64 = 1_700_000_000_000;
+        let clock = Arc::new(clock::ManualClock::new(NOW));
+        let mut state = test_state();
+        state.clock = Box::new(clock.clone());
+        let state = Arc::new(state);
+        let window = state.limits.timestamp_freshness_ms.unwrap();
+        let request = || MetadataVerificationRequest {
+            metadata: DatasetVerification { timestamp: NOW, ..fresh_metadata() },
+            intent_scope: None,
+        };
+
+        let Json(response) =
+            verify_metadata(State(state.clone()), no_query(), Json(request())).await.unwrap();
+        assert_eq!(response.response.timestamp_ms, NOW);
+
+        // Still fresh at the edge of the window, stale one millisecond later
+        clock.advance(window);
+        assert!(verify_metadata(State(state.clone()), no_query(), Json(request())).await.is_ok());
+        clock.advance(1);
+        let err = verify_metadata(State(state), no_query(), Json(request())).await.unwrap_err();
+        assert_eq!(
+            err.message(),
+            format!(
+                "timestamp {} is stale: more than {} ms behind enclave time {}",
+                NOW,
+                window,
+                NOW + window + 1
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dedup_window_follows_manual_clock() {
+        const NOW: u64 = 1_700_000_000_000;
+        let clock = Arc::new(clock::ManualClock::new(NOW));
+        let mut state = test_state();
+        state.clock = Box::new(clock.clone());
+        state.limits.timestamp_freshness_ms = None;
+        state.recent_hashes = Some(dedup::RecentHashes::default());
+        let state = Arc::new(state);
+        let request = |dataset_id: &[u8]| MetadataVerificationRequest {
+            metadata: DatasetVerification { dataset_id: dataset_id.to_vec(), ..fresh_metadata() },
+            intent_scope: None,
+        };
+
+        let verify = |id: &'static [u8]| {
+            verify_metadata(State(state.clone()), no_query(), Json(request(id)))
+        };
+        assert!(verify(b"dataset-1").await.is_ok());
+        clock.advance(dedup::DEFAULT_DEDUP_WINDOW_MS - 1);
+        assert!(matches!(verify(b"dataset-2").await, Err(EnclaveError::Conflict(_))));
+        clock.advance(1);
+        assert!(verify(b"dataset-3").await.is_ok());
+    }
 }
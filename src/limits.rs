@@ -0,0 +1,283 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    DatasetVerification, EnclaveError, DEFAULT_MAX_BATCH_BODY_BYTES,
+    DEFAULT_MAX_BATCH_FETCH_CONCURRENCY, DEFAULT_MAX_BATCH_ITEMS, DEFAULT_MAX_BATCH_TOTAL_BYTES,
+    DEFAULT_MAX_BCS_ITEM_BYTES, DEFAULT_MAX_BODY_BYTES, DEFAULT_MAX_DATASET_BYTES,
+    DEFAULT_MAX_EXPECTED_HASH_HEX_LEN, DEFAULT_MIN_DATASET_BYTES, DEFAULT_TIMESTAMP_FRESHNESS_MS,
+};
+use anyhow::anyhow;
+use std::time::Duration;
+
+/// Every tunable bound the enclave enforces, kept in one place so its policy
+/// can be audited at a glance. Built once from configuration at startup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Limits {
+    /// Limit on the size of a fetched dataset, all parts combined, in bytes
+    pub max_dataset_bytes: u64,
+    /// Smallest fetched dataset that is signed, in bytes
+    pub min_dataset_bytes: u64,
+    /// Maximum length of each signed metadata field
+    pub fields: FieldLimits,
+    /// Maximum number of items accepted in a batch request
+    pub max_batch_items: usize,
+    /// Batch items fetched at once; each fetch still takes a global fetch slot
+    pub max_batch_fetch_concurrency: usize,
+    /// Budget for the declared sizes of all items in one batch fetch, in bytes
+    pub max_batch_total_bytes: u64,
+    /// Maximum size of one BCS-encoded item decoded from a request, in bytes
+    pub max_bcs_item_bytes: usize,
+    /// Request body limit for single-dataset endpoints, in bytes
+    pub max_body_bytes: usize,
+    /// Request body limit for batch endpoints, in bytes
+    pub max_batch_body_bytes: usize,
+    /// Maximum hex digits in a client's `expected_hash`, checked before it
+    /// is decoded or anything is fetched
+    pub max_expected_hash_hex_len: usize,
+    /// Maximum allowed distance between a metadata timestamp and the enclave
+    /// clock, in milliseconds. `None` disables the freshness check.
+    pub timestamp_freshness_ms: Option<u64>,
+    /// Limit on a whole dataset fetch, body included. `None` only bounds
+    /// establishing the connection.
+    pub fetch_timeout: Option<Duration>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_dataset_bytes: DEFAULT_MAX_DATASET_BYTES,
+            min_dataset_bytes: DEFAULT_MIN_DATASET_BYTES,
+            fields: FieldLimits::default(),
+            max_batch_items: DEFAULT_MAX_BATCH_ITEMS,
+            max_batch_fetch_concurrency: DEFAULT_MAX_BATCH_FETCH_CONCURRENCY,
+            max_batch_total_bytes: DEFAULT_MAX_BATCH_TOTAL_BYTES,
+            max_bcs_item_bytes: DEFAULT_MAX_BCS_ITEM_BYTES,
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            max_batch_body_bytes: DEFAULT_MAX_BATCH_BODY_BYTES,
+            max_expected_hash_hex_len: DEFAULT_MAX_EXPECTED_HASH_HEX_LEN,
+            timestamp_freshness_ms: Some(DEFAULT_TIMESTAMP_FRESHNESS_MS),
+            fetch_timeout: None,
+        }
+    }
+}
+
+impl Limits {
+    /// Limits from the process environment; see [`Limits::from_vars`].
+    pub fn from_env() -> anyhow::Result<Self> {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    /// Defaults overridden by `MAX_DATASET_BYTES`, `MIN_DATASET_BYTES`, `MAX_BATCH_ITEMS`,
+    /// `MAX_BATCH_FETCH_CONCURRENCY` (at least 1), `MAX_BCS_ITEM_BYTES`, `MAX_BODY_BYTES`, `MAX_BATCH_BODY_BYTES`,
+    /// `MAX_BATCH_TOTAL_BYTES`, `MAX_EXPECTED_HASH_HEX_LEN`, `TIMESTAMP_FRESHNESS_MS`
+    /// (0 disables the check) and `FETCH_TIMEOUT_SECS` (0 for no limit).
+    pub fn from_vars(var: impl Fn(&str) -> Option<String>) -> anyhow::Result<Self> {
+        fn parse<T: std::str::FromStr>(name: &str, value: String) -> anyhow::Result<T>
+        where
+            T::Err: std::fmt::Display,
+        {
+            value
+                .trim()
+                .parse()
+                .map_err(|e| anyhow!("invalid {} {:?}: {}", name, value, e))
+        }
+
+        let mut limits = Self::default();
+        if let Some(value) = var("MAX_DATASET_BYTES") {
+            limits.max_dataset_bytes = parse("MAX_DATASET_BYTES", value)?;
+        }
+        if let Some(value) = var("MIN_DATASET_BYTES") {
+            limits.min_dataset_bytes = parse("MIN_DATASET_BYTES", value)?;
+        }
+        if let Some(value) = var("MAX_BATCH_ITEMS") {
+            limits.max_batch_items = parse("MAX_BATCH_ITEMS", value)?;
+        }
+        if let Some(value) = var("MAX_BATCH_FETCH_CONCURRENCY") {
+            limits.max_batch_fetch_concurrency = parse("MAX_BATCH_FETCH_CONCURRENCY", value)?;
+            if limits.max_batch_fetch_concurrency == 0 {
+                return Err(anyhow!("MAX_BATCH_FETCH_CONCURRENCY must be at least 1"));
+            }
+        }
+        if let Some(value) = var("MAX_BATCH_TOTAL_BYTES") {
+            limits.max_batch_total_bytes = parse("MAX_BATCH_TOTAL_BYTES", value)?;
+        }
+        if let Some(value) = var("MAX_BCS_ITEM_BYTES") {
+            limits.max_bcs_item_bytes = parse("MAX_BCS_ITEM_BYTES", value)?;
+        }
+        if let Some(value) = var("MAX_BODY_BYTES") {
+            limits.max_body_bytes = parse("MAX_BODY_BYTES", value)?;
+        }
+        if let Some(value) = var("MAX_BATCH_BODY_BYTES") {
+            limits.max_batch_body_bytes = parse("MAX_BATCH_BODY_BYTES", value)?;
+        }
+        if let Some(value) = var("MAX_EXPECTED_HASH_HEX_LEN") {
+            limits.max_expected_hash_hex_len = parse("MAX_EXPECTED_HASH_HEX_LEN", value)?;
+        }
+        if let Some(value) = var("TIMESTAMP_FRESHNESS_MS") {
+            let window_ms: u64 = parse("TIMESTAMP_FRESHNESS_MS", value)?;
+            limits.timestamp_freshness_ms = (window_ms > 0).then_some(window_ms);
+        }
+        if let Some(value) = var("FETCH_TIMEOUT_SECS") {
+            let secs: u64 = parse("FETCH_TIMEOUT_SECS", value)?;
+            limits.fetch_timeout = (secs > 0).then(|| Duration::from_secs(secs));
+        }
+        Ok(limits)
+    }
+
+    /// Reject a fetched dataset of `size` bytes below `min_dataset_bytes`.
+    pub fn check_min_dataset_size(&self, size: u64) -> Result<(), EnclaveError> {
+        if size < self.min_dataset_bytes {
+            return Err(EnclaveError::InvalidInput(format!(
+                "dataset below minimum size: {} bytes, minimum is {}",
+                size, self.min_dataset_bytes
+            )));
+        }
+        Ok(())
+    }
+
+    /// Reject a batch whose declared item sizes add up to more than
+    /// `max_batch_total_bytes`, before any of it is fetched.
+    pub fn check_batch_total_bytes(
+        &self,
+        sizes: impl IntoIterator<Item = u64>,
+    ) -> Result<(), EnclaveError> {
+        let total = sizes.into_iter().fold(0u64, u64::saturating_add);
+        if total > self.max_batch_total_bytes {
+            return Err(EnclaveError::PayloadTooLarge(format!(
+                "batch declares {} bytes in total, budget is {} bytes",
+                total, self.max_batch_total_bytes
+            )));
+        }
+        Ok(())
+    }
+
+    /// Reject an `expected_hash` with more hex digits than
+    /// `max_expected_hash_hex_len`, without echoing it, before it is decoded.
+    pub fn check_expected_hash_len(&self, expected_hash: &str) -> Result<(), EnclaveError> {
+        let trimmed = expected_hash.trim();
+        let digits = trimmed
+            .strip_prefix("0x")
+            .or_else(|| trimmed.strip_prefix("0X"))
+            .unwrap_or(trimmed);
+        if digits.len() > self.max_expected_hash_hex_len {
+            return Err(EnclaveError::InvalidInput(format!(
+                "expected_hash is too long: {} characters, maximum is {} hex digits",
+                digits.len(),
+                self.max_expected_hash_hex_len
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Maximum byte length of each `DatasetVerification` field. Everything signed
+/// ends up stored on-chain, so unbounded fields waste gas and risk Move aborts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldLimits {
+    pub max_dataset_id_len: usize,
+    pub max_name_len: usize,
+    pub max_description_len: usize,
+    pub max_format_len: usize,
+    pub max_original_hash_len: usize,
+    pub max_walrus_blob_id_len: usize,
+    pub max_seal_policy_id_len: usize,
+    pub max_uploader_len: usize,
+}
+
+impl Default for FieldLimits {
+    fn default() -> Self {
+        Self {
+            max_dataset_id_len: 128,
+            max_name_len: 256,
+            max_description_len: 4096,
+            max_format_len: 32,
+            max_original_hash_len: 64,
+            max_walrus_blob_id_len: 128,
+            max_seal_policy_id_len: 128,
+            // "0x" + 64 hex characters
+            max_uploader_len: 66,
+        }
+    }
+}
+
+impl FieldLimits {
+    /// Reject the first field longer than its limit, naming the field and limit.
+    pub fn check(&self, metadata: &DatasetVerification) -> Result<(), EnclaveError> {
+        match self.violations(metadata).into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    /// One error for every field longer than its limit, in field order.
+    pub fn violations(&self, metadata: &DatasetVerification) -> Vec<EnclaveError> {
+        let fields = [
+            ("dataset_id", metadata.dataset_id.len(), self.max_dataset_id_len),
+            ("name", metadata.name.len(), self.max_name_len),
+            ("description", metadata.description.len(), self.max_description_len),
+            ("format", metadata.format.len(), self.max_format_len),
+            ("original_hash", metadata.original_hash.len(), self.max_original_hash_len),
+            ("walrus_blob_id", metadata.walrus_blob_id.len(), self.max_walrus_blob_id_len),
+            ("seal_policy_id", metadata.seal_policy_id.len(), self.max_seal_policy_id_len),
+            ("uploader", metadata.uploader.len(), self.max_uploader_len),
+        ];
+        fields
+            .into_iter()
+            .filter(|(_, len, max)| len > max)
+            .map(|(field, len, max)| {
+                EnclaveError::InvalidInput(format!(
+                    "{} exceeds maximum length of {} bytes (got {})",
+                    field, max, len
+                ))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits_from_vars() {
+        let vars = |name: &str| match name {
+            "MAX_DATASET_BYTES" => Some("2048".to_string()),
+            "TIMESTAMP_FRESHNESS_MS" => Some("0".to_string()),
+            "FETCH_TIMEOUT_SECS" => Some("90".to_string()),
+            _ => None,
+        };
+        let limits = Limits::from_vars(vars).unwrap();
+
+        assert_eq!(limits.max_dataset_bytes, 2048);
+        assert_eq!(limits.timestamp_freshness_ms, None);
+        assert_eq!(limits.fetch_timeout, Some(Duration::from_secs(90)));
+        assert_eq!(limits.max_batch_items, DEFAULT_MAX_BATCH_ITEMS);
+
+        // An empty body is below the default minimum
+        assert!(limits.check_min_dataset_size(0).is_err());
+        assert!(limits.check_min_dataset_size(1).is_ok());
+
+        let err = Limits::from_vars(|_| Some("lots".to_string())).unwrap_err();
+        assert!(err.to_string().starts_with("invalid MAX_DATASET_BYTES"));
+    }
+
+    #[test]
+    fn test_expected_hash_length_bound() {
+        let limits = Limits::default();
+        assert!(limits.check_expected_hash_len(&"ab".repeat(32)).is_ok());
+        assert!(limits.check_expected_hash_len(&format!("0x{}", "ab".repeat(64))).is_ok());
+
+        // A pasted file is refused by length alone, without being echoed back
+        let pasted = "id,label\n1,dog\n".repeat(100_000);
+        let err = limits.check_expected_hash_len(&pasted).unwrap_err();
+        assert!(matches!(err, EnclaveError::InvalidInput(_)));
+        assert_eq!(
+            err.message(),
+            format!(
+                "expected_hash is too long: {} characters, maximum is 128 hex digits",
+                pasted.len()
+            )
+        );
+    }
+}
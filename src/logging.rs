@@ -0,0 +1,92 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{anyhow, bail};
+use tracing_subscriber::EnvFilter;
+
+/// Level used when neither `RUST_LOG` nor `LOG_LEVEL` is set.
+pub const DEFAULT_LOG_LEVEL: &str = "info";
+
+/// Output format of log lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable lines (default)
+    Text,
+    /// One JSON object per line, including the fields of the enclosing
+    /// spans such as `request_id`
+    Json,
+}
+
+impl LogFormat {
+    /// Parse a `LOG_FORMAT` value: `text` (default) or `json`.
+    pub fn parse(value: Option<&str>) -> anyhow::Result<Self> {
+        match value.map(|v| v.trim().to_ascii_lowercase()).as_deref() {
+            None | Some("") | Some("text") => Ok(LogFormat::Text),
+            Some("json") => Ok(LogFormat::Json),
+            Some(other) => bail!("LOG_FORMAT must be text or json, got {}", other),
+        }
+    }
+}
+
+/// Filter from `RUST_LOG` when set, else from `LOG_LEVEL`, else `info`.
+pub fn env_filter(rust_log: Option<&str>, log_level: Option<&str>) -> anyhow::Result<EnvFilter> {
+    let (name, directives) = match (rust_log, log_level) {
+        (Some(directives), _) => ("RUST_LOG", directives),
+        (None, Some(level)) => ("LOG_LEVEL", level),
+        (None, None) => ("default", DEFAULT_LOG_LEVEL),
+    };
+    EnvFilter::try_new(directives).map_err(|e| anyhow!("Invalid {} {:?}: {}", name, directives, e))
+}
+
+/// Install the global subscriber from `RUST_LOG`, `LOG_LEVEL` and `LOG_FORMAT`.
+///
+/// Request spans only record the request ID, method and path. Headers are only
+/// logged by `access_log` at debug level, with `Authorization` and `X-API-Key`
+/// redacted, so API keys do not reach the logs.
+pub fn init() -> anyhow::Result<()> {
+    init_with(
+        std::env::var("RUST_LOG").ok().as_deref(),
+        std::env::var("LOG_LEVEL").ok().as_deref(),
+        LogFormat::parse(std::env::var("LOG_FORMAT").ok().as_deref())?,
+    )
+}
+
+/// Like `init`, with the `RUST_LOG` and `LOG_LEVEL` values and the format given.
+pub fn init_with(
+    rust_log: Option<&str>,
+    log_level: Option<&str>,
+    format: LogFormat,
+) -> anyhow::Result<()> {
+    let filter = env_filter(rust_log, log_level)?;
+
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    match format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder.json().with_current_span(true).with_span_list(true).init(),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_log_format() {
+        assert_eq!(LogFormat::parse(None).unwrap(), LogFormat::Text);
+        assert_eq!(LogFormat::parse(Some("JSON")).unwrap(), LogFormat::Json);
+        assert!(LogFormat::parse(Some("xml")).is_err());
+    }
+
+    #[test]
+    fn test_rust_log_takes_precedence_over_log_level() {
+        let filter = env_filter(Some("debug"), Some("warn")).unwrap();
+        assert_eq!(filter.to_string(), "debug");
+
+        let filter = env_filter(None, Some("warn")).unwrap();
+        assert_eq!(filter.to_string(), "warn");
+
+        let filter = env_filter(None, None).unwrap();
+        assert_eq!(filter.to_string(), DEFAULT_LOG_LEVEL);
+    }
+}
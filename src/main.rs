@@ -6,55 +6,204 @@
 //! This is a mock server for local testing WITHOUT AWS Nitro Enclave.
 //! For production, deploy using the full Nautilus infrastructure.
 
-use axum::{routing::{get, post}, Router};
 use fastcrypto::ed25519::Ed25519KeyPair;
 use fastcrypto::traits::KeyPair;
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::net::TcpListener;
-use tower_http::cors::{CorsLayer, Any};
-use sealtrust_nautilus::{process_data, verify_metadata, get_attestation, health_check, AppState};
+use sealtrust_nautilus::config::Config;
+use sealtrust_nautilus::cors::{cors_layer, AllowedOrigins};
+use sealtrust_nautilus::dev_key;
+use sealtrust_nautilus::key_rotation;
+use sealtrust_nautilus::logging;
+use sealtrust_nautilus::request_id::with_request_id;
+use sealtrust_nautilus::router::build_router;
+use sealtrust_nautilus::server;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    tracing_subscriber::fmt::init();
+    // Every setting, from the environment or CONFIG_FILE, validated before anything starts
+    // (see sealtrust_nautilus::config)
+    let config = Config::from_env()?;
 
-    // Generate ephemeral keypair for signing (in real enclave, this comes from NSM)
-    let eph_kp = Ed25519KeyPair::generate(&mut rand::thread_rng());
+    // RUST_LOG (or LOG_LEVEL) filters; LOG_FORMAT=json for structured output
+    logging::init_with(
+        config.rust_log.as_deref(),
+        config.log_level.as_deref(),
+        config.log_format,
+    )?;
+
+    // --listen <host:port>, or HOST / PORT; fail fast on a bad value
+    let addr = listen_addr(&config)?;
+
+    // Generate ephemeral keypair for signing (in real enclave, this comes from NSM).
+    // ENCLAVE_KEY_FILE or --key-file <path> persists a DEV ONLY key across restarts.
+    let key_file = flag_arg("--key-file").map(PathBuf::from).or_else(|| config.key_file.clone());
+    let eph_kp = match key_file {
+        Some(path) => {
+            println!("🚨 Loading PERSISTED dev keypair from {} - NEVER do this in a real enclave!", path.display());
+            dev_key::load_or_generate(&path)?
+        }
+        None => Ed25519KeyPair::generate(&mut rand::thread_rng()),
+    };
 
     println!("🔐 Ephemeral public key: {:?}", eph_kp.public());
     println!("⚠️  WARNING: This is a DEV server. Use real Nautilus enclave for production!");
 
-    let state = Arc::new(AppState {
-        eph_kp,
-        api_key: "local-dev-key".to_string(),
-    });
-
-    // Configure CORS to allow requests from frontend
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any);
-
-    let app = Router::new()
-        .route("/process_data", post(process_data))        // Legacy endpoint (deprecated)
-        .route("/verify_metadata", post(verify_metadata))  // V3 Architecture endpoint
-        .route("/get_attestation", get(get_attestation))   // NSM attestation for on-chain registration
-        .route("/health_check", get(health_check))         // Full health check with endpoint status
-        .route("/health", get(|| async { "OK" }))          // Simple health check
-        .layer(cors)
-        .with_state(state);
-
-    let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
+    let dev_mode = std::env::args().any(|arg| arg == "--dev");
+
+    // API_KEY enables enforcement; without it the dev server stays open with "local-dev-key"
+    if config.api_key.is_none() {
+        println!("⚠️  API_KEY not set: protected endpoints are NOT authenticated");
+    }
+    match &config.proxy {
+        Some(proxy) => println!(
+            "🛰️  Dataset fetches go through proxy {} (authenticated: {})",
+            proxy.url,
+            proxy.credentials.is_some()
+        ),
+        None => println!("🛰️  No fetch proxy configured, datasets are fetched directly"),
+    }
+    if let Some(webhook) = &config.webhook {
+        println!("🔔 Webhook notifications: {:?}", webhook);
+    }
+    println!("📏 Limits: {:?}", config.limits);
+    if let Some((requests_per_sec, burst)) = config.rate_limit {
+        println!("🚦 Rate limit: {} req/s per client, burst {}", requests_per_sec, burst);
+    }
+    let mut state = config.build_state(eph_kp);
+    // Without an NSM device the dev server returns a mock attestation
+    state.allow_mock_attestation = dev_mode;
+    let state = Arc::new(state);
+
+    // Opt-in: rotate the signing key every KEY_ROTATION_INTERVAL_SECS. Retired keys
+    // stay in /public_key for KEY_ROTATION_GRACE_SECS; on-chain registrations must
+    // be refreshed within that window.
+    if let Some((interval, grace_period)) = config.key_rotation {
+        println!("🔄 Rotating signing key every {:?} (grace period {:?})", interval, grace_period);
+        key_rotation::spawn_rotation(state.clone(), interval, grace_period, || {
+            Ed25519KeyPair::generate(&mut rand::thread_rng())
+        });
+    }
+
+    // Configure CORS from CORS_ALLOWED_ORIGINS (comma-separated, or "*").
+    // --dev keeps the permissive Any/Any/Any policy for local frontends.
+    let allowed_origins = match config.cors_allowed_origins.clone() {
+        Some(origins) => origins,
+        None if dev_mode => AllowedOrigins::Any,
+        None => AllowedOrigins::List(Vec::new()),
+    };
+    println!("🌐 CORS allowed origins: {:?}", allowed_origins);
+    let cors = cors_layer(allowed_origins);
+
+    let app = build_router(state.clone()).layer(cors);
+    // Tag every request (and its log lines) with an X-Request-Id
+    let app = with_request_id(app);
+
+    // Connection tuning: HTTP2=false disables h2c, KEEP_ALIVE_INTERVAL_SECS=0 disables pings
+    let server_config = config.server.clone();
+
+    // HTTPS: TLS_CERT_PATH and TLS_KEY_PATH (PEM) together, loaded now so a bad
+    // pair stops startup; plain HTTP when both are unset
+    let tls = match &config.tls {
+        Some(paths) => {
+            let acceptor =
+                server::load_tls_acceptor(&paths.cert, &paths.key, server_config.http2)?;
+            println!("🔒 Serving HTTPS with certificate {}", paths.cert.display());
+            Some(acceptor)
+        }
+        None => None,
+    };
+
+    // Time /health_check reports "draining" after SIGTERM before the process exits
+    let shutdown_drain = config.shutdown_drain;
+
     let listener = TcpListener::bind(addr).await?;
 
-    println!("🚀 SealTrust Nautilus server listening on http://{}", addr);
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    println!("🚀 SealTrust Nautilus server listening on {}://{}", scheme, addr);
     println!("📡 Endpoints:");
     println!("   POST /verify_metadata - [V3] Verify and sign metadata (RECOMMENDED)");
+    println!("   POST /verify_metadata_scopes - Sign metadata under several intent scopes at once");
+    println!("   POST /verify_metadata_attested - Sign metadata bound to the enclave's attestation digest");
     println!("   POST /process_data    - [Legacy] Verify dataset and return signed hash");
-    println!("   GET  /health          - Health check");
+    println!("   POST /process_data_provenance - process_data also signing Content-Type and final URL");
+    println!("   POST /process_multipart_data - Hash several URLs as one dataset and sign");
+    println!("   POST /verify_walrus_blob - Hash a Walrus blob and return signed verification");
+    println!("   POST /verify_and_fetch_walrus - Sign metadata only if its Walrus blob matches original_hash");
+    println!("   POST /verify_dataset  - Sign metadata only if the fetched dataset matches its size and hash");
+    println!("   POST /verify_head     - Sign the size a dataset URL reports via HEAD (content not hashed)");
+    println!("   POST /check_hash      - Compare a fetched dataset against an expected hash (no signature)");
+    println!("   POST /preflight       - Check a dataset URL resolves, connects, handshakes TLS and answers HEAD");
+    println!("   POST /sign_digest     - Sign metadata carrying a precomputed hash");
+    println!("   POST /resign_metadata - Re-sign previously signed metadata with the current time");
+    println!("   POST /sign_intent     - Sign the BCS payload of a registered type under its scope");
+    println!("   POST /timestamp       - Sign a digest with the current enclave time");
+    println!("   GET  /audit           - Recent signing operations for a dataset_id (in memory only)");
+    println!("   GET  /rejections      - Recently refused requests with status and error code (in memory only)");
+    println!("   POST /sign_merkle_root - Sign a Merkle root over a set of dataset verifications");
+    println!("   POST /batch_verify_metadata - Sign many metadata items for batch verification");
+    println!("   POST /sign_batch_commitment - Sign a whole batch of metadata with one signature");
+    println!("   POST /batch_verify_dataset - Fetch and verify many datasets concurrently");
+    println!("   POST /validate_metadata - Check metadata without signing");
+    println!("   POST /bcs_preview     - Preview the BCS bytes that would be signed");
+    println!("   GET  /health          - Liveness probe");
+    println!("   GET  /health_check    - Readiness probe with dependency status");
+    println!("   GET  /selftest        - Sign and verify a fixed payload with the current key");
+    println!("   GET  /public_key      - Current signing key and keys still in their rotation grace period");
+    println!("   GET  /metrics         - Prometheus metrics");
+    println!("   GET  /version         - Build version, git commit and PCR values");
+    println!("   GET  /capabilities    - Supported algorithms, schema versions, scopes and limits");
+    #[cfg(feature = "schema")]
+    println!("   GET  /schema          - JSON Schema of the request and response types");
 
-    axum::serve(listener, app).await?;
+    println!(
+        "🔌 HTTP/2: {}, keep-alive interval: {:?}, keep-alive timeout: {:?}, max concurrent streams: {}",
+        server_config.http2,
+        server_config.keep_alive_interval,
+        server_config.keep_alive_timeout,
+        server_config.max_concurrent_streams
+    );
+
+    let serving = match tls {
+        Some(acceptor) => tokio::spawn(server::serve_tls(listener, app, server_config, acceptor)),
+        None => tokio::spawn(server::serve(listener, app, server_config)),
+    };
+    tokio::select! {
+        result = serving => result??,
+        _ = server::shutdown_signal() => {
+            // Keep serving so the load balancer sees "draining" and in-flight requests finish
+            state.begin_drain();
+            println!("🛑 Shutdown requested, draining for {:?}", shutdown_drain);
+            tokio::time::sleep(shutdown_drain).await;
+        }
+    }
 
     Ok(())
 }
+
+/// Value of a `--flag <value>` or `--flag=<value>` command line flag, if given.
+fn flag_arg(flag: &str) -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == flag {
+            return args.next();
+        }
+        if let Some(value) = arg.strip_prefix(flag).and_then(|rest| rest.strip_prefix('=')) {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+/// Address to bind: `--listen <host:port>`, else `HOST` / `PORT` from the
+/// config, defaulting to 127.0.0.1:3000.
+fn listen_addr(config: &Config) -> Result<SocketAddr, Box<dyn std::error::Error>> {
+    match flag_arg("--listen") {
+        Some(listen) => listen
+            .parse()
+            .map_err(|e| format!("invalid --listen address {:?}: {}", listen, e).into()),
+        None => Ok(config.listen),
+    }
+}
@@ -0,0 +1,198 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Signed Merkle commitments over a collection of `DatasetVerification`s.
+//!
+//! Node hashing rule (what a Move verifier must reproduce):
+//! - leaf     = sha256(0x00 || bcs(DatasetVerification))
+//! - interior = sha256(0x01 || left || right)
+//! - leaves are kept in request order; each level pairs nodes left to right,
+//!   and an unpaired last node is promoted unchanged to the next level
+//! - the root of a single leaf is the leaf hash itself
+//!
+//! The signed payload is `IntentMessage<MerkleRootCommitment>` under
+//! `IntentScope::MerkleRoot`.
+
+use crate::common::{to_signed_response, IntentMessage, IntentScope, ProcessedDataResponse};
+use crate::{validate_metadata, AppState, DatasetVerification, EnclaveError};
+use axum::extract::State;
+use axum::Json;
+use fastcrypto::encoding::{Encoding, Hex};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tracing::info;
+
+/// Domain separator prefixed to leaf preimages.
+pub const LEAF_PREFIX: u8 = 0x00;
+/// Domain separator prefixed to interior node preimages.
+pub const NODE_PREFIX: u8 = 0x01;
+
+/// Inner type for IntentMessage<T> - MUST match Move contract exactly
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MerkleRootCommitment {
+    pub root: Vec<u8>,      // Merkle root over all leaves
+    pub leaf_count: u64,    // Number of committed datasets
+}
+
+/// Request to commit to a collection of datasets.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SignMerkleRootRequest {
+    pub items: Vec<DatasetVerification>,
+}
+
+/// Signed root plus the ordered leaf hashes needed to build inclusion proofs.
+#[derive(Serialize, Deserialize)]
+pub struct MerkleRootResponse {
+    /// Hex encoded Merkle root
+    pub root: String,
+    /// Hex encoded leaf hashes, in request order
+    pub leaf_hashes: Vec<String>,
+    pub signed: ProcessedDataResponse<IntentMessage<MerkleRootCommitment>>,
+}
+
+/// Hash of a single dataset leaf.
+pub fn leaf_hash(item: &DatasetVerification) -> Vec<u8> {
+    let bytes = bcs::to_bytes(item).expect("should not fail");
+    let mut hasher = Sha256::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update(&bytes);
+    hasher.finalize().to_vec()
+}
+
+/// Hash of an interior node.
+pub fn node_hash(left: &[u8], right: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().to_vec()
+}
+
+/// Merkle root over the given leaf hashes. `None` for an empty set.
+pub fn merkle_root(leaves: &[Vec<u8>]) -> Option<Vec<u8>> {
+    if leaves.is_empty() {
+        return None;
+    }
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => node_hash(left, right),
+                [single] => single.clone(),
+                _ => unreachable!("chunks(2) yields one or two nodes"),
+            })
+            .collect();
+    }
+    level.pop()
+}
+
+/// Endpoint that validates every dataset and signs one Merkle root over them.
+pub async fn sign_merkle_root(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<SignMerkleRootRequest>,
+) -> Result<Json<MerkleRootResponse>, EnclaveError> {
+    let result = commit_merkle_root(&state, request);
+    state.metrics.record("sign_merkle_root", &result);
+    result
+}
+
+fn commit_merkle_root(
+    state: &AppState,
+    request: SignMerkleRootRequest,
+) -> Result<Json<MerkleRootResponse>, EnclaveError> {
+    if request.items.is_empty() {
+        return Err(EnclaveError::InvalidInput("items cannot be empty".to_string()));
+    }
+    if request.items.len() > state.limits.max_batch_items {
+        return Err(EnclaveError::InvalidInput(format!(
+            "too many items: {} (max {})",
+            request.items.len(),
+            state.limits.max_batch_items
+        )));
+    }
+    for (index, item) in request.items.iter().enumerate() {
+        validate_metadata(state, item).map_err(|e| {
+            EnclaveError::InvalidInput(format!("item {}: {}", index, e.message()))
+        })?;
+    }
+
+    let leaves: Vec<Vec<u8>> = request.items.iter().map(leaf_hash).collect();
+    let root = merkle_root(&leaves).expect("items are not empty");
+    info!(
+        "Signing Merkle root {} over {} datasets",
+        Hex::encode(&root),
+        leaves.len()
+    );
+
+    let commitment = MerkleRootCommitment {
+        root: root.clone(),
+        leaf_count: leaves.len() as u64,
+    };
+    let timestamp = state.clock.now_millis()?;
+
+    Ok(Json(MerkleRootResponse {
+        root: Hex::encode(&root),
+        leaf_hashes: leaves.iter().map(Hex::encode).collect(),
+        signed: to_signed_response(
+            state.signer.current().as_ref(),
+            commitment,
+            timestamp,
+            IntentScope::MerkleRoot,
+        ),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DATASET_VERIFICATION_VERSION;
+
+    fn item(id: &[u8]) -> DatasetVerification {
+        DatasetVerification {
+            version: DATASET_VERIFICATION_VERSION,
+            dataset_id: id.to_vec(),
+            name: b"data.csv".to_vec(),
+            description: b"Merkle test".to_vec(),
+            format: b"CSV".to_vec(),
+            size: 1024,
+            original_hash: vec![0xAB; 32],
+            walrus_blob_id: b"blob".to_vec(),
+            seal_policy_id: b"policy".to_vec(),
+            timestamp: 1700000000000,
+            uploader: b"0xA".to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_merkle_root_follows_node_rule() {
+        let leaves: Vec<Vec<u8>> = [item(b"a"), item(b"b"), item(b"c")]
+            .iter()
+            .map(leaf_hash)
+            .collect();
+
+        // ((a, b), c): the unpaired leaf is promoted unchanged
+        let expected = node_hash(&node_hash(&leaves[0], &leaves[1]), &leaves[2]);
+        assert_eq!(merkle_root(&leaves).unwrap(), expected);
+
+        assert_eq!(merkle_root(&leaves[..1]).unwrap(), leaves[0]);
+        assert_eq!(merkle_root(&[]), None);
+    }
+
+    #[test]
+    fn test_leaf_and_node_domains_are_separated() {
+        let leaf = leaf_hash(&item(b"a"));
+        let mut preimage = vec![LEAF_PREFIX];
+        preimage.extend(bcs::to_bytes(&item(b"a")).unwrap());
+        assert_eq!(leaf, Sha256::digest(&preimage).to_vec());
+        assert_ne!(node_hash(&leaf, &leaf), leaf_hash(&item(b"a")));
+    }
+
+    #[test]
+    fn test_merkle_root_is_order_sensitive() {
+        let ab: Vec<Vec<u8>> = [item(b"a"), item(b"b")].iter().map(leaf_hash).collect();
+        let ba: Vec<Vec<u8>> = [item(b"b"), item(b"a")].iter().map(leaf_hash).collect();
+        assert_ne!(merkle_root(&ab), merkle_root(&ba));
+    }
+}
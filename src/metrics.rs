@@ -0,0 +1,186 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{AppState, EnclaveError};
+use axum::extract::State;
+use axum::http::header::CONTENT_TYPE;
+use axum::response::IntoResponse;
+use prometheus::{
+    exponential_buckets, Encoder, Histogram, HistogramOpts, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+use std::sync::Arc;
+
+/// Content type of the Prometheus text exposition format.
+pub const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Prometheus metrics shared across handlers.
+pub struct Metrics {
+    pub registry: Registry,
+    /// Requests handled, labelled by endpoint
+    pub requests_total: IntCounterVec,
+    /// Failed requests, labelled by endpoint and `EnclaveError::code`
+    pub errors_total: IntCounterVec,
+    /// Time spent fetching a dataset body
+    pub fetch_duration_seconds: Histogram,
+    /// Size of fetched datasets
+    pub dataset_size_bytes: Histogram,
+    /// Signed-response cache lookups, labelled by `hit` or `miss`
+    pub signature_cache_total: IntCounterVec,
+    /// Dataset fetches currently holding a fetch slot
+    pub fetches_in_flight: IntGauge,
+    /// Requests queued for a free fetch slot
+    pub fetch_queue_depth: IntGauge,
+    /// Requests rejected by the rate limiter, labelled by `api_key` or `ip`
+    pub rate_limited_total: IntCounterVec,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+
+        let requests_total = IntCounterVec::new(
+            Opts::new("enclave_requests_total", "Total requests per endpoint"),
+            &["endpoint"],
+        )
+        .expect("valid metric");
+        let errors_total = IntCounterVec::new(
+            Opts::new("enclave_errors_total", "Total errors per endpoint and error code"),
+            &["endpoint", "code"],
+        )
+        .expect("valid metric");
+        let fetch_duration_seconds = Histogram::with_opts(HistogramOpts::new(
+            "enclave_dataset_fetch_duration_seconds",
+            "Dataset fetch duration in seconds",
+        ))
+        .expect("valid metric");
+        let dataset_size_bytes = Histogram::with_opts(
+            HistogramOpts::new("enclave_dataset_size_bytes", "Fetched dataset size in bytes")
+                // 1 KiB .. 1 TiB
+                .buckets(exponential_buckets(1024.0, 4.0, 16).expect("valid buckets")),
+        )
+        .expect("valid metric");
+        let signature_cache_total = IntCounterVec::new(
+            Opts::new("enclave_signature_cache_total", "Signed-response cache lookups by result"),
+            &["result"],
+        )
+        .expect("valid metric");
+        let fetches_in_flight = IntGauge::new(
+            "enclave_dataset_fetches_in_flight",
+            "Dataset fetches currently in flight",
+        )
+        .expect("valid metric");
+        let fetch_queue_depth = IntGauge::new(
+            "enclave_dataset_fetch_queue_depth",
+            "Requests waiting for a free dataset fetch slot",
+        )
+        .expect("valid metric");
+        let rate_limited_total = IntCounterVec::new(
+            Opts::new("enclave_rate_limited_total", "Requests rejected by the rate limiter"),
+            &["key"],
+        )
+        .expect("valid metric");
+
+        registry
+            .register(Box::new(requests_total.clone()))
+            .expect("register metric");
+        registry
+            .register(Box::new(errors_total.clone()))
+            .expect("register metric");
+        registry
+            .register(Box::new(fetch_duration_seconds.clone()))
+            .expect("register metric");
+        registry
+            .register(Box::new(dataset_size_bytes.clone()))
+            .expect("register metric");
+        registry
+            .register(Box::new(signature_cache_total.clone()))
+            .expect("register metric");
+        registry
+            .register(Box::new(fetches_in_flight.clone()))
+            .expect("register metric");
+        registry
+            .register(Box::new(fetch_queue_depth.clone()))
+            .expect("register metric");
+        registry
+            .register(Box::new(rate_limited_total.clone()))
+            .expect("register metric");
+
+        Self {
+            registry,
+            requests_total,
+            errors_total,
+            fetch_duration_seconds,
+            dataset_size_bytes,
+            signature_cache_total,
+            fetches_in_flight,
+            fetch_queue_depth,
+            rate_limited_total,
+        }
+    }
+
+    /// Count a handled request and, if it failed, its error code.
+    pub fn record<T>(&self, endpoint: &str, result: &Result<T, EnclaveError>) {
+        self.requests_total.with_label_values(&[endpoint]).inc();
+        if let Err(e) = result {
+            self.errors_total
+                .with_label_values(&[endpoint, e.code()])
+                .inc();
+        }
+    }
+
+    /// Render all metrics in the Prometheus text format.
+    pub fn render(&self) -> Result<String, EnclaveError> {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .map_err(|e| EnclaveError::GenericError(format!("Failed to encode metrics: {}", e)))?;
+        String::from_utf8(buffer)
+            .map_err(|e| EnclaveError::GenericError(format!("Failed to encode metrics: {}", e)))
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Endpoint exposing metrics for Prometheus scraping.
+pub async fn metrics(State(state): State<Arc<AppState>>) -> Result<impl IntoResponse, EnclaveError> {
+    let body = state.metrics.render()?;
+    Ok(([(CONTENT_TYPE, PROMETHEUS_CONTENT_TYPE)], body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_counts_requests_and_errors() {
+        let metrics = Metrics::new();
+        metrics.record::<()>("verify_metadata", &Ok(()));
+        metrics.record::<()>(
+            "verify_metadata",
+            &Err(EnclaveError::InvalidInput("name cannot be empty".to_string())),
+        );
+
+        assert_eq!(
+            metrics
+                .requests_total
+                .with_label_values(&["verify_metadata"])
+                .get(),
+            2
+        );
+        assert_eq!(
+            metrics
+                .errors_total
+                .with_label_values(&["verify_metadata", "invalid_input"])
+                .get(),
+            1
+        );
+
+        let rendered = metrics.render().unwrap();
+        assert!(rendered.contains("enclave_requests_total{endpoint=\"verify_metadata\"} 2"));
+    }
+}
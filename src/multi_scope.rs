@@ -0,0 +1,206 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! `/verify_metadata` signing one metadata under several intent scopes.
+//!
+//! During a contract migration the old and new verifiers expect the same
+//! metadata under different scopes. `/verify_metadata_scopes` runs the
+//! `/verify_metadata` checks once and returns one signed response per
+//! requested scope, all over the same metadata and timestamp, keyed by scope
+//! name (`"ProcessData"`, `"VerifyMetadata"`). Each signature is exactly the
+//! one `/verify_metadata` returns for that `intent_scope`.
+
+use crate::common::{
+    IntentMessage, IntentScope, IntentScopeSelector, ProcessedDataResponse, SigningQuery,
+};
+use crate::{
+    check_client_metadata, metadata_intent_scope, sign_client_metadata, webhook, AppState,
+    DatasetVerification, EnclaveError,
+};
+use axum::extract::{Query, State};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tracing::info;
+
+/// Metadata and every scope it should be signed under.
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MultiScopeMetadataRequest {
+    pub metadata: DatasetVerification,
+    /// Scopes by name or discriminant, each one `/verify_metadata` accepts
+    /// as `intent_scope`
+    pub intent_scopes: Vec<IntentScopeSelector>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MultiScopeMetadataResponse {
+    /// Signed metadata per requested scope, keyed by scope name
+    pub signatures: BTreeMap<String, ProcessedDataResponse<IntentMessage<DatasetVerification>>>,
+}
+
+/// Endpoint that verifies metadata like `/verify_metadata` and signs it
+/// under each requested scope.
+pub async fn verify_metadata_scopes(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SigningQuery>,
+    Json(request): Json<MultiScopeMetadataRequest>,
+) -> Result<Json<MultiScopeMetadataResponse>, EnclaveError> {
+    let started = tokio::time::Instant::now();
+    let result = sign_scopes(&state, request, &query);
+    state.pad_rejection(started, &result).await;
+    state.metrics.record("verify_metadata_scopes", &result);
+    if let Ok(Json(response)) = &result {
+        for signed in response.signatures.values() {
+            state.audit_log.record("verify_metadata_scopes", &signed.response);
+        }
+    }
+    webhook::notify(&state, "verify_metadata_scopes", &result);
+    result
+}
+
+fn sign_scopes(
+    state: &AppState,
+    request: MultiScopeMetadataRequest,
+    query: &SigningQuery,
+) -> Result<Json<MultiScopeMetadataResponse>, EnclaveError> {
+    if request.intent_scopes.is_empty() {
+        return Err(EnclaveError::InvalidInput("intent_scopes cannot be empty".to_string()));
+    }
+    let mut intents: Vec<IntentScope> = Vec::new();
+    for selector in &request.intent_scopes {
+        let intent = metadata_intent_scope(Some(selector))?;
+        if intents.contains(&intent) {
+            return Err(EnclaveError::InvalidInput(format!(
+                "intent_scope {:?} is requested more than once",
+                intent
+            )));
+        }
+        intents.push(intent);
+    }
+
+    // Checked once, so dedup and policy binding see a single verification
+    let metadata = check_client_metadata(state, request.metadata)?;
+    info!("Metadata verified successfully, signing under {:?}...", intents);
+
+    let signer = state.signer.current();
+    let signatures = intents
+        .into_iter()
+        .map(|intent| {
+            let signed = sign_client_metadata(state, signer.as_ref(), metadata.clone(), intent);
+            Ok((format!("{:?}", intent), query.apply(&state.signer, signed)?))
+        })
+        .collect::<Result<_, EnclaveError>>()?;
+    Ok(Json(MultiScopeMetadataResponse { signatures }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::common::signing_bytes;
+    use crate::{dedup, DATASET_VERIFICATION_VERSION, SUI_ADDRESS_LENGTH};
+    use fastcrypto::ed25519::Ed25519KeyPair;
+    use fastcrypto::encoding::{Encoding, Hex};
+    use fastcrypto::traits::KeyPair;
+
+    const NOW_MS: u64 = 1_700_000_000_000;
+
+    fn test_state() -> AppState {
+        let mut state = AppState::new(
+            Ed25519KeyPair::generate(&mut rand::thread_rng()),
+            "test-key".to_string(),
+        );
+        state.clock = Box::new(ManualClock::new(NOW_MS));
+        state
+    }
+
+    fn signed_bytes_query() -> SigningQuery {
+        SigningQuery { include_signed_bytes: true, ..Default::default() }
+    }
+
+    fn request(intent_scopes: serde_json::Value) -> MultiScopeMetadataRequest {
+        let metadata = DatasetVerification {
+            version: DATASET_VERIFICATION_VERSION,
+            dataset_id: b"dataset-1".to_vec(),
+            name: b"data.csv".to_vec(),
+            description: b"Migrating".to_vec(),
+            format: b"CSV".to_vec(),
+            size: 1024,
+            original_hash: vec![0xAB; 32],
+            walrus_blob_id: b"blob-1".to_vec(),
+            seal_policy_id: b"policy-1".to_vec(),
+            timestamp: NOW_MS,
+            uploader: format!("0x{}", "ab".repeat(SUI_ADDRESS_LENGTH)).into_bytes(),
+        };
+        let request = serde_json::json!({ "metadata": metadata, "intent_scopes": intent_scopes });
+        serde_json::from_value(request).unwrap()
+    }
+
+    #[test]
+    fn test_each_signature_verifies_under_its_scope() {
+        let mut state = test_state();
+        // A second verification of the same hash would be refused
+        state.recent_hashes = Some(dedup::RecentHashes::default());
+        let scopes = serde_json::json!(["process_data", 1]);
+        let Json(response) = sign_scopes(&state, request(scopes), &signed_bytes_query()).unwrap();
+
+        let names: Vec<_> = response.signatures.keys().map(String::as_str).collect();
+        assert_eq!(names, ["ProcessData", "VerifyMetadata"]);
+        let process_data = &response.signatures["ProcessData"];
+        let verify_metadata = &response.signatures["VerifyMetadata"];
+        assert_eq!(process_data.response.intent, IntentScope::ProcessData);
+        assert_eq!(verify_metadata.response.intent, IntentScope::VerifyMetadata);
+
+        for signed in [process_data, verify_metadata] {
+            let message = &signed.response;
+            assert_eq!(message.timestamp_ms, NOW_MS);
+            assert_eq!(message.data.description, b"Migrating");
+            let bytes = signing_bytes(message);
+            assert_eq!(signed.signed_bytes, Some(Hex::encode(&bytes)));
+            let signature = Hex::decode(&signed.signature).unwrap();
+            assert!(state.signer.verify(&bytes, &signature));
+        }
+
+        // The bytes differ only in the scope byte, so neither signature
+        // verifies under the other scope
+        let process_bytes = signing_bytes(&process_data.response);
+        let metadata_bytes = signing_bytes(&verify_metadata.response);
+        assert_eq!(process_bytes[1..], metadata_bytes[1..]);
+        let signature = Hex::decode(&process_data.signature).unwrap();
+        assert!(!state.signer.verify(&metadata_bytes, &signature));
+    }
+
+    #[tokio::test]
+    async fn test_rejections_wait_for_floor() {
+        const FLOOR: std::time::Duration = std::time::Duration::from_millis(200);
+        let mut state = test_state();
+        state.rejection_floor = Some(FLOOR);
+        let started = std::time::Instant::now();
+        let request = request(serde_json::json!(["bogus"]));
+        let query = Query(SigningQuery::default());
+        let result = verify_metadata_scopes(State(Arc::new(state)), query, Json(request));
+        assert!(result.await.is_err());
+        assert!(started.elapsed() >= FLOOR);
+    }
+
+    #[test]
+    fn test_invalid_scope_lists_are_refused() {
+        let state = test_state();
+        for scopes in [
+            serde_json::json!([]),
+            serde_json::json!(["verify_metadata", "bogus"]),
+            serde_json::json!(["verify_metadata", "merkle_root"]),
+            serde_json::json!(["verify_metadata", "VerifyMetadata"]),
+            serde_json::json!([0, 255]),
+        ] {
+            let result = sign_scopes(&state, request(scopes.clone()), &SigningQuery::default());
+            assert!(
+                matches!(result.err(), Some(EnclaveError::InvalidInput(_))),
+                "{} should be refused",
+                scopes
+            );
+        }
+    }
+}
@@ -0,0 +1,433 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Pre-flight reachability checks for a dataset URL.
+//!
+//! `/preflight` runs the steps a fetch would take, one by one, and reports
+//! each outcome instead of failing on the first: DNS resolution, a TCP
+//! connection, the TLS handshake and certificate (https only), then a HEAD
+//! request following redirects under the usual URL policy. Nothing is
+//! downloaded or signed. A step is omitted when an earlier one failed.
+//!
+//! DNS and TCP are checked directly from the enclave, so with a fetch proxy
+//! configured they can fail even though fetches succeed through the proxy.
+
+use crate::url_policy::UrlPolicy;
+use crate::{fetch, ipfs, AppState, EnclaveError};
+use axum::extract::State;
+use axum::Json;
+use reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE, LOCATION};
+use reqwest::tls::TlsInfo;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::TcpStream;
+use tracing::info;
+use url::{Host, Url};
+
+const MS_PER_DAY: i64 = 24 * 60 * 60 * 1000;
+
+/// Request to check that a dataset URL can be fetched.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PreflightRequest {
+    pub dataset_url: String,
+}
+
+/// Outcome of one check: `result` holds what was observed, `error` why the
+/// check failed. A failed check may still carry a `result`, such as the
+/// status of a 404.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Check<T> {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<T>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl<T> Check<T> {
+    fn passed(result: T) -> Self {
+        Self { ok: true, result: Some(result), error: None }
+    }
+
+    fn failed(result: Option<T>, error: String) -> Self {
+        Self { ok: false, result, error: Some(error) }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DnsInfo {
+    pub host: String,
+    pub addresses: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConnectInfo {
+    /// Address the connection was made to
+    pub address: String,
+}
+
+/// Leaf certificate presented by the dataset host.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CertificateInfo {
+    pub subject: String,
+    pub issuer: String,
+    /// Start of the validity period, ms since the Unix epoch
+    pub not_before_ms: i64,
+    /// End of the validity period, ms since the Unix epoch
+    pub not_after_ms: i64,
+    /// Whole days until `not_after_ms`, negative once expired
+    pub expires_in_days: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HttpInfo {
+    pub status: u16,
+    /// URL that answered, after following redirects
+    pub final_url: String,
+    pub redirects: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_length: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+}
+
+/// Every check that ran, in order. `reachable` is true only when all passed.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PreflightResponse {
+    pub dataset_url: String,
+    pub reachable: bool,
+    pub dns: Check<DnsInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connect: Option<Check<ConnectInfo>>,
+    /// Only for https URLs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<Check<CertificateInfo>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http: Option<Check<HttpInfo>>,
+}
+
+/// Endpoint reporting whether `dataset_url` resolves, connects, completes a
+/// TLS handshake and answers a HEAD request. A URL the fetch policy forbids
+/// is rejected outright, as it would be by any fetch.
+pub async fn preflight(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<PreflightRequest>,
+) -> Result<Json<PreflightResponse>, EnclaveError> {
+    let result = run_preflight(&state, request).await.map(Json);
+    state.metrics.record("preflight", &result);
+    result
+}
+
+async fn run_preflight(
+    state: &AppState,
+    request: PreflightRequest,
+) -> Result<PreflightResponse, EnclaveError> {
+    let fetch_url = ipfs::resolve_dataset_url(&state.ipfs_gateway, &request.dataset_url)?;
+    let url = state.url_policy.check(&fetch_url)?;
+    info!("Pre-flight check of {}", url);
+
+    let mut response = PreflightResponse {
+        dataset_url: request.dataset_url,
+        reachable: false,
+        dns: resolve(&state.url_policy, &url).await,
+        connect: None,
+        tls: None,
+        http: None,
+    };
+    let Some(addresses) = response.dns.result.as_ref().filter(|_| response.dns.ok) else {
+        return Ok(response);
+    };
+    let addresses: Vec<SocketAddr> =
+        addresses.addresses.iter().filter_map(|a| a.parse().ok()).collect();
+    let connect = connect(&addresses).await;
+    let connected = connect.ok;
+    response.connect = Some(connect);
+    if !connected {
+        return Ok(response);
+    }
+
+    let _permit = state.fetch_limiter.acquire(&state.metrics).await?;
+    let (tls, http) = probe(state, url).await;
+    response.reachable = tls.as_ref().map_or(true, |tls| tls.ok) && http.ok;
+    response.tls = tls;
+    response.http = Some(http);
+    Ok(response)
+}
+
+/// Resolve the host of `url`. A hostname resolving to an address the policy
+/// blocks fails, as its fetch would; IP literals were checked with the URL.
+async fn resolve(policy: &UrlPolicy, url: &Url) -> Check<DnsInfo> {
+    // Url::host_str keeps the brackets around IPv6 literals, as lookup_host expects
+    let host = url.host_str().unwrap_or_default().to_string();
+    let port = url.port_or_known_default().unwrap_or(443);
+    let lookup = tokio::net::lookup_host(format!("{}:{}", host, port));
+    match tokio::time::timeout(fetch::CONNECT_TIMEOUT, lookup).await {
+        Ok(Ok(addresses)) => {
+            let addresses: Vec<SocketAddr> = addresses.collect();
+            if addresses.is_empty() {
+                return Check::failed(None, format!("{} has no addresses", host));
+            }
+            let blocked = match url.host() {
+                Some(Host::Domain(domain)) => addresses
+                    .iter()
+                    .find_map(|address| policy.check_resolved(domain, address.ip()).err()),
+                _ => None,
+            };
+            let addresses = addresses.iter().map(|a| a.to_string()).collect();
+            let dns = DnsInfo { host, addresses };
+            match blocked {
+                Some(blocked) => Check::failed(Some(dns), blocked.to_string()),
+                None => Check::passed(dns),
+            }
+        }
+        Ok(Err(e)) => Check::failed(None, format!("failed to resolve {}: {}", host, e)),
+        Err(_) => Check::failed(None, format!("resolving {} timed out", host)),
+    }
+}
+
+async fn connect(addresses: &[SocketAddr]) -> Check<ConnectInfo> {
+    match tokio::time::timeout(fetch::CONNECT_TIMEOUT, TcpStream::connect(addresses)).await {
+        Ok(Ok(stream)) => {
+            let address = stream.peer_addr().map(|a| a.to_string()).unwrap_or_default();
+            Check::passed(ConnectInfo { address })
+        }
+        Ok(Err(e)) => Check::failed(None, format!("failed to connect: {}", e)),
+        Err(_) => Check::failed(None, "connecting timed out".to_string()),
+    }
+}
+
+/// HEAD `url`, following redirects as a fetch would. The TLS check reflects
+/// the first hop, which is the host named in the request.
+async fn probe(
+    state: &AppState,
+    mut url: Url,
+) -> (Option<Check<CertificateInfo>>, Check<HttpInfo>) {
+    let mut tls = None;
+    let mut redirects = 0;
+    loop {
+        let mut request = state.raw_http_client.head(url.as_str());
+        if let Some(timeout) = state.limits.fetch_timeout {
+            request = request.timeout(timeout);
+        }
+        let response = match request.send().await {
+            Ok(response) => response,
+            Err(e) => {
+                let error = format!("HEAD {} failed: {}", url, e);
+                // TCP already connected, so a first-hop connect error is the handshake
+                if redirects == 0 && url.scheme() == "https" && e.is_connect() {
+                    tls = Some(Check::failed(None, error.clone()));
+                }
+                return (tls, Check::failed(None, error));
+            }
+        };
+        if redirects == 0 && url.scheme() == "https" {
+            let tls_info = response.extensions().get::<TlsInfo>();
+            tls = Some(certificate_check(state, tls_info));
+        }
+
+        let headers = response.headers();
+        let status = response.status();
+        let location = headers.get(LOCATION).filter(|_| status.is_redirection());
+        if let Some(location) = location {
+            let next = match next_hop(state, &url, location.to_str().ok(), redirects) {
+                Ok(next) => next,
+                Err(e) => return (tls, Check::failed(None, e.message().to_string())),
+            };
+            url = next;
+            redirects += 1;
+            continue;
+        }
+
+        let info = HttpInfo {
+            status: status.as_u16(),
+            final_url: url.to_string(),
+            redirects,
+            content_length: headers
+                .get(CONTENT_LENGTH)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse().ok()),
+            content_type: headers
+                .get(CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string),
+        };
+        let http = if status.is_success() {
+            Check::passed(info)
+        } else {
+            Check::failed(Some(info), format!("{} returned {}", url, status))
+        };
+        return (tls, http);
+    }
+}
+
+/// The redirect target, checked against the same limit and URL policy as a fetch.
+fn next_hop(
+    state: &AppState,
+    current: &Url,
+    location: Option<&str>,
+    redirects: usize,
+) -> Result<Url, EnclaveError> {
+    if redirects >= state.max_redirects {
+        return Err(EnclaveError::FetchFailed(format!(
+            "exceeded the limit of {} redirects",
+            state.max_redirects
+        )));
+    }
+    let next = location.and_then(|location| current.join(location).ok()).ok_or_else(|| {
+        EnclaveError::FetchFailed(format!("{} redirected to an invalid location", current))
+    })?;
+    state.url_policy.check_redirect(current, &next)?;
+    Ok(next)
+}
+
+fn certificate_check(state: &AppState, tls: Option<&TlsInfo>) -> Check<CertificateInfo> {
+    let Some(der) = tls.and_then(TlsInfo::peer_certificate) else {
+        return Check::failed(None, "no peer certificate available".to_string());
+    };
+    let now_ms = match state.clock.now_millis() {
+        Ok(now) => now as i64,
+        Err(e) => return Check::failed(None, e.message().to_string()),
+    };
+    match certificate_info(der, now_ms) {
+        Ok(info) if info.not_after_ms < now_ms => {
+            Check::failed(Some(info), "certificate has expired".to_string())
+        }
+        Ok(info) if info.not_before_ms > now_ms => {
+            Check::failed(Some(info), "certificate is not yet valid".to_string())
+        }
+        Ok(info) => Check::passed(info),
+        Err(e) => Check::failed(None, e),
+    }
+}
+
+/// Subject, issuer and validity period of a DER encoded certificate.
+fn certificate_info(der: &[u8], now_ms: i64) -> Result<CertificateInfo, String> {
+    let (_, certificate) = x509_parser::parse_x509_certificate(der)
+        .map_err(|e| format!("invalid peer certificate: {}", e))?;
+    let validity = certificate.validity();
+    let not_after_ms = validity.not_after.timestamp() * 1000;
+    Ok(CertificateInfo {
+        subject: certificate.subject().to_string(),
+        issuer: certificate.issuer().to_string(),
+        not_before_ms: validity.not_before.timestamp() * 1000,
+        not_after_ms,
+        expires_in_days: (not_after_ms - now_ms).div_euclid(MS_PER_DAY),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fastcrypto::ed25519::Ed25519KeyPair;
+    use fastcrypto::traits::KeyPair;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn test_state() -> AppState {
+        let mut state = AppState::new(
+            Ed25519KeyPair::generate(&mut rand::thread_rng()),
+            "test-key".to_string(),
+        );
+        state.url_policy.allowlist = vec!["127.0.0.1".to_string()];
+        state
+    }
+
+    /// Answer each connection with the next of `responses`, returning the base URL.
+    async fn serve(responses: Vec<&'static str>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = vec![0u8; 4096];
+                let _ = socket.read(&mut request).await;
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        base
+    }
+
+    fn request(dataset_url: String) -> PreflightRequest {
+        PreflightRequest { dataset_url }
+    }
+
+    #[tokio::test]
+    async fn test_reports_each_check_after_redirect() {
+        let base = serve(vec![
+            // The TCP check connects and closes without sending anything
+            "",
+            "HTTP/1.1 302 Found\r\nLocation: /data.csv\r\nContent-Length: 0\r\n\
+             Connection: close\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Length: 5000\r\nContent-Type: text/csv\r\n\
+             Connection: close\r\n\r\n",
+        ])
+        .await;
+        let state = test_state();
+        let response = run_preflight(&state, request(format!("{}/start", base))).await.unwrap();
+
+        assert!(response.reachable);
+        assert!(response.dns.ok);
+        assert_eq!(response.dns.result.unwrap().addresses, vec![base[7..].to_string()]);
+        assert!(response.connect.unwrap().ok);
+        // Plain http has no TLS check
+        assert!(response.tls.is_none());
+        let http = response.http.unwrap();
+        assert!(http.ok);
+        let info = http.result.unwrap();
+        assert_eq!(info.status, 200);
+        assert_eq!(info.final_url, format!("{}/data.csv", base));
+        assert_eq!(info.redirects, 1);
+        assert_eq!(info.content_length, Some(5000));
+        assert_eq!(info.content_type.as_deref(), Some("text/csv"));
+    }
+
+    #[tokio::test]
+    async fn test_failed_checks_are_reported_not_returned() {
+        let base = serve(vec![
+            "",
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        ])
+        .await;
+        let state = test_state();
+        let response = run_preflight(&state, request(format!("{}/gone", base))).await.unwrap();
+        assert!(!response.reachable);
+        let http = response.http.unwrap();
+        assert!(!http.ok);
+        assert_eq!(http.result.unwrap().status, 404);
+        assert!(http.error.unwrap().contains("404"));
+
+        // Nothing listens on a port that was just released
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/data.csv", listener.local_addr().unwrap());
+        drop(listener);
+        let response = run_preflight(&state, request(url)).await.unwrap();
+        assert!(!response.reachable);
+        assert!(response.dns.ok);
+        let connect = response.connect.unwrap();
+        assert!(!connect.ok);
+        assert!(connect.error.unwrap().starts_with("failed to connect"));
+        assert!(response.http.is_none());
+
+        // The URL policy still applies
+        let err = run_preflight(&state, request("http://10.0.0.5/data.csv".to_string()))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, EnclaveError::Forbidden(_)));
+    }
+
+    #[tokio::test]
+    async fn test_hostname_resolving_to_blocked_address_fails_dns_check() {
+        let mut state = test_state();
+        // Allowed by the URL policy, so only the resolved address is refused
+        state.url_policy.allowlist.push("localhost".to_string());
+        let response = run_preflight(&state, request("http://localhost:9/data.csv".to_string()))
+            .await
+            .unwrap();
+        assert!(!response.reachable);
+        assert!(!response.dns.ok);
+        assert!(!response.dns.result.unwrap().addresses.is_empty());
+        assert!(response.dns.error.unwrap().starts_with("localhost resolved to blocked address"));
+        assert!(response.connect.is_none());
+    }
+}
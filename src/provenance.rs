@@ -0,0 +1,242 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! `/process_data` with the dataset's `Content-Type` and final URL signed.
+//!
+//! The server's `Content-Type` and the URL reached after redirects say where
+//! the bytes actually came from, which the requested `dataset_url` alone does
+//! not. They are signed as a separate `DatasetProvenance` under
+//! `IntentScope::Provenance`, so the `/process_data` layout and the Move
+//! verifiers already deployed for it are unchanged. A Move verifier rebuilds
+//! the signed bytes with `bcs::to_bytes(&intent_message)`, which is:
+//! - the scope byte `0x06`
+//! - `timestamp_ms` as a little-endian u64
+//! - the BCS of the `DatasetVerification`, exactly as `/process_data` signs it
+//! - `content_type` as a ULEB128 length and its bytes, empty when the server
+//!   sent none
+//! - `final_url` as a ULEB128 length and its bytes
+
+use crate::common::{
+    to_signed_response, IntentMessage, IntentScope, ProcessDataRequest, ProcessedDataResponse,
+    SigningQuery,
+};
+use crate::{
+    describe_dataset, webhook, AppState, DatasetRequest, DatasetVerification, EnclaveError,
+};
+use axum::extract::{Query, State};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::info;
+
+/// Inner type for IntentMessage<T> pairing a `DatasetVerification` with what the dataset's
+/// server reported - MUST match Move contract exactly
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DatasetProvenance {
+    pub verification: DatasetVerification,
+    /// `Content-Type` header of the response, as sent
+    pub content_type: Vec<u8>,
+    /// URL that served the body, after following redirects
+    pub final_url: Vec<u8>,
+}
+
+/// Endpoint that fetches and signs a dataset like `/process_data`, also
+/// signing its `Content-Type` and final URL.
+pub async fn process_data_provenance(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SigningQuery>,
+    Json(request): Json<ProcessDataRequest<DatasetRequest>>,
+) -> Result<Json<ProcessedDataResponse<IntentMessage<DatasetProvenance>>>, EnclaveError> {
+    let result = sign_provenance(&state, request.payload)
+        .await
+        .and_then(|Json(response)| query.apply(&state.signer, response).map(Json));
+    state.metrics.record("process_data_provenance", &result);
+    if let Ok(Json(response)) = &result {
+        let message = &response.response;
+        let verification = message.data.verification.clone();
+        let signed = IntentMessage::new(verification, message.timestamp_ms, message.intent);
+        state.audit_log.record("process_data_provenance", &signed);
+    }
+    webhook::notify(&state, "process_data_provenance", &result);
+    result
+}
+
+async fn sign_provenance(
+    state: &AppState,
+    payload: DatasetRequest,
+) -> Result<Json<ProcessedDataResponse<IntentMessage<DatasetProvenance>>>, EnclaveError> {
+    let (verification, response_info) = describe_dataset(state, payload).await?;
+    info!("Signing provenance of {}", response_info.final_url);
+    let timestamp = verification.timestamp;
+    let provenance = DatasetProvenance {
+        verification,
+        content_type: response_info.content_type.unwrap_or_default().into_bytes(),
+        final_url: response_info.final_url.into_bytes(),
+    };
+    Ok(Json(to_signed_response(
+        state.signer.current().as_ref(),
+        provenance,
+        timestamp,
+        IntentScope::Provenance,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::signing_bytes;
+    use crate::DATASET_VERIFICATION_VERSION;
+    use fastcrypto::ed25519::Ed25519KeyPair;
+    use fastcrypto::encoding::{Encoding, Hex};
+    use fastcrypto::traits::KeyPair;
+    use sha2::{Digest, Sha256};
+
+    fn request(dataset_url: String) -> ProcessDataRequest<DatasetRequest> {
+        ProcessDataRequest {
+            payload: DatasetRequest {
+                dataset_url,
+                expected_hash: None,
+                format: "CSV".to_string(),
+                schema_version: "v1.0".to_string(),
+                hash_algorithm: None,
+                strict_format: None,
+                hash_wire_bytes: None,
+                validate_csv_schema: None,
+                byte_range: None,
+                decompress: None,
+                auth: None,
+                populate_metadata: None,
+                no_redirects: None,
+            },
+        }
+    }
+
+    /// Redirect `/old.csv` to `/data.csv`, then serve `body` there as CSV.
+    async fn serve_redirected(body: &'static [u8]) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let _ = socket.read(&mut [0u8; 4096]).await;
+            let redirect = format!(
+                "HTTP/1.1 302 Found\r\nLocation: http://{}/data.csv\r\n\
+                 Content-Length: 0\r\nConnection: close\r\n\r\n",
+                addr
+            );
+            socket.write_all(redirect.as_bytes()).await.unwrap();
+            drop(socket);
+
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let _ = socket.read(&mut [0u8; 4096]).await;
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/csv\r\nContent-Length: {}\r\n\
+                 Connection: close\r\n\r\n",
+                body.len()
+            );
+            socket.write_all(head.as_bytes()).await.unwrap();
+            socket.write_all(body).await.unwrap();
+        });
+        format!("http://{}/old.csv", addr)
+    }
+
+    #[tokio::test]
+    async fn test_provenance_signs_content_type_and_final_url() {
+        const BODY: &[u8] = b"id,label\n1,dog\n";
+        let mut state = AppState::new(
+            Ed25519KeyPair::generate(&mut rand::thread_rng()),
+            "test-key".to_string(),
+        );
+        state.url_policy.allowlist = vec!["127.0.0.1".to_string()];
+        let state = Arc::new(state);
+
+        let url = serve_redirected(BODY).await;
+        let Json(signed) = process_data_provenance(
+            State(state.clone()),
+            Query(SigningQuery::default()),
+            Json(request(url.clone())),
+        )
+        .await
+        .unwrap();
+        let message = &signed.response;
+        assert_eq!(message.intent, IntentScope::Provenance);
+        let provenance = &message.data;
+        assert_eq!(provenance.content_type, b"text/csv");
+        assert_eq!(provenance.final_url, url.replace("/old.csv", "/data.csv").into_bytes());
+        // The verification is what /process_data signs for the requested URL
+        assert_eq!(provenance.verification.name, url.as_bytes());
+        assert_eq!(provenance.verification.original_hash, Sha256::digest(BODY).to_vec());
+        assert_eq!(provenance.verification.timestamp, message.timestamp_ms);
+
+        let signature = Hex::decode(&signed.signature).unwrap();
+        assert!(state.signer.verify(&signing_bytes(message), &signature));
+    }
+
+    #[tokio::test]
+    async fn test_signing_is_recorded_in_audit_log() {
+        let mut state = AppState::new(
+            Ed25519KeyPair::generate(&mut rand::thread_rng()),
+            "test-key".to_string(),
+        );
+        state.url_policy.allowlist = vec!["127.0.0.1".to_string()];
+        let state = Arc::new(state);
+
+        let url = serve_redirected(b"id,label\n1,dog\n").await;
+        let query = Query(SigningQuery::default());
+        let Json(signed) = process_data_provenance(State(state.clone()), query, Json(request(url)))
+            .await
+            .unwrap();
+
+        let message = &signed.response;
+        let dataset_id = String::from_utf8(message.data.verification.dataset_id.clone()).unwrap();
+        let records = state.audit_log.find(&dataset_id);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].endpoint, "process_data_provenance");
+        assert_eq!(records[0].intent, IntentScope::Provenance);
+        assert_eq!(records[0].timestamp_ms, message.timestamp_ms);
+    }
+
+    #[test]
+    fn test_provenance_bytes_are_pinned() {
+        let verification = DatasetVerification {
+            version: DATASET_VERIFICATION_VERSION,
+            dataset_id: b"legacy".to_vec(),
+            name: b"https://a.io/d".to_vec(),
+            description: b"d".to_vec(),
+            format: b"CSV".to_vec(),
+            size: 3,
+            original_hash: vec![0xAB; 2],
+            walrus_blob_id: vec![],
+            seal_policy_id: vec![],
+            timestamp: 1,
+            uploader: vec![],
+        };
+        let provenance = DatasetProvenance {
+            verification: verification.clone(),
+            content_type: b"text/csv".to_vec(),
+            final_url: b"https://b.io/d".to_vec(),
+        };
+        let message = IntentMessage::new(provenance, 1, IntentScope::Provenance);
+
+        // scope || timestamp_ms || verification || content_type || final_url
+        let mut expected = vec![0x06];
+        expected.extend(1u64.to_le_bytes());
+        expected.extend(bcs::to_bytes(&verification).unwrap());
+        expected.push(8);
+        expected.extend(b"text/csv");
+        expected.push(14);
+        expected.extend(b"https://b.io/d");
+        assert_eq!(signing_bytes(&message), expected);
+
+        // Pinned, so any layout change here fails loudly before Move sees it
+        let pinned = concat!(
+            "06", "0100000000000000",
+            "01", "066c6567616379", "0e68747470733a2f2f612e696f2f64", "0164", "03435356",
+            "0300000000000000", "02abab", "00", "00", "0100000000000000", "00",
+            "08746578742f637376", "0e68747470733a2f2f622e696f2f64",
+        );
+        assert_eq!(Hex::encode(signing_bytes(&message)), pinned);
+    }
+}
@@ -0,0 +1,149 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::auth::extract_api_key;
+use crate::{AppState, EnclaveError};
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::header::RETRY_AFTER;
+use axum::http::HeaderValue;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use lru::LruCache;
+use std::net::SocketAddr;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Default number of clients whose buckets are remembered.
+pub const DEFAULT_RATE_LIMIT_CAPACITY: usize = 10_000;
+
+/// Token bucket of one client.
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+/// Token-bucket rate limiter with one bucket per client. Each bucket holds up
+/// to `burst` requests and refills at `requests_per_sec`. Once full, the
+/// least recently seen client is forgotten first, which only ever resets its
+/// bucket to full.
+pub struct RateLimiter {
+    requests_per_sec: f64,
+    burst: f64,
+    buckets: Mutex<LruCache<String, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_sec: f64, burst: u32, capacity: NonZeroUsize) -> Self {
+        Self {
+            requests_per_sec,
+            burst: f64::from(burst.max(1)),
+            buckets: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+
+    /// Take one token from `key`'s bucket at `now`, or return how long until
+    /// a token is available.
+    pub fn check(&self, key: &str, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().expect("rate limiter lock poisoned");
+        let bucket = buckets.get_or_insert_mut(key.to_string(), || Bucket {
+            tokens: self.burst,
+            updated_at: now,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.requests_per_sec).min(self.burst);
+        bucket.updated_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        if self.requests_per_sec <= 0.0 {
+            return Err(Duration::MAX);
+        }
+        Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.requests_per_sec))
+    }
+}
+
+/// Middleware throttling requests per client when `state.rate_limiter` is set.
+///
+/// Clients are keyed by API key when keys are enforced (so this must run
+/// after `require_api_key`), otherwise by source IP.
+pub async fn rate_limit(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(limiter) = &state.rate_limiter else {
+        return next.run(request).await;
+    };
+
+    let (kind, key) = client_key(&state, &request);
+    match limiter.check(&key, Instant::now()) {
+        Ok(()) => next.run(request).await,
+        Err(retry_after) => {
+            state.metrics.rate_limited_total.with_label_values(&[kind]).inc();
+            let mut response =
+                EnclaveError::TooManyRequests("rate limit exceeded".to_string()).into_response();
+            // Whole seconds, rounded up so an immediate retry is never advised
+            let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+            if let Ok(value) = HeaderValue::from_str(&secs.to_string()) {
+                response.headers_mut().insert(RETRY_AFTER, value);
+            }
+            response
+        }
+    }
+}
+
+/// Bucket key for the request: the API key when keys are enforced, else the
+/// source IP. Returns the kind of key alongside, for the throttle metric.
+fn client_key(state: &AppState, request: &Request) -> (&'static str, String) {
+    if state.require_api_key {
+        if let Some(key) = extract_api_key(request.headers()) {
+            return ("api_key", format!("key:{}", key));
+        }
+    }
+    let ip = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    ("ip", format!("ip:{}", ip))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_burst_then_refill() {
+        let limiter = RateLimiter::new(2.0, 3, NonZeroUsize::new(8).unwrap());
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.check("client", start).is_ok());
+        }
+        let retry_after = limiter.check("client", start).unwrap_err();
+        assert_eq!(retry_after, Duration::from_millis(500));
+
+        // Other clients have their own bucket
+        assert!(limiter.check("other", start).is_ok());
+
+        // Half a second at 2 req/s refills one token
+        assert!(limiter.check("client", start + Duration::from_millis(500)).is_ok());
+        assert!(limiter.check("client", start + Duration::from_millis(500)).is_err());
+    }
+
+    #[test]
+    fn test_refill_is_capped_at_burst() {
+        let limiter = RateLimiter::new(100.0, 2, NonZeroUsize::new(8).unwrap());
+        let start = Instant::now();
+        assert!(limiter.check("client", start).is_ok());
+
+        let later = start + Duration::from_secs(60);
+        assert!(limiter.check("client", later).is_ok());
+        assert!(limiter.check("client", later).is_ok());
+        assert!(limiter.check("client", later).is_err());
+    }
+}
@@ -0,0 +1,196 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Signed rejection receipts.
+//!
+//! When a client sends `X-Rejection-Receipt: true` and the enclave refuses
+//! the request with a 4xx error (hash mismatch, stale timestamp, invalid
+//! field, ...), the JSON error body gains a `receipt` field: an
+//! `IntentMessage<RejectionReceipt>` signed under `IntentScope::Rejection`
+//! with the enclave key. It proves to an auditor that the enclave evaluated
+//! the request identified by `request_digest` and declined it.
+
+use crate::common::{to_signed_response, IntentMessage, IntentScope, ProcessedDataResponse};
+use crate::{AppState, EnclaveError};
+use axum::body::Body;
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+/// Request header opting in to a signed receipt for rejected requests.
+pub const REJECTION_RECEIPT_HEADER: &str = "x-rejection-receipt";
+
+/// Inner type for IntentMessage<T> - MUST match Move contract exactly
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RejectionReceipt {
+    pub endpoint: Vec<u8>,       // Request path, e.g. /verify_metadata
+    pub code: Vec<u8>,           // EnclaveError code, e.g. hash_mismatch
+    pub reason: Vec<u8>,         // Human-readable error message
+    pub request_digest: Vec<u8>, // sha256 of the request body
+}
+
+/// Middleware attaching a signed `RejectionReceipt` to 4xx error responses
+/// when the request carries `X-Rejection-Receipt: true`.
+pub async fn rejection_receipt(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let requested = request
+        .headers()
+        .get(REJECTION_RECEIPT_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.trim().eq_ignore_ascii_case("true"));
+    if !requested {
+        return next.run(request).await;
+    }
+
+    // Buffer the body to digest it, then hand it on unchanged
+    let endpoint = request.uri().path().to_string();
+    let (parts, body) = request.into_parts();
+    let Ok(bytes) = axum::body::to_bytes(body, state.limits.max_batch_body_bytes).await else {
+        return EnclaveError::PayloadTooLarge("request body exceeds the size limit".to_string())
+            .into_response();
+    };
+    let request_digest = Sha256::digest(&bytes).to_vec();
+    let response = next.run(Request::from_parts(parts, Body::from(bytes))).await;
+
+    if !response.status().is_client_error() {
+        return response;
+    }
+    match attach_receipt(&state, response, endpoint, request_digest).await {
+        Ok(response) => response,
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Re-emit the JSON error body of `response` with a signed receipt added.
+async fn attach_receipt(
+    state: &AppState,
+    response: Response,
+    endpoint: String,
+    request_digest: Vec<u8>,
+) -> Result<Response, EnclaveError> {
+    let (parts, body) = response.into_parts();
+    let bytes = axum::body::to_bytes(body, usize::MAX)
+        .await
+        .map_err(|e| EnclaveError::GenericError(format!("Failed to read error body: {}", e)))?;
+    let parsed = serde_json::from_slice::<serde_json::Value>(&bytes).ok();
+    let mut error = parsed.filter(|value| value.is_object()).unwrap_or_else(|| {
+        // Rejections from axum extractors are plain text
+        serde_json::json!({
+            "error": String::from_utf8_lossy(&bytes),
+            "code": parts.status.canonical_reason().unwrap_or("rejected"),
+        })
+    });
+
+    let field = |name: &str| error[name].as_str().unwrap_or_default().as_bytes().to_vec();
+    let receipt = RejectionReceipt {
+        endpoint: endpoint.into_bytes(),
+        code: field("code"),
+        reason: field("error"),
+        request_digest,
+    };
+    error["receipt"] = serde_json::to_value(sign_receipt(state, receipt)?)?;
+
+    Ok((parts.status, Json(error)).into_response())
+}
+
+/// Sign `receipt` with the current enclave key under `IntentScope::Rejection`.
+pub fn sign_receipt(
+    state: &AppState,
+    receipt: RejectionReceipt,
+) -> Result<ProcessedDataResponse<IntentMessage<RejectionReceipt>>, EnclaveError> {
+    let timestamp = state.clock.now_millis()?;
+    Ok(to_signed_response(
+        state.signer.current().as_ref(),
+        receipt,
+        timestamp,
+        IntentScope::Rejection,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::signing_bytes;
+    use crate::{verify_metadata, DatasetVerification, DATASET_VERIFICATION_VERSION};
+    use axum::http::header::CONTENT_TYPE;
+    use axum::routing::post;
+    use axum::{middleware, Router};
+    use fastcrypto::ed25519::Ed25519KeyPair;
+    use fastcrypto::encoding::{Encoding, Hex};
+    use fastcrypto::traits::KeyPair;
+    use tower::ServiceExt;
+
+    fn app(state: Arc<AppState>) -> Router {
+        Router::new()
+            .route("/verify_metadata", post(verify_metadata))
+            .layer(middleware::from_fn_with_state(state.clone(), rejection_receipt))
+            .with_state(state)
+    }
+
+    fn stale_request_body() -> String {
+        let metadata = DatasetVerification {
+            version: DATASET_VERIFICATION_VERSION,
+            dataset_id: b"dataset-1".to_vec(),
+            name: b"data.csv".to_vec(),
+            description: b"Stale".to_vec(),
+            format: b"CSV".to_vec(),
+            size: 1024,
+            original_hash: vec![0xAB; 32],
+            walrus_blob_id: b"blob-1".to_vec(),
+            seal_policy_id: b"policy-1".to_vec(),
+            timestamp: 1,
+            uploader: format!("0x{}", "ab".repeat(32)).into_bytes(),
+        };
+        serde_json::json!({ "metadata": metadata }).to_string()
+    }
+
+    async fn send(app: Router, body: &str, receipt: bool) -> (u16, serde_json::Value) {
+        let mut request = axum::http::Request::post("/verify_metadata")
+            .header(CONTENT_TYPE, "application/json");
+        if receipt {
+            request = request.header(REJECTION_RECEIPT_HEADER, "true");
+        }
+        let response = app
+            .oneshot(request.body(Body::from(body.to_string())).unwrap())
+            .await
+            .unwrap();
+        let status = response.status().as_u16();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_rejection_receipt_is_signed_on_request() {
+        let state = Arc::new(AppState::new(
+            Ed25519KeyPair::generate(&mut rand::thread_rng()),
+            "test-key".to_string(),
+        ));
+        let body = stale_request_body();
+
+        let (status, error) = send(app(state.clone()), &body, false).await;
+        assert_eq!(status, 400);
+        assert!(error.get("receipt").is_none());
+
+        let (status, error) = send(app(state.clone()), &body, true).await;
+        assert_eq!(status, 400);
+        assert_eq!(error["code"], "invalid_input");
+
+        let receipt: ProcessedDataResponse<IntentMessage<RejectionReceipt>> =
+            serde_json::from_value(error["receipt"].clone()).unwrap();
+        assert_eq!(receipt.response.intent, IntentScope::Rejection);
+        assert_eq!(receipt.response.data.endpoint, b"/verify_metadata");
+        assert_eq!(receipt.response.data.code, b"invalid_input");
+        let digest = Sha256::digest(body.as_bytes()).to_vec();
+        assert_eq!(receipt.response.data.request_digest, digest);
+
+        let signature = Hex::decode(&receipt.signature).unwrap();
+        assert!(state.signer.verify(&signing_bytes(&receipt.response), &signature));
+    }
+}
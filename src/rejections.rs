@@ -0,0 +1,263 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! In-memory record of refused requests, answering "what is the enclave
+//! rejecting, and why?" without grepping logs. The counterpart of the audit
+//! log, which records successes.
+//!
+//! Every 4xx or 5xx response from a protected endpoint is recorded with its
+//! path, status and error code, plus the SHA-256 of the request body. Nothing
+//! else is kept: no headers (so no API keys or credentials), no query string,
+//! no body and no error message, since messages can echo request fields. The
+//! log is a bounded ring buffer in enclave memory, lost on restart.
+
+use crate::{AppState, EnclaveError};
+use axum::body::Body;
+use axum::extract::{Query, Request, State};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use fastcrypto::encoding::{Encoding, Hex};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// Default number of rejected requests kept.
+pub const DEFAULT_REJECTION_LOG_SIZE: usize = 1_000;
+
+/// One rejected request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RejectionRecord {
+    /// Request path, e.g. `/verify_metadata`
+    pub endpoint: String,
+    pub status: u16,
+    /// `EnclaveError` code, e.g. `hash_mismatch`
+    pub code: String,
+    /// Hex encoded SHA-256 of the request body; absent when it was too large
+    /// to read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_digest: Option<String>,
+    /// Enclave time of the rejection, in milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+}
+
+/// Bounded ring buffer of the most recent rejections.
+pub struct RejectionLog {
+    capacity: usize,
+    records: Mutex<VecDeque<RejectionRecord>>,
+}
+
+impl RejectionLog {
+    /// Create a log holding up to `capacity` records; 0 disables it.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            records: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    /// Add `record`, evicting the oldest one when full.
+    pub fn record(&self, record: RejectionRecord) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut records = self.records.lock().expect("rejection log lock poisoned");
+        if records.len() == self.capacity {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+
+    /// The `limit` most recent records (all when `None`), oldest first.
+    pub fn recent(&self, limit: Option<usize>) -> Vec<RejectionRecord> {
+        let records = self.records.lock().expect("rejection log lock poisoned");
+        let skip = limit.map_or(0, |limit| records.len().saturating_sub(limit));
+        records.iter().skip(skip).cloned().collect()
+    }
+}
+
+impl Default for RejectionLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_REJECTION_LOG_SIZE)
+    }
+}
+
+/// Middleware recording every error response in `state.rejection_log`.
+pub async fn record_rejections(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !state.rejection_log.is_enabled() {
+        return next.run(request).await;
+    }
+
+    // Buffer the body to digest it, then hand it on unchanged
+    let endpoint = request.uri().path().to_string();
+    let (parts, body) = request.into_parts();
+    let Ok(bytes) = axum::body::to_bytes(body, state.limits.max_batch_body_bytes).await else {
+        let error =
+            EnclaveError::PayloadTooLarge("request body exceeds the size limit".to_string());
+        record(&state, endpoint, error.status_code(), error.code().to_string(), None);
+        return error.into_response();
+    };
+    let request_digest = Hex::encode(Sha256::digest(&bytes));
+    let response = next.run(Request::from_parts(parts, Body::from(bytes))).await;
+
+    let status = response.status();
+    if !status.is_client_error() && !status.is_server_error() {
+        return response;
+    }
+    let (parts, body) = response.into_parts();
+    let Ok(bytes) = axum::body::to_bytes(body, usize::MAX).await else {
+        return EnclaveError::GenericError("Failed to read error body".to_string()).into_response();
+    };
+    let code = serde_json::from_slice::<serde_json::Value>(&bytes)
+        .ok()
+        .and_then(|error| error["code"].as_str().map(str::to_string))
+        // Rejections from axum extractors are plain text
+        .unwrap_or_else(|| status.canonical_reason().unwrap_or("rejected").to_string());
+    record(&state, endpoint, status, code, Some(request_digest));
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+fn record(
+    state: &AppState,
+    endpoint: String,
+    status: StatusCode,
+    code: String,
+    request_digest: Option<String>,
+) {
+    state.rejection_log.record(RejectionRecord {
+        endpoint,
+        status: status.as_u16(),
+        code,
+        request_digest,
+        timestamp_ms: state.clock.now_millis().unwrap_or_default(),
+    });
+}
+
+/// Query parameters for `/rejections`.
+#[derive(Debug, Default, Deserialize)]
+pub struct RejectionsQuery {
+    /// Return only this many of the most recent records
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RejectionsResponse {
+    /// Rejections still in the log, oldest first
+    pub records: Vec<RejectionRecord>,
+}
+
+/// Endpoint listing recent rejections.
+pub async fn rejections(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<RejectionsQuery>,
+) -> Json<RejectionsResponse> {
+    Json(RejectionsResponse { records: state.rejection_log.recent(query.limit) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::API_KEY_HEADER;
+    use crate::router::build_router;
+    use crate::{DatasetVerification, DATASET_VERIFICATION_VERSION};
+    use axum::http::header::CONTENT_TYPE;
+    use fastcrypto::ed25519::Ed25519KeyPair;
+    use fastcrypto::traits::KeyPair;
+    use tower::ServiceExt;
+
+    fn stale_request_body() -> String {
+        let metadata = DatasetVerification {
+            version: DATASET_VERIFICATION_VERSION,
+            dataset_id: b"dataset-1".to_vec(),
+            name: b"data.csv".to_vec(),
+            description: b"Stale".to_vec(),
+            format: b"CSV".to_vec(),
+            size: 1024,
+            original_hash: vec![0xAB; 32],
+            walrus_blob_id: b"blob-1".to_vec(),
+            seal_policy_id: b"policy-1".to_vec(),
+            timestamp: 1,
+            uploader: format!("0x{}", "ab".repeat(32)).into_bytes(),
+        };
+        serde_json::json!({ "metadata": metadata }).to_string()
+    }
+
+    async fn send(state: &Arc<AppState>, request: axum::http::Request<Body>) -> Response {
+        build_router(state.clone()).oneshot(request).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_rejected_request_is_listed() {
+        let state = Arc::new(AppState::new(
+            Ed25519KeyPair::generate(&mut rand::thread_rng()),
+            "test-key".to_string(),
+        ));
+        let body = stale_request_body();
+        let request = axum::http::Request::post("/verify_metadata")
+            .header(CONTENT_TYPE, "application/json")
+            .header(API_KEY_HEADER, "test-key")
+            .body(Body::from(body.clone()))
+            .unwrap();
+        let response = send(&state, request).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        // The error body reaches the client intact
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let error: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(error["code"], "invalid_input");
+
+        let request = axum::http::Request::get("/rejections")
+            .header(API_KEY_HEADER, "test-key")
+            .body(Body::empty())
+            .unwrap();
+        let response = send(&state, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let listed: RejectionsResponse = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(listed.records.len(), 1);
+        let record = &listed.records[0];
+        assert_eq!(record.endpoint, "/verify_metadata");
+        assert_eq!(record.status, 400);
+        assert_eq!(record.code, "invalid_input");
+        let digest = Hex::encode(Sha256::digest(body.as_bytes()));
+        assert_eq!(record.request_digest.as_deref(), Some(digest.as_str()));
+        // Only the digest of the input is kept, never headers such as the API key
+        assert!(!String::from_utf8_lossy(&bytes).contains("test-key"));
+
+        // Unauthenticated requests are refused and recorded; successes are not
+        let request = axum::http::Request::get("/rejections").body(Body::empty()).unwrap();
+        assert_eq!(send(&state, request).await.status(), StatusCode::UNAUTHORIZED);
+        let records = state.rejection_log.recent(None);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].code, "unauthorized");
+        assert_eq!(state.rejection_log.recent(Some(1)), records[1..]);
+    }
+
+    #[test]
+    fn test_log_is_bounded() {
+        let log = RejectionLog::new(2);
+        for timestamp_ms in 1..=3 {
+            log.record(RejectionRecord {
+                endpoint: "/verify_metadata".to_string(),
+                status: 400,
+                code: "invalid_input".to_string(),
+                request_digest: None,
+                timestamp_ms,
+            });
+        }
+        let timestamps: Vec<_> = log.recent(None).iter().map(|r| r.timestamp_ms).collect();
+        assert_eq!(timestamps, [2, 3]);
+
+        let disabled = RejectionLog::new(0);
+        assert!(!disabled.is_enabled());
+    }
+}
@@ -0,0 +1,77 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::access_log::access_log;
+use axum::extract::Request;
+use axum::http::HeaderName;
+use axum::{middleware, Router};
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+use tower_http::trace::TraceLayer;
+use tracing::Span;
+
+/// Header carrying the request ID, honoured from clients and echoed in responses.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Span wrapping each request; every log line emitted while handling the
+/// request carries its `request_id`.
+pub fn make_request_span(request: &Request) -> Span {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("-");
+    tracing::info_span!(
+        "request",
+        request_id = %request_id,
+        method = %request.method(),
+        path = %request.uri().path(),
+    )
+}
+
+/// Assign a UUID request ID (unless the client sent `X-Request-Id`), run the
+/// request inside a span carrying it, log its outcome with `access_log`, and
+/// echo the ID in the response headers.
+pub fn with_request_id<S>(router: Router<S>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let header = HeaderName::from_static(REQUEST_ID_HEADER);
+    router
+        .layer(middleware::from_fn(access_log))
+        .layer(PropagateRequestIdLayer::new(header.clone()))
+        .layer(TraceLayer::new_for_http().make_span_with(make_request_span))
+        .layer(SetRequestIdLayer::new(header, MakeRequestUuid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use axum::routing::get;
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        with_request_id(Router::new().route("/health", get(|| async { "OK" })))
+    }
+
+    #[tokio::test]
+    async fn test_incoming_request_id_is_echoed() {
+        let request = Request::get("/health")
+            .header(REQUEST_ID_HEADER, "gateway-123")
+            .body(Body::empty())
+            .unwrap();
+        let response = app().oneshot(request).await.unwrap();
+
+        assert_eq!(response.headers()[REQUEST_ID_HEADER], "gateway-123");
+    }
+
+    #[tokio::test]
+    async fn test_request_id_is_generated() {
+        let request = Request::get("/health").body(Body::empty()).unwrap();
+        let response = app().oneshot(request).await.unwrap();
+
+        let request_id = response.headers()[REQUEST_ID_HEADER].to_str().unwrap();
+        assert_eq!(request_id.len(), 36, "expected a UUID, got {}", request_id);
+    }
+}
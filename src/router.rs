@@ -0,0 +1,368 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::attested::verify_metadata_attested;
+use crate::audit::audit;
+use crate::auth::require_api_key;
+use crate::batch::batch_verify_metadata;
+use crate::batch_commitment::sign_batch_commitment;
+use crate::batch_dataset::batch_verify_dataset;
+use crate::bcs_preview::bcs_preview;
+use crate::capabilities::capabilities;
+use crate::catch_panic::catch_panic;
+use crate::head::verify_head;
+use crate::idempotency::idempotency;
+use crate::key_rotation::public_key;
+use crate::merkle::sign_merkle_root;
+use crate::metrics::metrics;
+use crate::multi_scope::verify_metadata_scopes;
+use crate::preflight::preflight;
+use crate::provenance::process_data_provenance;
+use crate::rate_limit::rate_limit;
+use crate::receipt::rejection_receipt;
+use crate::rejections::{record_rejections, rejections};
+use crate::selftest::selftest;
+use crate::sign_intent::sign_intent;
+use crate::timestamp::timestamp;
+use crate::{
+    check_hash, get_attestation, health, health_check, map_payload_too_large, process_data,
+    process_multipart_data, refuse_while_draining, resign_metadata, sign_digest,
+    validate_metadata_only, verify_and_fetch_walrus, verify_dataset, verify_metadata,
+    verify_walrus_blob, version, AppState,
+};
+use axum::extract::DefaultBodyLimit;
+use axum::middleware;
+use axum::routing::{get, post};
+use axum::Router;
+use std::sync::Arc;
+use tower_http::compression::predicate::{Predicate, SizeAbove};
+use tower_http::compression::{CompressionLayer, DefaultPredicate};
+
+/// Responses smaller than this are sent uncompressed, where gzip would cost
+/// more than it saves.
+pub const MIN_COMPRESSED_RESPONSE_BYTES: u16 = 1024;
+
+/// Gzip for responses of at least `MIN_COMPRESSED_RESPONSE_BYTES`, when the
+/// client sends `Accept-Encoding: gzip`.
+fn response_compression() -> CompressionLayer<impl Predicate> {
+    CompressionLayer::new()
+        .compress_when(DefaultPredicate::new().and(SizeAbove::new(MIN_COMPRESSED_RESPONSE_BYTES)))
+}
+
+/// Layers every signing endpoint shares, innermost first.
+fn protect(router: Router<Arc<AppState>>, state: &Arc<AppState>) -> Router<Arc<AppState>> {
+    router
+        // Refuse new work while draining; stored idempotent responses are still replayed
+        .route_layer(middleware::from_fn_with_state(state.clone(), refuse_while_draining))
+        // Replay the stored response for a repeated Idempotency-Key instead of re-signing
+        .route_layer(middleware::from_fn_with_state(state.clone(), idempotency))
+        // Signed receipt for rejected requests sent with X-Rejection-Receipt: true
+        .route_layer(middleware::from_fn_with_state(state.clone(), rejection_receipt))
+        // Throttle after authentication, so only valid API keys get their own bucket
+        .route_layer(middleware::from_fn_with_state(state.clone(), rate_limit))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_api_key))
+        // Outermost, so refusals by the layers above are listed by /rejections too
+        .route_layer(middleware::from_fn_with_state(state.clone(), record_rejections))
+}
+
+/// All enclave routes with their authentication, throttling and body limit
+/// layers. CORS and request IDs depend on the deployment and are added by the
+/// caller.
+pub fn build_router(state: Arc<AppState>) -> Router {
+    let max_batch_body_bytes = state.limits.max_batch_body_bytes;
+    // Signing endpoints require the API key (when enforcement is enabled)
+    let protected = Router::new()
+        .route("/process_data", post(process_data))        // Legacy endpoint (deprecated)
+        .route("/process_data_provenance", post(process_data_provenance)) // Also signs Content-Type and final URL
+        .route("/process_multipart_data", post(process_multipart_data)) // One hash over several URLs
+        .route("/verify_metadata", post(verify_metadata))  // V3 Architecture endpoint
+        .route("/verify_metadata_attested", post(verify_metadata_attested)) // Metadata bound to the attestation
+        .route("/verify_metadata_scopes", post(verify_metadata_scopes)) // One signature per requested scope
+        .route("/verify_walrus_blob", post(verify_walrus_blob)) // Hash blob content fetched from Walrus
+        .route("/verify_and_fetch_walrus", post(verify_and_fetch_walrus)) // Metadata checked against its Walrus blob
+        .route("/verify_dataset", post(verify_dataset))    // Sign only if fetched size and hash match
+        .route("/verify_head", post(verify_head))          // Sign the Content-Length of a URL, unhashed
+        .route("/sign_digest", post(sign_digest))          // Sign metadata with a precomputed hash
+        .route("/check_hash", post(check_hash))            // Compare a dataset to a hash, never signs
+        .route("/preflight", post(preflight))              // DNS, TCP, TLS and HEAD checks of a URL
+        .route("/resign_metadata", post(resign_metadata))  // Fresh timestamp for our own past signature
+        .route("/sign_intent", post(sign_intent))          // BCS payload of a registered type
+        .route("/timestamp", post(timestamp))              // Enclave time signed over a client digest
+        .route("/audit", get(audit))                       // Recent signing operations for a dataset_id
+        .route("/rejections", get(rejections));            // Recent refused requests and their error codes
+    let protected = protect(protected, &state);
+
+    // Batch endpoints take the larger body limit and may gzip their responses
+    let batches = Router::new()
+        // One signed commitment over many datasets, under the larger batch body limit
+        .route(
+            "/sign_merkle_root",
+            post(sign_merkle_root).layer(DefaultBodyLimit::max(max_batch_body_bytes)),
+        )
+        // Per-item signatures from one key, for aggregate verification
+        .route(
+            "/batch_verify_metadata",
+            post(batch_verify_metadata).layer(DefaultBodyLimit::max(max_batch_body_bytes)),
+        )
+        // One signature over the BCS of a whole batch
+        .route(
+            "/sign_batch_commitment",
+            post(sign_batch_commitment).layer(DefaultBodyLimit::max(max_batch_body_bytes)),
+        )
+        // Fetch and check many datasets concurrently, one result per item
+        .route(
+            "/batch_verify_dataset",
+            post(batch_verify_dataset).layer(DefaultBodyLimit::max(max_batch_body_bytes)),
+        );
+    // Compress outside the idempotency layer, so it stores and replays plain
+    // bytes and each replay is negotiated against the retrying client
+    let batches = protect(batches, &state).route_layer(response_compression());
+
+    let app = Router::new()
+        .merge(protected)
+        .merge(batches)
+        .route("/get_attestation", get(get_attestation))   // NSM attestation for on-chain registration
+        .route("/health_check", get(health_check))         // Readiness probe with dependency status
+        .route("/validate_metadata", post(validate_metadata_only)) // Dry run of verify_metadata, never signs
+        .route("/bcs_preview", post(bcs_preview))          // Unsigned BCS bytes for debugging
+        .route("/health", get(health))                     // Liveness probe
+        .route("/selftest", get(selftest))                 // Sign and verify a fixed payload
+        .route("/public_key", get(public_key))             // Current and recently rotated-out keys
+        .route("/metrics", get(metrics))                   // Prometheus metrics
+        .route("/version", get(version))                   // Crate version, git commit and PCRs
+        .route("/capabilities", get(capabilities));        // Accepted algorithms, schemas and limits
+    // JSON Schema of the request/response types, with the `schema` feature
+    #[cfg(feature = "schema")]
+    let app = app.route("/schema", get(crate::schema::schema).layer(response_compression()));
+    app.layer(DefaultBodyLimit::max(state.limits.max_body_bytes))
+        .layer(middleware::map_response(map_payload_too_large))
+        // Outermost, so a panic anywhere above still gets a JSON 500
+        .layer(middleware::from_fn(catch_panic))
+        .with_state(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::API_KEY_HEADER;
+    use crate::common::{signing_bytes, IntentMessage, IntentScope, ProcessedDataResponse};
+    use crate::{
+        current_timestamp_ms, DatasetVerification, DATASET_VERIFICATION_VERSION, SUI_ADDRESS_LENGTH,
+    };
+    use axum::body::Body;
+    use axum::http::header::CONTENT_TYPE;
+    use axum::http::{Request, StatusCode};
+    use fastcrypto::ed25519::Ed25519KeyPair;
+    use fastcrypto::encoding::{Encoding, Hex};
+    use fastcrypto::traits::KeyPair;
+    use sha2::{Digest, Sha256};
+    use tower::ServiceExt;
+
+    fn test_state() -> Arc<AppState> {
+        let mut state = AppState::new(
+            Ed25519KeyPair::generate(&mut rand::thread_rng()),
+            "test-key".to_string(),
+        );
+        state.url_policy.allowlist = vec!["127.0.0.1".to_string()];
+        Arc::new(state)
+    }
+
+    fn metadata() -> DatasetVerification {
+        DatasetVerification {
+            version: DATASET_VERIFICATION_VERSION,
+            dataset_id: b"dataset-1".to_vec(),
+            name: b"data.csv".to_vec(),
+            description: b"Test dataset".to_vec(),
+            format: b"CSV".to_vec(),
+            size: 1024,
+            original_hash: vec![0xAB; 32],
+            walrus_blob_id: b"blob-1".to_vec(),
+            seal_policy_id: b"policy-1".to_vec(),
+            timestamp: current_timestamp_ms().unwrap(),
+            uploader: format!("0x{}", "ab".repeat(SUI_ADDRESS_LENGTH)).into_bytes(),
+        }
+    }
+
+    /// POST `body` as JSON through the full router and return the status and JSON body.
+    async fn post_json(
+        state: Arc<AppState>,
+        path: &str,
+        api_key: Option<&str>,
+        body: serde_json::Value,
+    ) -> (StatusCode, serde_json::Value) {
+        let mut request = Request::post(path).header(CONTENT_TYPE, "application/json");
+        if let Some(key) = api_key {
+            request = request.header(API_KEY_HEADER, key);
+        }
+        let response = build_router(state)
+            .oneshot(request.body(Body::from(body.to_string())).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    /// Serve `body` once on a local port and return its URL.
+    async fn serve_dataset_once(body: &'static [u8]) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/data.csv", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let _ = socket.read(&mut [0u8; 4096]).await;
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            );
+            socket.write_all(head.as_bytes()).await.unwrap();
+            socket.write_all(body).await.unwrap();
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_verify_metadata_over_http() {
+        let state = test_state();
+        let body = serde_json::json!({ "metadata": metadata() });
+
+        let (status, json) =
+            post_json(state.clone(), "/verify_metadata", Some("test-key"), body).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["response"]["intent"], 1);
+        assert!(json["response"]["timestamp_ms"].is_u64());
+        assert!(json.get("signed_bytes").is_none());
+
+        let signed: ProcessedDataResponse<IntentMessage<DatasetVerification>> =
+            serde_json::from_value(json).unwrap();
+        assert_eq!(signed.response.data.dataset_id, b"dataset-1");
+        let signature = Hex::decode(&signed.signature).unwrap();
+        assert!(state.signer.verify(&signing_bytes(&signed.response), &signature));
+    }
+
+    #[tokio::test]
+    async fn test_draining_refuses_every_protected_endpoint() {
+        let state = test_state();
+        state.begin_drain();
+
+        for path in [
+            "/verify_metadata",
+            "/process_multipart_data",
+            "/verify_walrus_blob",
+            "/verify_and_fetch_walrus",
+            "/verify_dataset",
+            "/sign_digest",
+            "/resign_metadata",
+            "/sign_merkle_root",
+            "/batch_verify_metadata",
+        ] {
+            let body = serde_json::json!({ "metadata": metadata() });
+            let (status, json) = post_json(state.clone(), path, Some("test-key"), body).await;
+            assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE, "{}", path);
+            assert_eq!(json["code"], "draining", "{}", path);
+        }
+
+        // Probes stay up so the load balancer can see the drain
+        let request = Request::get("/health").body(Body::empty()).unwrap();
+        let response = build_router(state).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_verify_metadata_rejections_over_http() {
+        let state = test_state();
+        let body = serde_json::json!({ "metadata": metadata() });
+
+        let (status, json) = post_json(state.clone(), "/verify_metadata", None, body.clone()).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(json["code"], "unauthorized");
+
+        let (status, _) = post_json(state.clone(), "/verify_metadata", Some("wrong"), body).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let stale = DatasetVerification { timestamp: 1, ..metadata() };
+        let stale = serde_json::json!({ "metadata": stale });
+        let (status, json) = post_json(state, "/verify_metadata", Some("test-key"), stale).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(json["code"], "invalid_input");
+    }
+
+    #[tokio::test]
+    async fn test_process_data_over_http() {
+        const BODY: &[u8] = b"id,label\n1,dog\n";
+        let state = test_state();
+        let dataset_url = serve_dataset_once(BODY).await;
+        let body = serde_json::json!({
+            "payload": {
+                "dataset_url": dataset_url,
+                "expected_hash": Hex::encode(Sha256::digest(BODY)),
+                "format": "CSV",
+                "schema_version": "v1.0",
+            }
+        });
+
+        let (status, json) =
+            post_json(state.clone(), "/process_data", Some("test-key"), body).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["response"]["intent"], IntentScope::ProcessData as u8);
+
+        let signed: ProcessedDataResponse<IntentMessage<DatasetVerification>> =
+            serde_json::from_value(json).unwrap();
+        assert_eq!(signed.response.data.size, BODY.len() as u64);
+        assert_eq!(signed.response.data.original_hash, Sha256::digest(BODY).to_vec());
+        assert_eq!(signed.response.data.name, dataset_url.into_bytes());
+        let signature = Hex::decode(&signed.signature).unwrap();
+        assert!(state.signer.verify(&signing_bytes(&signed.response), &signature));
+
+        // A mismatching expected hash is refused with 422 and nothing signed
+        let body = serde_json::json!({
+            "payload": {
+                "dataset_url": serve_dataset_once(BODY).await,
+                "expected_hash": "00".repeat(32),
+                "format": "CSV",
+                "schema_version": "v1.0",
+            }
+        });
+        let (status, json) = post_json(state, "/process_data", Some("test-key"), body).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(json["code"], "hash_mismatch");
+        assert!(json.get("signature").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_large_batch_response_is_gzipped() {
+        use axum::http::header::{ACCEPT_ENCODING, CONTENT_ENCODING};
+        use std::io::Read;
+
+        let post = |body: serde_json::Value| {
+            Request::post("/batch_verify_metadata")
+                .header(CONTENT_TYPE, "application/json")
+                .header(ACCEPT_ENCODING, "gzip")
+                .header(API_KEY_HEADER, "test-key")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+        let app = build_router(test_state());
+
+        // Ten signed items are far above the compression threshold
+        let items: Vec<_> = (0..10).map(|_| metadata()).collect();
+        let response = app
+            .clone()
+            .oneshot(post(serde_json::json!({ "items": items })))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_ENCODING], "gzip");
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let mut json = String::new();
+        flate2::read::GzDecoder::new(&bytes[..]).read_to_string(&mut json).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(json["signatures"].as_array().unwrap().len(), 10);
+
+        // A short error body is sent as is
+        let response = app.oneshot(post(serde_json::json!({ "items": [] }))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(response.headers().get(CONTENT_ENCODING).is_none());
+    }
+}
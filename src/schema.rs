@@ -0,0 +1,50 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! JSON Schema of the public request and response types, generated from
+//! their `schemars` derives. Only built with the `schema` feature.
+
+use crate::common::{IntentMessage, ProcessDataRequest, ProcessedDataResponse};
+use crate::{DatasetRequest, DatasetVerification, MetadataVerificationRequest};
+use axum::Json;
+use schemars::schema::RootSchema;
+use schemars::schema_for;
+use std::collections::BTreeMap;
+
+/// Schemas keyed by type name.
+pub fn schemas() -> BTreeMap<&'static str, RootSchema> {
+    BTreeMap::from([
+        ("DatasetVerification", schema_for!(DatasetVerification)),
+        ("MetadataVerificationRequest", schema_for!(MetadataVerificationRequest)),
+        ("ProcessDataRequest", schema_for!(ProcessDataRequest<DatasetRequest>)),
+        (
+            "SignedDatasetVerification",
+            schema_for!(ProcessedDataResponse<IntentMessage<DatasetVerification>>),
+        ),
+    ])
+}
+
+/// Endpoint returning the JSON Schema of every public request/response type.
+pub async fn schema() -> Json<BTreeMap<&'static str, RootSchema>> {
+    Json(schemas())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schemas_describe_request_fields() {
+        let schemas = serde_json::to_value(schemas()).unwrap();
+
+        let metadata = &schemas["MetadataVerificationRequest"];
+        assert!(metadata["properties"]["metadata"].is_object());
+        assert_eq!(metadata["required"], serde_json::json!(["metadata"]));
+        assert!(metadata["definitions"]["DatasetVerification"]["properties"]["original_hash"]
+            .is_object());
+
+        // IntentScope is serialized as an integer
+        let signed = &schemas["SignedDatasetVerification"];
+        assert_eq!(signed["definitions"]["IntentScope"]["type"], "integer");
+    }
+}
@@ -0,0 +1,149 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Self-test of the signing path: BCS encoding, signing and verification.
+//!
+//! `/selftest` encodes a fixed `IntentMessage<DatasetVerification>`, checks
+//! the bytes against `SELFTEST_BCS`, signs them and verifies the signature
+//! with the current public key. The signed bytes start with
+//! `SELFTEST_DOMAIN`, which no intent scope does, so the signature can never
+//! be passed off as a signed dataset.
+
+use crate::common::{signing_bytes, IntentMessage, IntentScope};
+use crate::{AppState, DatasetVerification, DATASET_VERIFICATION_VERSION};
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+use fastcrypto::encoding::{Encoding, Hex};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Prefix of the self-test's signed bytes, keeping them apart from every
+/// `IntentMessage`.
+pub const SELFTEST_DOMAIN: &[u8] = b"sealtrust-selftest";
+
+/// Hex BCS of `selftest_message()`. Changes only with the
+/// `DatasetVerification` layout, and must be updated along with it.
+pub const SELFTEST_BCS: &str = concat!(
+    "00", "0000000000000000",
+    "01", "0873656c6674657374", "0873656c6674657374", "00", "03435356",
+    "0000000000000000", "00", "00", "00", "0000000000000000", "00",
+);
+
+/// The fixed payload: every field constant, so the bytes are reproducible.
+fn selftest_message() -> IntentMessage<DatasetVerification> {
+    let verification = DatasetVerification {
+        version: DATASET_VERIFICATION_VERSION,
+        dataset_id: b"selftest".to_vec(),
+        name: b"selftest".to_vec(),
+        description: vec![],
+        format: b"CSV".to_vec(),
+        size: 0,
+        original_hash: vec![],
+        walrus_blob_id: vec![],
+        seal_policy_id: vec![],
+        timestamp: 0,
+        uploader: vec![],
+    };
+    IntentMessage::new(verification, 0, IntentScope::ProcessData)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SelfTestResponse {
+    pub ok: bool,
+    /// Which step failed, when one did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Endpoint signing and verifying the fixed payload. Responds 503 with the
+/// failing step when the signing path is broken.
+pub async fn selftest(
+    State(state): State<Arc<AppState>>,
+) -> (StatusCode, Json<SelfTestResponse>) {
+    match run_selftest(&state) {
+        Ok(()) => (StatusCode::OK, Json(SelfTestResponse { ok: true, error: None })),
+        Err(error) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(SelfTestResponse { ok: false, error: Some(error) }),
+        ),
+    }
+}
+
+fn run_selftest(state: &AppState) -> Result<(), String> {
+    let encoded = signing_bytes(&selftest_message());
+    if Hex::encode(&encoded) != SELFTEST_BCS {
+        return Err(format!("BCS encoding changed: {}", Hex::encode(&encoded)));
+    }
+
+    let signer = state.signer.current();
+    if signer.public_key_bytes().is_empty() {
+        return Err("no public key loaded".to_string());
+    }
+    let message = [SELFTEST_DOMAIN, &encoded].concat();
+    let signature = signer.sign(&message);
+    if !signer.verify(&message, &signature) {
+        return Err(format!(
+            "{} signature does not verify against the public key",
+            signer.scheme()
+        ));
+    }
+    // A verifier accepting anything would pass the check above
+    if signer.verify(SELFTEST_DOMAIN, &signature) {
+        return Err(format!("{} signature verifies for another message", signer.scheme()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signer::Signer;
+    use fastcrypto::ed25519::Ed25519KeyPair;
+    use fastcrypto::traits::KeyPair;
+
+    /// A signer whose signatures never verify.
+    struct BrokenSigner;
+
+    impl Signer for BrokenSigner {
+        fn scheme(&self) -> &'static str {
+            "broken"
+        }
+
+        fn sign(&self, _msg: &[u8]) -> Vec<u8> {
+            vec![0; 64]
+        }
+
+        fn public_key_bytes(&self) -> Vec<u8> {
+            vec![1; 32]
+        }
+
+        fn verify(&self, _msg: &[u8], _signature: &[u8]) -> bool {
+            false
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fresh_state_passes_selftest() {
+        let state = Arc::new(AppState::new(
+            Ed25519KeyPair::generate(&mut rand::thread_rng()),
+            "test-key".to_string(),
+        ));
+        let (status, Json(response)) = selftest(State(state)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(response.ok);
+        assert!(response.error.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_broken_signer_fails_selftest() {
+        let state = Arc::new(AppState::new(BrokenSigner, "test-key".to_string()));
+        let (status, Json(response)) = selftest(State(state)).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(!response.ok);
+        assert_eq!(
+            response.error.as_deref(),
+            Some("broken signature does not verify against the public key")
+        );
+    }
+}
@@ -0,0 +1,344 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{anyhow, Context};
+use axum::extract::ConnectInfo;
+use axum::http::Request;
+use axum::Router;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto::Builder;
+use hyper_util::service::TowerToHyperService;
+use std::fs::File;
+use std::io::{BufReader, ErrorKind};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::rustls;
+use tokio_rustls::TlsAcceptor;
+use tower::ServiceExt;
+use tracing::{info, warn};
+
+/// How long the server keeps running after a shutdown signal, reporting
+/// "draining" from `/health_check` and finishing in-flight requests.
+pub const DEFAULT_SHUTDOWN_DRAIN: Duration = Duration::from_secs(15);
+
+/// Time a client has to complete the TLS handshake before its connection is dropped.
+pub const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Pause before accepting again after an accept error such as EMFILE.
+pub const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Connection-level tuning for the HTTP server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerConfig {
+    /// Accept HTTP/2 (prior knowledge, h2c) alongside HTTP/1.1
+    pub http2: bool,
+    /// Interval between HTTP/2 keep-alive pings; `None` disables pings
+    pub keep_alive_interval: Option<Duration>,
+    /// Close an HTTP/2 connection whose keep-alive ping is not acknowledged in time
+    pub keep_alive_timeout: Duration,
+    /// Maximum concurrent streams per HTTP/2 connection
+    pub max_concurrent_streams: u32,
+    /// Keep HTTP/1.1 connections open between requests
+    pub http1_keep_alive: bool,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            http2: true,
+            keep_alive_interval: Some(Duration::from_secs(30)),
+            keep_alive_timeout: Duration::from_secs(20),
+            max_concurrent_streams: 256,
+            http1_keep_alive: true,
+        }
+    }
+}
+
+impl ServerConfig {
+    fn builder(&self) -> Builder<TokioExecutor> {
+        let mut builder = Builder::new(TokioExecutor::new());
+        builder.http1().keep_alive(self.http1_keep_alive);
+        builder
+            .http2()
+            .keep_alive_interval(self.keep_alive_interval)
+            .keep_alive_timeout(self.keep_alive_timeout)
+            .max_concurrent_streams(self.max_concurrent_streams);
+        if !self.http2 {
+            builder = builder.http1_only();
+        }
+        builder
+    }
+}
+
+/// Serve `app` on `listener` with the given connection settings. Runs until
+/// the task is dropped; accept errors are logged and retried, see `accept`.
+/// The peer address is available to handlers and middleware as
+/// `ConnectInfo<SocketAddr>`.
+pub async fn serve(listener: TcpListener, app: Router, config: ServerConfig) -> std::io::Result<()> {
+    info!("Server connection settings: {:?}", config);
+    let builder = Arc::new(config.builder());
+
+    loop {
+        let (stream, peer) = accept(&listener).await;
+        let (builder, app) = (builder.clone(), app.clone());
+        tokio::spawn(async move { serve_connection(&builder, app, stream, peer).await });
+    }
+}
+
+/// Like `serve`, terminating TLS with `acceptor` first. Handshakes run on the
+/// connection's own task, so a slow client cannot hold up `accept`.
+pub async fn serve_tls(
+    listener: TcpListener,
+    app: Router,
+    config: ServerConfig,
+    acceptor: TlsAcceptor,
+) -> std::io::Result<()> {
+    info!("Server connection settings: {:?}, TLS enabled", config);
+    let builder = Arc::new(config.builder());
+
+    loop {
+        let (stream, peer) = accept(&listener).await;
+        let (builder, app, acceptor) = (builder.clone(), app.clone(), acceptor.clone());
+        tokio::spawn(async move {
+            let handshake = tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, acceptor.accept(stream));
+            match handshake.await {
+                Ok(Ok(stream)) => serve_connection(&builder, app, stream, peer).await,
+                Ok(Err(e)) => warn!("TLS handshake with {} failed: {}", peer, e),
+                Err(_) => warn!("TLS handshake with {} timed out", peer),
+            }
+        });
+    }
+}
+
+/// Accept the next connection. Like `axum::serve`, errors are not returned:
+/// one about a single connection is skipped, and any other (such as EMFILE
+/// or ENFILE, which clear once connections close) is logged and retried after
+/// `ACCEPT_ERROR_BACKOFF`, so a transient failure cannot stop the server.
+async fn accept(listener: &TcpListener) -> (TcpStream, SocketAddr) {
+    loop {
+        match listener.accept().await {
+            Ok(accepted) => return accepted,
+            Err(e) if is_connection_error(&e) => {}
+            Err(e) => {
+                warn!("Failed to accept connection: {}, retrying in {:?}", e, ACCEPT_ERROR_BACKOFF);
+                tokio::time::sleep(ACCEPT_ERROR_BACKOFF).await;
+            }
+        }
+    }
+}
+
+fn is_connection_error(e: &std::io::Error) -> bool {
+    matches!(
+        e.kind(),
+        ErrorKind::ConnectionRefused | ErrorKind::ConnectionAborted | ErrorKind::ConnectionReset
+    )
+}
+
+async fn serve_connection<S>(
+    builder: &Builder<TokioExecutor>,
+    app: Router,
+    stream: S,
+    peer: SocketAddr,
+) where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let service = TowerToHyperService::new(app.map_request(with_peer(peer)));
+    if let Err(e) = builder.serve_connection(TokioIo::new(stream), service).await {
+        warn!("Connection from {} closed with error: {}", peer, e);
+    }
+}
+
+/// Build a TLS acceptor from a PEM certificate chain (leaf first) and a PEM
+/// private key (PKCS#8, PKCS#1 or SEC1). Fails when either file is unreadable
+/// or holds no certificate or key, or when rustls rejects the pair.
+/// With `http2`, ALPN offers h2 ahead of HTTP/1.1.
+pub fn load_tls_acceptor(
+    cert_path: &Path,
+    key_path: &Path,
+    http2: bool,
+) -> anyhow::Result<TlsAcceptor> {
+    let open = |path: &Path| {
+        File::open(path)
+            .map(BufReader::new)
+            .with_context(|| format!("failed to open {}", path.display()))
+    };
+    let certs = rustls_pemfile::certs(&mut open(cert_path)?)
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("invalid certificate PEM in {}", cert_path.display()))?;
+    if certs.is_empty() {
+        return Err(anyhow!("no certificate found in {}", cert_path.display()));
+    }
+    let key = rustls_pemfile::private_key(&mut open(key_path)?)
+        .with_context(|| format!("invalid private key PEM in {}", key_path.display()))?
+        .ok_or_else(|| anyhow!("no private key found in {}", key_path.display()))?;
+
+    let mut config = rustls::ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .context("certificate and private key are not a usable pair")?;
+    config.alpn_protocols = if http2 {
+        vec![b"h2".to_vec(), b"http/1.1".to_vec()]
+    } else {
+        vec![b"http/1.1".to_vec()]
+    };
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// Resolve on Ctrl-C or, on Unix, SIGTERM.
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                warn!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+/// Attach the connection's peer address to each request, as axum's
+/// `into_make_service_with_connect_info` would.
+fn with_peer<B>(peer: SocketAddr) -> impl Fn(Request<B>) -> Request<B> + Clone {
+    move |mut request| {
+        request.extensions_mut().insert(ConnectInfo(peer));
+        request
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use std::path::PathBuf;
+
+    async fn spawn_server(config: ServerConfig) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new().route("/health", get(|| async { "OK" }));
+        tokio::spawn(serve(listener, app, config));
+        format!("http://{}/health", addr)
+    }
+
+    #[test]
+    fn test_accept_errors_about_one_connection_are_skipped() {
+        assert!(is_connection_error(&ErrorKind::ConnectionAborted.into()));
+        assert!(is_connection_error(&ErrorKind::ConnectionReset.into()));
+        // EMFILE is retried after a backoff instead
+        assert!(!is_connection_error(&std::io::Error::from_raw_os_error(24)));
+    }
+
+    #[tokio::test]
+    async fn test_serves_http1_and_http2() {
+        let url = spawn_server(ServerConfig::default()).await;
+
+        let response = reqwest::get(&url).await.unwrap();
+        assert_eq!(response.version(), reqwest::Version::HTTP_11);
+
+        let client = reqwest::Client::builder().http2_prior_knowledge().build().unwrap();
+        let response = client.get(&url).send().await.unwrap();
+        assert_eq!(response.version(), reqwest::Version::HTTP_2);
+        assert_eq!(response.text().await.unwrap(), "OK");
+    }
+
+    #[tokio::test]
+    async fn test_peer_address_is_exposed() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new().route(
+            "/peer",
+            get(|ConnectInfo(peer): ConnectInfo<SocketAddr>| async move { peer.ip().to_string() }),
+        );
+        tokio::spawn(serve(listener, app, ServerConfig::default()));
+
+        let body = reqwest::get(format!("http://{}/peer", addr)).await.unwrap().text().await.unwrap();
+        assert_eq!(body, "127.0.0.1");
+    }
+
+    /// Write a self-signed certificate for `localhost` and its key to temporary
+    /// files, returning their paths and the certificate PEM.
+    fn self_signed(name: &str) -> (PathBuf, PathBuf, String) {
+        let rcgen::CertifiedKey { cert, key_pair } =
+            rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let prefix = format!("sealtrust-tls-{}-{}", name, std::process::id());
+        let cert_path = std::env::temp_dir().join(format!("{}-cert.pem", prefix));
+        let key_path = std::env::temp_dir().join(format!("{}-key.pem", prefix));
+        std::fs::write(&cert_path, cert.pem()).unwrap();
+        std::fs::write(&key_path, key_pair.serialize_pem()).unwrap();
+        (cert_path, key_path, cert.pem())
+    }
+
+    #[tokio::test]
+    async fn test_serves_https_with_configured_certificate() {
+        let (cert_path, key_path, cert_pem) = self_signed("serve");
+        let acceptor = load_tls_acceptor(&cert_path, &key_path, true).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new().route("/health", get(|| async { "OK" }));
+        tokio::spawn(serve_tls(listener, app, ServerConfig::default(), acceptor));
+
+        // The handshake succeeds for a client trusting the configured certificate
+        let client = reqwest::Client::builder()
+            .add_root_certificate(reqwest::Certificate::from_pem(cert_pem.as_bytes()).unwrap())
+            .resolve("localhost", addr)
+            .build()
+            .unwrap();
+        let url = format!("https://localhost:{}/health", addr.port());
+        let response = client.get(&url).send().await.unwrap();
+        assert_eq!(response.text().await.unwrap(), "OK");
+
+        // ...and fails for plain HTTP and for clients that do not trust it
+        assert!(reqwest::get(format!("http://{}/health", addr)).await.is_err());
+        let untrusting = reqwest::Client::builder().resolve("localhost", addr).build().unwrap();
+        assert!(untrusting.get(&url).send().await.is_err());
+
+        std::fs::remove_file(&cert_path).unwrap();
+        std::fs::remove_file(&key_path).unwrap();
+    }
+
+    #[test]
+    fn test_unusable_tls_files_fail_to_load() {
+        let (cert_path, key_path, _) = self_signed("invalid");
+        let missing = std::env::temp_dir().join("sealtrust-tls-missing.pem");
+
+        let err = load_tls_acceptor(&missing, &key_path, true).unwrap_err();
+        assert!(err.to_string().starts_with("failed to open"));
+        let err = load_tls_acceptor(&key_path, &key_path, true).unwrap_err();
+        assert!(err.to_string().starts_with("no certificate found"));
+        let err = load_tls_acceptor(&cert_path, &cert_path, true).unwrap_err();
+        assert!(err.to_string().starts_with("no private key found"));
+
+        std::fs::remove_file(&cert_path).unwrap();
+        std::fs::remove_file(&key_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_http2_can_be_disabled() {
+        let config = ServerConfig { http2: false, ..ServerConfig::default() };
+        let url = spawn_server(config).await;
+
+        let client = reqwest::Client::builder().http2_prior_knowledge().build().unwrap();
+        assert!(client.get(&url).send().await.is_err());
+    }
+}
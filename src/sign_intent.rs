@@ -0,0 +1,282 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Signing of payload types other than `DatasetVerification`.
+//!
+//! A client sends a type tag, an intent scope and the BCS bytes of the inner
+//! payload. The enclave only signs types registered in `AppState::payload_types`:
+//! the bytes must decode as exactly that Rust type (which mirrors the Move
+//! struct) within `bcs_guard` bounds, and pass the type's own checks. The
+//! signed message is the BCS of `IntentMessage<T>`, as for every other endpoint.
+//!
+//! No type is registered yet, so every request is refused as an unknown type
+//! tag. Registering one in `AppState::new` first needs a dedicated
+//! `IntentScope` variant, mirrored in the Move contract: a scope shared with
+//! another type, or with a built-in endpoint, would let a signature over one
+//! be presented as the other. `DatasetVerification` is deliberately never
+//! registered, so its policy checks cannot be bypassed through this endpoint.
+
+use crate::bcs_guard::decode_bcs;
+use crate::common::{IntentScope, IntentScopeSelector};
+use crate::text::decode_hex;
+use crate::{AppState, EnclaveError};
+use axum::extract::State;
+use axum::Json;
+use fastcrypto::encoding::{Encoding, Hex};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tracing::info;
+
+type PayloadCheck = Box<dyn Fn(&AppState, &[u8]) -> Result<(), EnclaveError> + Send + Sync>;
+
+/// A payload type the enclave agrees to sign.
+struct PayloadType {
+    scopes: Vec<IntentScope>,
+    check: PayloadCheck,
+}
+
+/// Payload types accepted by `/sign_intent`, by type tag.
+#[derive(Default)]
+pub struct PayloadRegistry {
+    types: BTreeMap<String, PayloadType>,
+}
+
+impl std::fmt::Debug for PayloadRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(self.types.iter().map(|(tag, payload)| (tag, &payload.scopes)))
+            .finish()
+    }
+}
+
+impl PayloadRegistry {
+    /// Accept BCS payloads of `T` under `type_tag`, signed under one of
+    /// `scopes`. `check` runs on the decoded value before signing. Fails if
+    /// the tag is taken, or a scope is signed by a built-in endpoint or
+    /// already belongs to another type.
+    pub fn register<T, F>(
+        &mut self,
+        type_tag: &str,
+        scopes: &[IntentScope],
+        check: F,
+    ) -> anyhow::Result<()>
+    where
+        T: Serialize + DeserializeOwned,
+        F: Fn(&AppState, &T) -> Result<(), EnclaveError> + Send + Sync + 'static,
+    {
+        if scopes.is_empty() {
+            anyhow::bail!("payload type {} needs at least one intent scope", type_tag);
+        }
+        if self.types.contains_key(type_tag) {
+            anyhow::bail!("payload type {} is already registered", type_tag);
+        }
+        if let Some(scope) = scopes.iter().find(|scope| scope.is_built_in()) {
+            anyhow::bail!("intent scope {:?} is signed by a built-in endpoint", scope);
+        }
+        for (other, payload) in &self.types {
+            if let Some(scope) = scopes.iter().find(|scope| payload.scopes.contains(scope)) {
+                anyhow::bail!("intent scope {:?} is already used by {}", scope, other);
+            }
+        }
+
+        let tag = type_tag.to_string();
+        let check = move |state: &AppState, bytes: &[u8]| -> Result<(), EnclaveError> {
+            let what = format!("{} payload", tag);
+            let value: T = decode_bcs(&what, bytes, state.limits.max_bcs_item_bytes)?;
+            check(state, &value)
+        };
+        self.types.insert(
+            type_tag.to_string(),
+            PayloadType { scopes: scopes.to_vec(), check: Box::new(check) },
+        );
+        Ok(())
+    }
+
+    /// Registered type tags, in sorted order.
+    pub fn type_tags(&self) -> impl Iterator<Item = &str> {
+        self.types.keys().map(String::as_str)
+    }
+}
+
+/// Request to sign a registered payload type.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SignIntentRequest {
+    /// Registered type tag, e.g. the Move type name
+    pub type_tag: String,
+    /// Scope to sign under, by name or discriminant
+    pub intent_scope: IntentScopeSelector,
+    /// Hex encoded BCS bytes of the inner payload
+    pub payload: String,
+}
+
+/// Signature over `bcs(IntentMessage<T>)`, with the request echoed back.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SignIntentResponse {
+    pub type_tag: String,
+    pub intent: IntentScope,
+    pub timestamp_ms: u64,
+    /// Hex encoded BCS bytes of the inner payload, as signed
+    pub payload: String,
+    /// Hex of the exact bytes that were signed
+    pub signed_bytes: String,
+    pub signature: String,
+}
+
+/// Bytes of `IntentMessage<T>` for a payload already BCS-encoded: BCS
+/// serializes a struct as the concatenation of its fields.
+pub fn intent_signing_bytes(intent: IntentScope, timestamp_ms: u64, payload: &[u8]) -> Vec<u8> {
+    let mut bytes = bcs::to_bytes(&(intent, timestamp_ms)).expect("should not fail");
+    bytes.extend_from_slice(payload);
+    bytes
+}
+
+/// Endpoint signing a BCS payload of a registered type under a chosen scope.
+pub async fn sign_intent(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<SignIntentRequest>,
+) -> Result<Json<SignIntentResponse>, EnclaveError> {
+    let result = sign_registered_payload(&state, request);
+    state.metrics.record("sign_intent", &result);
+    result
+}
+
+fn sign_registered_payload(
+    state: &AppState,
+    request: SignIntentRequest,
+) -> Result<Json<SignIntentResponse>, EnclaveError> {
+    let payload_type = state.payload_types.types.get(&request.type_tag).ok_or_else(|| {
+        EnclaveError::InvalidInput(format!("unknown type_tag: {:?}", request.type_tag))
+    })?;
+    let intent = IntentScope::from_selector(&request.intent_scope)?;
+    if !payload_type.scopes.contains(&intent) {
+        return Err(EnclaveError::InvalidInput(format!(
+            "intent_scope {:?} cannot be used to sign {}",
+            intent, request.type_tag
+        )));
+    }
+    let payload = decode_hex("payload", &request.payload)?;
+    (payload_type.check)(state, &payload)?;
+
+    let timestamp_ms = state.clock.now_millis()?;
+    let signed_bytes = intent_signing_bytes(intent, timestamp_ms, &payload);
+    let signature = state.signer.current().sign(&signed_bytes);
+    info!("Signed {} payload under {:?}", request.type_tag, intent);
+
+    Ok(Json(SignIntentResponse {
+        type_tag: request.type_tag,
+        intent,
+        timestamp_ms,
+        payload: Hex::encode(&payload),
+        signed_bytes: Hex::encode(&signed_bytes),
+        signature: Hex::encode(signature),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{signing_bytes, IntentMessage};
+    use fastcrypto::ed25519::Ed25519KeyPair;
+    use fastcrypto::traits::KeyPair;
+
+    /// Stand-in for a second Move-verified type
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct ModelCard {
+        model_id: Vec<u8>,
+        parameters: u64,
+    }
+
+    fn state() -> AppState {
+        let mut state = AppState::new(
+            Ed25519KeyPair::generate(&mut rand::thread_rng()),
+            "test-key".to_string(),
+        );
+        state
+            .payload_types
+            .register("sealtrust::ModelCard", &[IntentScope::TestPayload], |_, card: &ModelCard| {
+                if card.model_id.is_empty() {
+                    return Err(EnclaveError::InvalidInput("model_id cannot be empty".into()));
+                }
+                Ok(())
+            })
+            .unwrap();
+        state
+    }
+
+    fn request(type_tag: &str, scope: &str, payload: &[u8]) -> SignIntentRequest {
+        SignIntentRequest {
+            type_tag: type_tag.to_string(),
+            intent_scope: IntentScopeSelector::Name(scope.to_string()),
+            payload: Hex::encode(payload),
+        }
+    }
+
+    #[test]
+    fn test_registered_payload_is_signed_as_intent_message() {
+        let state = state();
+        let card = ModelCard { model_id: b"model-1".to_vec(), parameters: 7 };
+        let bytes = bcs::to_bytes(&card).unwrap();
+
+        let request = request("sealtrust::ModelCard", "test_payload", &bytes);
+        let Json(response) = sign_registered_payload(&state, request).unwrap();
+        assert_eq!(response.type_tag, "sealtrust::ModelCard");
+        assert_eq!(response.intent, IntentScope::TestPayload);
+
+        let typed = IntentMessage::new(card, response.timestamp_ms, IntentScope::TestPayload);
+        assert_eq!(Hex::decode(&response.signed_bytes).unwrap(), signing_bytes(&typed));
+        let signature = Hex::decode(&response.signature).unwrap();
+        assert!(state.signer.verify(&signing_bytes(&typed), &signature));
+    }
+
+    #[test]
+    fn test_unregistered_or_malformed_payloads_are_refused() {
+        let state = state();
+        let card = bcs::to_bytes(&ModelCard { model_id: b"m".to_vec(), parameters: 1 }).unwrap();
+        let refusal = |type_tag: &str, scope: &str, payload: &[u8]| {
+            let result = sign_registered_payload(&state, request(type_tag, scope, payload));
+            result.unwrap_err().message().to_string()
+        };
+
+        // DatasetVerification must go through verify_metadata
+        let err = refusal("DatasetVerification", "verify_metadata", &card);
+        assert_eq!(err, "unknown type_tag: \"DatasetVerification\"");
+        let err = refusal("sealtrust::ModelCard", "rejection", &card);
+        assert_eq!(err, "intent_scope Rejection cannot be used to sign sealtrust::ModelCard");
+
+        let mut trailing = card.clone();
+        trailing.push(0);
+        let err = refusal("sealtrust::ModelCard", "test_payload", &trailing);
+        assert!(err.starts_with("sealtrust::ModelCard payload is not valid BCS"));
+
+        let empty = bcs::to_bytes(&ModelCard { model_id: vec![], parameters: 1 }).unwrap();
+        let err = refusal("sealtrust::ModelCard", "test_payload", &empty);
+        assert_eq!(err, "model_id cannot be empty");
+    }
+
+    #[test]
+    fn test_scopes_cannot_be_shared_between_types() {
+        let mut state = state();
+        let ok = |_: &AppState, _: &ModelCard| -> Result<(), EnclaveError> { Ok(()) };
+        let registry = &mut state.payload_types;
+        let card = "sealtrust::ModelCard";
+        let other = "sealtrust::Other";
+        assert!(registry.register(card, &[IntentScope::TestPayload], ok).is_err());
+        assert!(registry.register(other, &[IntentScope::TestPayload], ok).is_err());
+        assert!(registry.register(other, &[], ok).is_err());
+        assert_eq!(registry.type_tags().collect::<Vec<_>>(), [card]);
+    }
+
+    #[test]
+    fn test_built_in_scopes_cannot_be_registered() {
+        let mut registry = PayloadRegistry::default();
+        let ok = |_: &AppState, _: &ModelCard| -> Result<(), EnclaveError> { Ok(()) };
+        for scope in IntentScope::ALL.iter().copied().filter(|scope| scope.is_built_in()) {
+            let err = registry.register("sealtrust::ModelCard", &[scope], ok).unwrap_err();
+            let expected = format!("intent scope {:?} is signed by a built-in endpoint", scope);
+            assert_eq!(err.to_string(), expected);
+        }
+        assert!(registry.register("sealtrust::ModelCard", &[IntentScope::TestPayload], ok).is_ok());
+    }
+}
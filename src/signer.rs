@@ -0,0 +1,62 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use fastcrypto::ed25519::{Ed25519KeyPair, Ed25519Signature};
+use fastcrypto::traits::{KeyPair, ToFromBytes, VerifyingKey};
+
+/// Key scheme the enclave signs responses with. Handlers only go through this
+/// trait, so a scheme other than Ed25519 can be plugged into `AppState`.
+pub trait Signer: Send + Sync {
+    /// Name of the signature scheme, e.g. "ed25519".
+    fn scheme(&self) -> &'static str;
+
+    /// Sign `msg`, returning the raw signature bytes.
+    fn sign(&self, msg: &[u8]) -> Vec<u8>;
+
+    /// Raw bytes of the public key, as committed to in the attestation document.
+    fn public_key_bytes(&self) -> Vec<u8>;
+
+    /// Whether `signature` is a valid signature of `msg` under this key.
+    fn verify(&self, msg: &[u8], signature: &[u8]) -> bool;
+}
+
+impl Signer for Ed25519KeyPair {
+    fn scheme(&self) -> &'static str {
+        "ed25519"
+    }
+
+    fn sign(&self, msg: &[u8]) -> Vec<u8> {
+        fastcrypto::traits::Signer::sign(self, msg).as_bytes().to_vec()
+    }
+
+    fn public_key_bytes(&self) -> Vec<u8> {
+        self.public().as_bytes().to_vec()
+    }
+
+    fn verify(&self, msg: &[u8], signature: &[u8]) -> bool {
+        Ed25519Signature::from_bytes(signature)
+            .map(|signature| self.public().verify(msg, &signature).is_ok())
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ed25519_signer_matches_fastcrypto() {
+        let kp = Ed25519KeyPair::generate(&mut rand::thread_rng());
+        let signer: &dyn Signer = &kp;
+
+        let signature = signer.sign(b"message");
+        assert_eq!(
+            signature,
+            fastcrypto::traits::Signer::sign(&kp, b"message").as_bytes()
+        );
+        assert!(signer.verify(b"message", &signature));
+        assert!(!signer.verify(b"other message", &signature));
+        assert!(!signer.verify(b"message", b"not a signature"));
+        assert_eq!(signer.public_key_bytes(), kp.public().as_bytes());
+    }
+}
@@ -0,0 +1,225 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{DatasetVerification, EnclaveError};
+use unicode_normalization::UnicodeNormalization;
+
+/// Rewrite client metadata into its canonical form before signing, so that
+/// equivalent submissions produce identical BCS bytes and signatures. This
+/// changes the signed content:
+///
+/// - `name`, `description` and `format` have leading and trailing whitespace
+///   trimmed and are normalized to Unicode NFC. Fields that are not valid
+///   UTF-8 are left as is for `check_text_fields` to reject.
+/// - `uploader` is trimmed and ASCII-lowercased, so `0xAB..` and `0xab..`
+///   sign as the same address. Invalid UTF-8 is left as is.
+/// - `dataset_id`, `walrus_blob_id` and `seal_policy_id` are opaque and never
+///   rewritten; surrounding whitespace in them is rejected rather than trimmed.
+/// - `original_hash`, `size` and `timestamp` are signed exactly as sent.
+pub fn canonicalize_metadata(
+    mut metadata: DatasetVerification,
+) -> Result<DatasetVerification, EnclaveError> {
+    for field in [&mut metadata.name, &mut metadata.description, &mut metadata.format] {
+        if let Ok(text) = std::str::from_utf8(field) {
+            *field = text.trim().nfc().collect::<String>().into_bytes();
+        }
+    }
+    if let Ok(uploader) = std::str::from_utf8(&metadata.uploader) {
+        metadata.uploader = uploader.trim().to_ascii_lowercase().into_bytes();
+    }
+
+    let identifiers: [(&str, &[u8]); 3] = [
+        ("dataset_id", &metadata.dataset_id),
+        ("walrus_blob_id", &metadata.walrus_blob_id),
+        ("seal_policy_id", &metadata.seal_policy_id),
+    ];
+    for (field, bytes) in identifiers {
+        let padded = |b: Option<&u8>| b.is_some_and(u8::is_ascii_whitespace);
+        if padded(bytes.first()) || padded(bytes.last()) {
+            return Err(EnclaveError::InvalidInput(format!(
+                "{} has leading or trailing whitespace",
+                field
+            )));
+        }
+    }
+    Ok(metadata)
+}
+
+/// Decode client-supplied hex such as an `expected_hash` or a nonce. Surrounding
+/// whitespace and an optional `0x` prefix are stripped and either letter case
+/// is accepted; anything else that is not an even number of hex digits is
+/// rejected with the reason, naming the input as `what`.
+pub fn decode_hex(what: &str, value: &str) -> Result<Vec<u8>, EnclaveError> {
+    let invalid = |reason: String| {
+        EnclaveError::InvalidInput(format!("invalid {} format: {}", what, reason))
+    };
+    let trimmed = value.trim();
+    let digits = trimmed
+        .strip_prefix("0x")
+        .or_else(|| trimmed.strip_prefix("0X"))
+        .unwrap_or(trimmed);
+    if digits.is_empty() {
+        return Err(invalid("no hex digits".to_string()));
+    }
+    if let Some((offset, c)) = digits.char_indices().find(|(_, c)| !c.is_ascii_hexdigit()) {
+        let reason = if c.is_whitespace() {
+            format!("whitespace at offset {}", offset)
+        } else {
+            format!("invalid character {:?} at offset {}", c, offset)
+        };
+        return Err(invalid(reason));
+    }
+    if digits.len() % 2 != 0 {
+        return Err(invalid(format!("odd number of hex digits ({})", digits.len())));
+    }
+    Ok(hex::decode(digits).expect("checked to be an even number of hex digits"))
+}
+
+/// Check that the human-readable fields `name`, `description` and `format`
+/// are valid UTF-8. With `reject_control_chars`, they must also not contain
+/// control characters, except for line breaks and tabs in `description`.
+/// These fields are shown by explorers and written to logs, where invalid
+/// bytes or escape sequences corrupt the output.
+pub fn check_text_fields(
+    metadata: &DatasetVerification,
+    reject_control_chars: bool,
+) -> Result<(), EnclaveError> {
+    match text_field_errors(metadata, reject_control_chars).into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+/// The first problem of each text field, in field order; see
+/// [`check_text_fields`].
+pub fn text_field_errors(
+    metadata: &DatasetVerification,
+    reject_control_chars: bool,
+) -> Vec<EnclaveError> {
+    let fields: [(&str, &[u8], &[char]); 3] = [
+        ("name", &metadata.name, &[]),
+        ("description", &metadata.description, &['\n', '\r', '\t']),
+        ("format", &metadata.format, &[]),
+    ];
+    let mut errors = Vec::new();
+    for (field, bytes, allowed) in fields {
+        let text = match std::str::from_utf8(bytes) {
+            Ok(text) => text,
+            Err(e) => {
+                errors.push(EnclaveError::InvalidInput(format!(
+                    "{} is not valid UTF-8 (invalid byte at offset {})",
+                    field,
+                    e.valid_up_to()
+                )));
+                continue;
+            }
+        };
+        if !reject_control_chars {
+            continue;
+        }
+        if let Some((offset, c)) = text
+            .char_indices()
+            .find(|(_, c)| c.is_control() && !allowed.contains(c))
+        {
+            errors.push(EnclaveError::InvalidInput(format!(
+                "{} contains control character U+{:04X} at byte offset {}",
+                field, c as u32, offset
+            )));
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DATASET_VERIFICATION_VERSION;
+
+    fn metadata(name: &[u8], description: &[u8]) -> DatasetVerification {
+        DatasetVerification {
+            version: DATASET_VERIFICATION_VERSION,
+            dataset_id: b"dataset-1".to_vec(),
+            name: name.to_vec(),
+            description: description.to_vec(),
+            format: b"CSV".to_vec(),
+            size: 1024,
+            original_hash: vec![0xAB; 32],
+            walrus_blob_id: b"blob-1".to_vec(),
+            seal_policy_id: b"policy-1".to_vec(),
+            timestamp: 1700000000000,
+            uploader: b"0xA".to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_equivalent_metadata_canonicalizes_to_identical_bcs() {
+        // "é" precomposed versus "e" + combining acute accent, padded with spaces
+        let mut composed = metadata("Café.csv".as_bytes(), b"Weather data");
+        composed.uploader = format!("0x{}", "ab".repeat(32)).into_bytes();
+        let mut decomposed = metadata("  Cafe\u{301}.csv\n".as_bytes(), b" Weather data ");
+        decomposed.format = b" CSV".to_vec();
+        decomposed.uploader = format!("0x{}", "AB".repeat(32)).into_bytes();
+        assert_ne!(bcs::to_bytes(&composed).unwrap(), bcs::to_bytes(&decomposed).unwrap());
+
+        let composed = canonicalize_metadata(composed).unwrap();
+        let decomposed = canonicalize_metadata(decomposed).unwrap();
+        assert_eq!(bcs::to_bytes(&composed).unwrap(), bcs::to_bytes(&decomposed).unwrap());
+        assert_eq!(composed.name, "Café.csv".as_bytes());
+    }
+
+    #[test]
+    fn test_canonicalization_rejects_padded_identifiers() {
+        let mut padded = metadata(b"data.csv", b"ok");
+        padded.seal_policy_id = b"policy-1 ".to_vec();
+        let err = canonicalize_metadata(padded).unwrap_err();
+        assert_eq!(err.message(), "seal_policy_id has leading or trailing whitespace");
+
+        // Invalid UTF-8 passes through untouched, for check_text_fields to report
+        let invalid = canonicalize_metadata(metadata(b" data\xff ", b"ok")).unwrap();
+        assert_eq!(invalid.name, b" data\xff ");
+    }
+
+    #[test]
+    fn test_decode_hex() {
+        assert_eq!(decode_hex("expected hash", "abCD").unwrap(), [0xab, 0xcd]);
+        assert_eq!(decode_hex("expected hash", " 0xABcd\n").unwrap(), [0xab, 0xcd]);
+        assert_eq!(decode_hex("expected hash", "0XABCD").unwrap(), [0xab, 0xcd]);
+
+        let err = decode_hex("expected hash", "abc").unwrap_err();
+        assert_eq!(err.message(), "invalid expected hash format: odd number of hex digits (3)");
+        let err = decode_hex("expected hash", "ab cd").unwrap_err();
+        assert_eq!(err.message(), "invalid expected hash format: whitespace at offset 2");
+        let err = decode_hex("nonce", "0xzz").unwrap_err();
+        assert_eq!(err.message(), "invalid nonce format: invalid character 'z' at offset 0");
+        assert!(decode_hex("nonce", "0x").is_err());
+        assert!(decode_hex("nonce", "0x0x00").is_err());
+    }
+
+    #[test]
+    fn test_valid_text_is_accepted() {
+        let valid = metadata("Données météo 🌦".as_bytes(), b"Line one\nLine two\tcolumn");
+        assert!(check_text_fields(&valid, true).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_utf8_is_rejected() {
+        let err = check_text_fields(&metadata(b"data\xff.csv", b"ok"), false).unwrap_err();
+        assert_eq!(err.message(), "name is not valid UTF-8 (invalid byte at offset 4)");
+    }
+
+    #[test]
+    fn test_control_characters_are_rejected_when_strict() {
+        let embedded_null = metadata(b"data\0.csv", b"ok");
+        let err = check_text_fields(&embedded_null, true).unwrap_err();
+        assert!(err.message().starts_with("name contains control character U+0000"));
+        assert!(check_text_fields(&embedded_null, false).is_ok());
+
+        // Newlines are only allowed in the description
+        assert!(check_text_fields(&metadata(b"data\n.csv", b"ok"), true).is_err());
+        let escape = metadata(b"data.csv", b"\x1b[31mred");
+        assert!(check_text_fields(&escape, true)
+            .unwrap_err()
+            .message()
+            .starts_with("description"));
+    }
+}
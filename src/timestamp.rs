@@ -0,0 +1,142 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Trusted timestamps for arbitrary digests.
+//!
+//! `/timestamp` signs `IntentMessage<TimestampToken>` under
+//! `IntentScope::Timestamp`, attesting that the enclave saw `digest` no later
+//! than `wall_clock_ms`. The enclave never learns what the digest commits to,
+//! so the token says nothing about any dataset; verifiers must check the
+//! scope so a token can never pass as a dataset verification.
+
+use crate::common::{
+    to_signed_response, IntentMessage, IntentScope, ProcessedDataResponse, SigningQuery,
+};
+use crate::hashing::HashAlgorithm;
+use crate::text::decode_hex;
+use crate::{AppState, EnclaveError};
+use axum::extract::{Query, State};
+use axum::Json;
+use fastcrypto::encoding::{Encoding, Hex};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::info;
+
+/// Inner type for IntentMessage<T> - MUST match Move contract exactly
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TimestampToken {
+    pub digest: Vec<u8>,        // Client digest, as sent
+    pub wall_clock_ms: u64,     // Enclave time, ms since the Unix epoch
+    pub monotonic_ms: u64,      // Enclave monotonic clock, ms since it started
+}
+
+/// Request to timestamp a digest.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TimestampRequest {
+    /// Hex encoded digest, of a length produced by a supported hash algorithm
+    pub digest: String,
+}
+
+/// Endpoint signing `digest` together with the current enclave time.
+pub async fn timestamp(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SigningQuery>,
+    Json(request): Json<TimestampRequest>,
+) -> Result<Json<ProcessedDataResponse<IntentMessage<TimestampToken>>>, EnclaveError> {
+    let result = sign_timestamp(&state, request)
+        .and_then(|Json(response)| query.apply(&state.signer, response).map(Json));
+    state.metrics.record("timestamp", &result);
+    result
+}
+
+fn sign_timestamp(
+    state: &AppState,
+    request: TimestampRequest,
+) -> Result<Json<ProcessedDataResponse<IntentMessage<TimestampToken>>>, EnclaveError> {
+    let digest = decode_hex("digest", &request.digest)?;
+    check_digest_len(&digest)?;
+
+    let wall_clock_ms = state.clock.now_millis()?;
+    let token = TimestampToken {
+        digest,
+        wall_clock_ms,
+        monotonic_ms: state.started_at.elapsed().as_millis() as u64,
+    };
+    info!("Timestamping digest {} at {}", Hex::encode(&token.digest), wall_clock_ms);
+
+    Ok(Json(to_signed_response(
+        state.signer.current().as_ref(),
+        token,
+        wall_clock_ms,
+        IntentScope::Timestamp,
+    )))
+}
+
+/// Accept only digest lengths of a supported hash algorithm, so the endpoint
+/// cannot be used to sign arbitrary short or long messages.
+fn check_digest_len(digest: &[u8]) -> Result<(), EnclaveError> {
+    if HashAlgorithm::ALL.iter().any(|algorithm| algorithm.digest_len() == digest.len()) {
+        return Ok(());
+    }
+    let mut lengths: Vec<usize> = HashAlgorithm::ALL.iter().map(|a| a.digest_len()).collect();
+    lengths.sort_unstable();
+    lengths.dedup();
+    let lengths: Vec<String> = lengths.iter().map(usize::to_string).collect();
+    Err(EnclaveError::InvalidInput(format!(
+        "digest has wrong length: expected {} bytes, got {}",
+        lengths.join(" or "),
+        digest.len()
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::common::signing_bytes;
+    use fastcrypto::ed25519::Ed25519KeyPair;
+    use fastcrypto::traits::KeyPair;
+
+    const NOW_MS: u64 = 1_700_000_000_000;
+
+    fn test_state() -> AppState {
+        let mut state = AppState::new(
+            Ed25519KeyPair::generate(&mut rand::thread_rng()),
+            "test-key".to_string(),
+        );
+        state.clock = Box::new(ManualClock::new(NOW_MS));
+        state
+    }
+
+    #[test]
+    fn test_digest_is_signed_with_enclave_time() {
+        let state = test_state();
+        let request = TimestampRequest { digest: format!("0x{}", "ab".repeat(32)) };
+        let Json(response) = sign_timestamp(&state, request).unwrap();
+
+        let message = &response.response;
+        assert_eq!(message.intent, IntentScope::Timestamp);
+        assert_eq!(message.data.digest, vec![0xAB; 32]);
+        assert_eq!(message.data.wall_clock_ms, NOW_MS);
+        assert_eq!(message.timestamp_ms, NOW_MS);
+
+        let signature = Hex::decode(&response.signature).unwrap();
+        assert!(state.signer.verify(&signing_bytes(message), &signature));
+    }
+
+    #[test]
+    fn test_invalid_digests_are_rejected() {
+        let state = test_state();
+        let sign = |digest: &str| {
+            sign_timestamp(&state, TimestampRequest { digest: digest.to_string() }).unwrap_err()
+        };
+
+        assert_eq!(sign("").message(), "invalid digest format: no hex digits");
+        assert_eq!(
+            sign(&"ab".repeat(20)).message(),
+            "digest has wrong length: expected 32 or 64 bytes, got 20"
+        );
+        assert!(sign(&"zz".repeat(32)).message().starts_with("invalid digest format"));
+        assert!(sign_timestamp(&state, TimestampRequest { digest: "cd".repeat(64) }).is_ok());
+    }
+}
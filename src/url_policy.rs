@@ -0,0 +1,373 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::EnclaveError;
+use std::fmt;
+use std::net::IpAddr;
+use url::{Host, Url};
+
+/// Which URLs the enclave may fetch, to prevent SSRF against internal services
+/// such as the instance metadata endpoint 169.254.169.254.
+#[derive(Debug, Clone, Default)]
+pub struct UrlPolicy {
+    /// Permitted hosts (`example.com`, or `*.example.com` for any subdomain)
+    /// or URL prefixes (`https://example.com/datasets/`). Empty allows any
+    /// public host. Internal targets are only reachable when listed here.
+    pub allowlist: Vec<String>,
+    /// Internal ranges a hostname may still resolve to. Checked on the
+    /// addresses actually connected to, so a public name rebound to an
+    /// internal address is refused. Empty permits none.
+    pub permitted_ip_ranges: Vec<IpRange>,
+    /// Host of the fetch proxy, which datasets may not be fetched from
+    pub proxy_host: Option<String>,
+}
+
+impl UrlPolicy {
+    /// Parse `url` and check it against the policy before any network call.
+    pub fn check(&self, url: &str) -> Result<Url, EnclaveError> {
+        let parsed = Url::parse(url)
+            .map_err(|e| EnclaveError::InvalidInput(format!("invalid url {}: {}", url, e)))?;
+        self.check_url(&parsed)?;
+        Ok(parsed)
+    }
+
+    /// Check an already parsed URL against the policy.
+    pub fn check_url(&self, url: &Url) -> Result<(), EnclaveError> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(not_allowed(url, "only http and https are permitted"));
+        }
+        let Some(host) = url.host() else {
+            return Err(not_allowed(url, "missing host"));
+        };
+        if let (Some(proxy), Some(name)) = (&self.proxy_host, url.host_str()) {
+            if name.eq_ignore_ascii_case(proxy) {
+                return Err(not_allowed(url, "host is the fetch proxy"));
+            }
+        }
+
+        let explicitly_allowed = self.allowlist.iter().any(|entry| entry_matches(entry, url));
+        if !self.allowlist.is_empty() && !explicitly_allowed {
+            return Err(not_allowed(url, "host is not in the fetch allowlist"));
+        }
+        if is_internal_host(&host) && !explicitly_allowed {
+            return Err(not_allowed(url, "internal addresses are blocked"));
+        }
+        Ok(())
+    }
+
+    /// Check a redirect from `from` to `to`: the target must pass the policy
+    /// and must not downgrade https to http.
+    pub fn check_redirect(&self, from: &Url, to: &Url) -> Result<(), EnclaveError> {
+        if from.scheme() == "https" && to.scheme() == "http" {
+            return Err(EnclaveError::Forbidden(format!(
+                "redirect not allowed: {} (downgrades https to http)",
+                to
+            )));
+        }
+        self.check_url(to)
+            .map_err(|e| EnclaveError::Forbidden(format!("redirect blocked: {}", e.message())))
+    }
+
+    /// Check an address `host` resolved to, before connecting to it.
+    pub fn check_resolved(&self, host: &str, ip: IpAddr) -> Result<(), BlockedAddress> {
+        let permitted = self.permitted_ip_ranges.iter().any(|range| range.contains(&ip));
+        if is_internal_ip(&ip) && !permitted {
+            return Err(BlockedAddress { host: host.to_string(), ip });
+        }
+        Ok(())
+    }
+}
+
+/// A hostname resolved to an address the policy forbids.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockedAddress {
+    pub host: String,
+    pub ip: IpAddr,
+}
+
+impl fmt::Display for BlockedAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} resolved to blocked address {}", self.host, self.ip)
+    }
+}
+
+impl std::error::Error for BlockedAddress {}
+
+impl From<BlockedAddress> for EnclaveError {
+    fn from(blocked: BlockedAddress) -> Self {
+        EnclaveError::Forbidden(format!("url not allowed: {}", blocked))
+    }
+}
+
+/// A CIDR block such as `10.0.0.0/8` or `fd00::/8`; a bare address is a
+/// block of one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpRange {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpRange {
+    pub fn parse(range: &str) -> Result<Self, EnclaveError> {
+        let invalid = || EnclaveError::InvalidInput(format!("invalid IP range: {}", range));
+        let (address, prefix_len) = match range.trim().split_once('/') {
+            Some((address, prefix_len)) => (address, Some(prefix_len)),
+            None => (range.trim(), None),
+        };
+        let network: IpAddr = address.parse().map_err(|_| invalid())?;
+        let max_len = if network.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix_len {
+            Some(prefix_len) => prefix_len.parse().map_err(|_| invalid())?,
+            None => max_len,
+        };
+        if prefix_len > max_len {
+            return Err(invalid());
+        }
+        Ok(Self { network, prefix_len })
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(*ip, IpAddr::V4),
+            IpAddr::V4(_) => *ip,
+        };
+        match (self.network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix_len as u32).unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix_len as u32).unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+fn not_allowed(url: &Url, reason: &str) -> EnclaveError {
+    EnclaveError::Forbidden(format!("url not allowed: {} ({})", url, reason))
+}
+
+fn entry_matches(entry: &str, url: &Url) -> bool {
+    if entry.contains("://") {
+        return prefix_matches(entry, url);
+    }
+    let Some(host) = url.host_str() else {
+        return false;
+    };
+    match entry.strip_prefix("*.") {
+        Some(domain) => host
+            .strip_suffix(domain)
+            .is_some_and(|sub| sub.ends_with('.')),
+        None => host.eq_ignore_ascii_case(entry),
+    }
+}
+
+/// Whether `url` is under the URL prefix `entry`: same scheme, host and
+/// port, and a path starting with the entry's path segments. A plain string
+/// prefix would let `https://host` match `https://host.evil.com`. An entry
+/// that does not parse matches nothing.
+fn prefix_matches(entry: &str, url: &Url) -> bool {
+    let Ok(prefix) = Url::parse(entry) else {
+        return false;
+    };
+    if prefix.scheme() != url.scheme()
+        || prefix.host() != url.host()
+        || prefix.port_or_known_default() != url.port_or_known_default()
+    {
+        return false;
+    }
+    let prefix_path = prefix.path().trim_end_matches('/');
+    url.path()
+        .strip_prefix(prefix_path)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// Loopback, link-local, private (RFC1918 / unique local) and unspecified
+/// hosts, including `localhost`.
+pub fn is_internal_host(host: &Host<&str>) -> bool {
+    match host {
+        Host::Domain(domain) => {
+            let domain = domain.to_ascii_lowercase();
+            domain == "localhost" || domain.ends_with(".localhost")
+        }
+        Host::Ipv4(ip) => is_internal_ip(&IpAddr::V4(*ip)),
+        Host::Ipv6(ip) => is_internal_ip(&IpAddr::V6(*ip)),
+    }
+}
+
+/// Whether `ip` belongs to a range that must never be fetched by default.
+pub fn is_internal_ip(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+        }
+        IpAddr::V6(ip) => {
+            if let Some(mapped) = ip.to_ipv4_mapped() {
+                return is_internal_ip(&IpAddr::V4(mapped));
+            }
+            let first = ip.segments()[0];
+            ip.is_loopback()
+                || ip.is_unspecified()
+                || (first & 0xfe00) == 0xfc00 // unique local fc00::/7
+                || (first & 0xffc0) == 0xfe80 // link-local fe80::/10
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_allowlist_allows_public_hosts_only() {
+        let policy = UrlPolicy::default();
+        assert!(policy.check("https://example.com/data.csv").is_ok());
+
+        for url in [
+            "http://169.254.169.254/latest/meta-data/",
+            "http://127.0.0.1:3000/health",
+            "http://0x7f000001/",
+            "http://localhost/data.csv",
+            "http://10.0.0.5/data.csv",
+            "http://192.168.1.1/data.csv",
+            "http://172.16.0.1/data.csv",
+            "http://[::1]/data.csv",
+            "http://[fd00::1]/data.csv",
+            "http://[::ffff:127.0.0.1]/data.csv",
+        ] {
+            assert!(policy.check(url).is_err(), "{} should be blocked", url);
+        }
+    }
+
+    #[test]
+    fn test_allowlist_entries() {
+        let policy = UrlPolicy {
+            allowlist: vec![
+                "datasets.example.com".to_string(),
+                "*.walrus.space".to_string(),
+                "https://cdn.example.org/public/".to_string(),
+                "127.0.0.1".to_string(),
+            ],
+            ..Default::default()
+        };
+
+        assert!(policy.check("https://datasets.example.com/a.csv").is_ok());
+        assert!(policy.check("https://aggregator.walrus-testnet.walrus.space/v1/blobs/x").is_ok());
+        assert!(policy.check("https://cdn.example.org/public/a.csv").is_ok());
+        // Explicitly listed internal host
+        assert!(policy.check("http://127.0.0.1:8080/a.csv").is_ok());
+
+        assert!(policy.check("https://other.example.com/a.csv").is_err());
+        assert!(policy.check("https://evilwalrus.space/a.csv").is_err());
+        assert!(policy.check("https://cdn.example.org/private/a.csv").is_err());
+        assert!(policy.check("http://localhost/a.csv").is_err());
+    }
+
+    #[test]
+    fn test_url_prefix_entries_match_host_and_path_segments() {
+        let policy = UrlPolicy {
+            allowlist: vec![
+                "https://data.example.com".to_string(),
+                "https://cdn.example.org/public".to_string(),
+            ],
+            ..Default::default()
+        };
+
+        for url in [
+            "https://data.example.com/a.csv",
+            "https://DATA.example.com:443/a.csv",
+            "https://cdn.example.org/public",
+            "https://cdn.example.org/public/nested/a.csv",
+        ] {
+            assert!(policy.check(url).is_ok(), "{} should be allowed", url);
+        }
+        for url in [
+            "https://data.example.com.evil.com/a.csv",
+            "https://data.example.com@evil.com/a.csv",
+            "https://data.example.com:8443/a.csv",
+            "http://data.example.com/a.csv",
+            "https://cdn.example.org/publicity/a.csv",
+        ] {
+            assert!(policy.check(url).is_err(), "{} should be blocked", url);
+        }
+    }
+
+    #[test]
+    fn test_redirect_checks() {
+        let policy = UrlPolicy::default();
+        let https = Url::parse("https://example.com/a.csv").unwrap();
+        let http = Url::parse("http://example.com/a.csv").unwrap();
+        let internal = Url::parse("http://169.254.169.254/latest/meta-data/").unwrap();
+
+        assert!(policy.check_redirect(&http, &https).is_ok());
+        assert!(policy.check_redirect(&https, &https).is_ok());
+
+        let err = policy.check_redirect(&https, &http).unwrap_err();
+        assert!(err.message().contains("http://example.com/a.csv"));
+        let err = policy.check_redirect(&http, &internal).unwrap_err();
+        assert!(err.message().contains("169.254.169.254"));
+    }
+
+    #[test]
+    fn test_resolved_addresses() {
+        let mut policy = UrlPolicy::default();
+        assert!(policy.check_resolved("example.com", "93.184.216.34".parse().unwrap()).is_ok());
+
+        for ip in ["127.0.0.1", "169.254.169.254", "10.1.2.3", "::1", "::ffff:192.168.0.1"] {
+            let err = policy.check_resolved("rebound.example", ip.parse().unwrap()).unwrap_err();
+            let expected = format!("rebound.example resolved to blocked address {}", ip);
+            assert_eq!(err.to_string(), expected);
+        }
+
+        policy.permitted_ip_ranges = vec![IpRange::parse("10.0.0.0/8").unwrap()];
+        assert!(policy.check_resolved("minio.internal", "10.1.2.3".parse().unwrap()).is_ok());
+        assert!(policy.check_resolved("minio.internal", "192.168.0.1".parse().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_proxy_host_is_refused_even_when_allowlisted() {
+        let policy = UrlPolicy {
+            allowlist: vec!["proxy.internal".to_string(), "data.example.com".to_string()],
+            proxy_host: Some("proxy.internal".to_string()),
+            ..Default::default()
+        };
+        assert!(policy.check("https://data.example.com/a.csv").is_ok());
+        let err = policy.check("http://PROXY.internal:3128/a.csv").unwrap_err();
+        assert!(err.message().ends_with("(host is the fetch proxy)"));
+    }
+
+    #[test]
+    fn test_ip_ranges() {
+        let range = IpRange::parse("172.16.0.0/12").unwrap();
+        assert!(range.contains(&"172.31.255.255".parse().unwrap()));
+        assert!(range.contains(&"::ffff:172.16.0.1".parse().unwrap()));
+        assert!(!range.contains(&"172.32.0.0".parse().unwrap()));
+
+        let range = IpRange::parse("fd00::/8").unwrap();
+        assert!(range.contains(&"fd12::1".parse().unwrap()));
+        assert!(!range.contains(&"fe80::1".parse().unwrap()));
+
+        let single = IpRange::parse("10.0.0.7").unwrap();
+        assert!(single.contains(&"10.0.0.7".parse().unwrap()));
+        assert!(!single.contains(&"10.0.0.8".parse().unwrap()));
+        assert!(IpRange::parse("0.0.0.0/0").unwrap().contains(&"8.8.8.8".parse().unwrap()));
+
+        for invalid in ["10.0.0.0/33", "fd00::/129", "not-an-ip/8", "10.0.0.0/x"] {
+            assert!(IpRange::parse(invalid).is_err(), "{} should be rejected", invalid);
+        }
+    }
+
+    #[test]
+    fn test_rejects_non_http_and_malformed_urls() {
+        let policy = UrlPolicy::default();
+        assert!(policy.check("file:///etc/passwd").is_err());
+        assert!(policy.check("ftp://example.com/data.csv").is_err());
+        assert!(matches!(policy.check("not a url"), Err(EnclaveError::InvalidInput(_))));
+    }
+}
@@ -0,0 +1,111 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::EnclaveError;
+
+/// Length of a decoded Walrus blob ID in bytes.
+pub const WALRUS_BLOB_ID_LENGTH: usize = 32;
+
+/// Check that `blob_id` is a Walrus blob ID: the unpadded base64url encoding
+/// of exactly `WALRUS_BLOB_ID_LENGTH` bytes.
+pub fn validate_blob_id(blob_id: &[u8]) -> Result<(), EnclaveError> {
+    match decode_base64url(blob_id) {
+        Some(decoded) if decoded.len() == WALRUS_BLOB_ID_LENGTH => Ok(()),
+        _ => Err(EnclaveError::InvalidInput(format!(
+            "walrus_blob_id must be an unpadded base64url encoding of {} bytes, got {:?}",
+            WALRUS_BLOB_ID_LENGTH,
+            String::from_utf8_lossy(blob_id)
+        ))),
+    }
+}
+
+/// Decode unpadded base64url, rejecting non-canonical encodings whose unused
+/// trailing bits are set.
+fn decode_base64url(encoded: &[u8]) -> Option<Vec<u8>> {
+    fn sextet(c: u8) -> Option<u32> {
+        match c {
+            b'A'..=b'Z' => Some(u32::from(c - b'A')),
+            b'a'..=b'z' => Some(u32::from(c - b'a') + 26),
+            b'0'..=b'9' => Some(u32::from(c - b'0') + 52),
+            b'-' => Some(62),
+            b'_' => Some(63),
+            _ => None,
+        }
+    }
+
+    if encoded.len() % 4 == 1 {
+        return None;
+    }
+    let mut decoded = Vec::with_capacity(encoded.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for &c in encoded {
+        buffer = (buffer << 6) | sextet(c)?;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    (buffer == 0).then_some(decoded)
+}
+
+/// Build the aggregator URL serving the content of `blob_id`.
+/// The blob ID must be non-empty and only contain base64url characters so it
+/// cannot alter the request path.
+pub fn blob_url(aggregator_url: &str, blob_id: &str) -> Result<String, EnclaveError> {
+    if blob_id.is_empty()
+        || !blob_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(EnclaveError::InvalidInput(format!(
+            "walrus_blob_id is not a valid blob ID: {}",
+            blob_id
+        )));
+    }
+    if !aggregator_url.starts_with("https://") && !aggregator_url.starts_with("http://") {
+        return Err(EnclaveError::InvalidInput(format!(
+            "aggregator_url must be an http(s) URL: {}",
+            aggregator_url
+        )));
+    }
+    Ok(format!(
+        "{}/v1/blobs/{}",
+        aggregator_url.trim_end_matches('/'),
+        blob_id
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_blob_id() {
+        // 32 bytes of 0xff, then 32 zero bytes
+        assert!(validate_blob_id(format!("{}8", "_".repeat(42)).as_bytes()).is_ok());
+        assert!(validate_blob_id("A".repeat(43).as_bytes()).is_ok());
+
+        // Fixture-style IDs, wrong lengths, padding and non-canonical trailing bits
+        assert!(validate_blob_id(b"blob-123").is_err());
+        assert!(validate_blob_id("A".repeat(42).as_bytes()).is_err());
+        assert!(validate_blob_id(format!("{}=", "A".repeat(43)).as_bytes()).is_err());
+        assert!(validate_blob_id(format!("{}B", "A".repeat(42)).as_bytes()).is_err());
+
+        let err = validate_blob_id(b"blob-123").unwrap_err();
+        assert!(err.message().contains("walrus_blob_id"));
+    }
+
+    #[test]
+    fn test_blob_url() {
+        assert_eq!(
+            blob_url("https://aggregator.walrus-testnet.walrus.space/", "abc_DEF-123").unwrap(),
+            "https://aggregator.walrus-testnet.walrus.space/v1/blobs/abc_DEF-123"
+        );
+        assert!(blob_url("https://aggregator.example", "../../etc").is_err());
+        assert!(blob_url("https://aggregator.example", "").is_err());
+        assert!(blob_url("file:///etc", "abc").is_err());
+    }
+}
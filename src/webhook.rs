@@ -0,0 +1,252 @@
+// Copyright (c), Mysten Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Optional notification of successful verifications.
+//!
+//! When `WEBHOOK_URL` is set, every signed `verify_metadata` / `process_data`
+//! response is POSTed there from a background task, retried on transient
+//! failures. Delivery never delays or alters the client response. With
+//! `WEBHOOK_SECRET`, the body is authenticated by an HMAC-SHA256 in the
+//! `X-Webhook-Signature` header.
+
+use crate::config;
+use crate::fetch::RetryPolicy;
+use crate::{AppState, EnclaveError};
+use axum::Json;
+use fastcrypto::encoding::{Encoding, Hex};
+use hmac::{Hmac, Mac};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Client;
+use serde::Serialize;
+use sha2::Sha256;
+use std::fmt;
+use std::time::Duration;
+use tracing::{info, warn};
+use url::Url;
+
+/// Header carrying `sha256=<hex HMAC-SHA256 of the body>`, when a secret is set.
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "x-webhook-signature";
+
+/// Header naming the endpoint whose response is delivered, e.g. `verify_metadata`.
+pub const WEBHOOK_EVENT_HEADER: &str = "x-webhook-event";
+
+/// Default timeout of one delivery attempt.
+pub const DEFAULT_WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where and how successful verifications are delivered.
+#[derive(Clone)]
+pub struct Webhook {
+    pub url: Url,
+    secret: Option<Vec<u8>>,
+    /// Timeout of each delivery attempt
+    pub timeout: Duration,
+    /// Retries after a connection error or a 5xx response
+    pub retry: RetryPolicy,
+}
+
+impl fmt::Debug for Webhook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Webhook")
+            .field("url", &self.url.as_str())
+            .field("signed", &self.secret.is_some())
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+impl Webhook {
+    pub fn new(url: &str, secret: Option<String>) -> anyhow::Result<Self> {
+        let url = Url::parse(url).map_err(|e| anyhow::anyhow!("invalid WEBHOOK_URL: {}", e))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            anyhow::bail!("WEBHOOK_URL must be an http(s) URL, got {}", url.scheme());
+        }
+        Ok(Self {
+            url,
+            secret: secret.map(String::into_bytes),
+            timeout: DEFAULT_WEBHOOK_TIMEOUT,
+            retry: RetryPolicy::default(),
+        })
+    }
+
+    /// Read `WEBHOOK_URL`, `WEBHOOK_SECRET` and `WEBHOOK_TIMEOUT_SECS`.
+    /// `None` when no URL is configured.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    /// Like `from_env`, reading variables through `var`.
+    pub fn from_vars(var: impl Fn(&str) -> Option<String>) -> anyhow::Result<Option<Self>> {
+        let Some(url) = var("WEBHOOK_URL").filter(|url| !url.trim().is_empty()) else {
+            return Ok(None);
+        };
+        let secret = var("WEBHOOK_SECRET").filter(|secret| !secret.is_empty());
+        let mut webhook = Self::new(url.trim(), secret)?;
+        if let Some(secs) = var("WEBHOOK_TIMEOUT_SECS") {
+            webhook.timeout = config::secs("WEBHOOK_TIMEOUT_SECS", &secs)?;
+        }
+        Ok(Some(webhook))
+    }
+
+    /// `sha256=<hex>` HMAC of `body` under the shared secret, if one is set.
+    pub fn signature(&self, body: &[u8]) -> Option<String> {
+        let secret = self.secret.as_ref()?;
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts any key length");
+        mac.update(body);
+        Some(format!("sha256={}", Hex::encode(mac.finalize().into_bytes())))
+    }
+
+    /// Deliver `payload` in the background. The returned handle is only
+    /// useful to tests; dropping it does not cancel delivery.
+    pub fn send<T: Serialize>(
+        &self,
+        client: &Client,
+        event: &'static str,
+        payload: &T,
+    ) -> Option<tokio::task::JoinHandle<()>> {
+        let body = match serde_json::to_vec(payload) {
+            Ok(body) => body,
+            Err(e) => {
+                warn!("Webhook {} not sent, response did not serialize: {}", event, e);
+                return None;
+            }
+        };
+        let (webhook, client) = (self.clone(), client.clone());
+        Some(tokio::spawn(async move {
+            match webhook.deliver(&client, event, body).await {
+                Ok(attempts) => info!("Webhook {} delivered after {} attempt(s)", event, attempts),
+                Err(e) => warn!("Webhook {} not delivered to {}: {}", event, webhook.url, e),
+            }
+        }))
+    }
+
+    /// POST `body`, retrying connection errors and 5xx responses. Returns the
+    /// number of attempts made.
+    async fn deliver(&self, client: &Client, event: &str, body: Vec<u8>) -> Result<u32, String> {
+        let signature = self.signature(&body);
+        let mut attempt = 1;
+        loop {
+            let mut request = client
+                .post(self.url.clone())
+                .timeout(self.timeout)
+                .header(CONTENT_TYPE, "application/json")
+                .header(WEBHOOK_EVENT_HEADER, event)
+                .body(body.clone());
+            if let Some(signature) = &signature {
+                request = request.header(WEBHOOK_SIGNATURE_HEADER, signature);
+            }
+            let error = match request.send().await {
+                Ok(response) if response.status().is_success() => return Ok(attempt),
+                Ok(response) if !response.status().is_server_error() => {
+                    return Err(format!("rejected with status {}", response.status()));
+                }
+                Ok(response) => format!("status {}", response.status()),
+                Err(e) => e.to_string(),
+            };
+            if attempt >= self.retry.max_attempts {
+                return Err(format!("{} after {} attempt(s)", error, attempt));
+            }
+            tokio::time::sleep(self.retry.backoff(attempt)).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// Notify the configured webhook, if any, of a successful `event` response.
+pub fn notify<T: Serialize>(
+    state: &AppState,
+    event: &'static str,
+    result: &Result<Json<T>, EnclaveError>,
+) {
+    if let (Some(webhook), Ok(Json(response))) = (&state.webhook, result) {
+        webhook.send(&state.webhook_client, event, response);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::State;
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::post;
+    use axum::Router;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::sync::mpsc;
+
+    type Received = (Arc<AtomicUsize>, mpsc::UnboundedSender<(HeaderMap, String)>);
+
+    /// Record every delivery, failing the first with a 503 so it is retried.
+    async fn flaky_receiver(
+        State((attempts, tx)): State<Received>,
+        headers: HeaderMap,
+        body: String,
+    ) -> StatusCode {
+        tx.send((headers, body)).unwrap();
+        if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+            StatusCode::SERVICE_UNAVAILABLE
+        } else {
+            StatusCode::NO_CONTENT
+        }
+    }
+
+    #[test]
+    fn test_from_vars() {
+        assert!(Webhook::from_vars(|_| None).unwrap().is_none());
+
+        let vars = |name: &str| match name {
+            "WEBHOOK_URL" => Some("https://indexer.example/hooks/enclave".to_string()),
+            "WEBHOOK_SECRET" => Some("shh".to_string()),
+            "WEBHOOK_TIMEOUT_SECS" => Some("3".to_string()),
+            _ => None,
+        };
+        let webhook = Webhook::from_vars(vars).unwrap().unwrap();
+        assert_eq!(webhook.timeout, Duration::from_secs(3));
+        assert!(!format!("{:?}", webhook).contains("shh"));
+
+        let file = |name: &str| (name == "WEBHOOK_URL").then(|| "file:///tmp/hook".to_string());
+        assert!(Webhook::from_vars(file).is_err());
+    }
+
+    #[test]
+    fn test_signature() {
+        let unsigned = Webhook::new("https://indexer.example", None).unwrap();
+        assert_eq!(unsigned.signature(b"{}"), None);
+
+        // RFC 4231 test case 2
+        let signed = Webhook::new("https://indexer.example", Some("Jefe".to_string())).unwrap();
+        assert_eq!(
+            signed.signature(b"what do ya want for nothing?").unwrap(),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delivery_is_signed_and_retried() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let app = Router::new()
+            .route("/hook", post(flaky_receiver))
+            .with_state((Arc::new(AtomicUsize::new(0)), tx));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let mut webhook = Webhook::new(&url, Some("secret".to_string())).unwrap();
+        webhook.retry.base_delay = Duration::from_millis(1);
+        let payload = serde_json::json!({ "signature": "abcd" });
+        let delivery = webhook.send(&Client::new(), "verify_metadata", &payload).unwrap();
+        delivery.await.unwrap();
+
+        let mut received = Vec::new();
+        while let Ok(request) = rx.try_recv() {
+            received.push(request);
+        }
+        assert_eq!(received.len(), 2);
+        let (headers, body) = &received[1];
+        assert_eq!(body, r#"{"signature":"abcd"}"#);
+        assert_eq!(headers[WEBHOOK_EVENT_HEADER], "verify_metadata");
+        assert_eq!(
+            headers[WEBHOOK_SIGNATURE_HEADER].to_str().unwrap(),
+            webhook.signature(body.as_bytes()).unwrap()
+        );
+    }
+}